@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::error::IoContext;
+use crate::{file_extension, Config, OrganizeError};
+
+// ── Archive peeking ──────────────────────────
+// Opt-in (--peek-archives): a .zip or .tar is routed by what it mostly
+// contains, e.g. a zip of photos goes to Images/Archives/. Only the entry
+// names are read — nothing is extracted — and at most ARCHIVE_PEEK_ENTRIES
+// of them.
+
+// Most entries looked at per archive
+pub const ARCHIVE_PEEK_ENTRIES: usize = 200;
+
+// The category most of an archive's entries belong to, or None when the
+// archive can't be read, isn't a kind we peek into, or nothing inside has a
+// category. Ties go to the alphabetically first category.
+pub fn dominant_category(path: &Path, config: &Config) -> Option<String> {
+    let names = archive_entry_names(path)?;
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in &names {
+        if let Some(category) = file_extension(Path::new(name)).and_then(|e| config.categorize(&e)) {
+            *counts.entry(category).or_default() += 1;
+        }
+    }
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(category, _)| category.to_string())
+}
+
+// File names inside a zip, tar, tar.gz or tgz (directories left out)
+fn archive_entry_names(path: &Path) -> Option<Vec<String>> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let open = || fs::File::open(path).ok();
+    if name.ends_with(".zip") {
+        let zip = zip::ZipArchive::new(open()?).ok()?;
+        Some(zip.file_names().filter(|n| !n.ends_with('/')).take(ARCHIVE_PEEK_ENTRIES).map(String::from).collect())
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar_entry_names(flate2::read::GzDecoder::new(open()?))
+    } else if name.ends_with(".tar") {
+        tar_entry_names(open()?)
+    } else {
+        None
+    }
+}
+
+fn tar_entry_names(reader: impl Read) -> Option<Vec<String>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut names = Vec::new();
+    for entry in archive.entries().ok()?.take(ARCHIVE_PEEK_ENTRIES) {
+        let entry = entry.ok()?;
+        if entry.header().entry_type().is_dir() { continue; }
+        names.push(entry.path().ok()?.to_string_lossy().into_owned());
+    }
+    Some(names)
+}
+
+// ── Archiving old files ──────────────────────
+// Opt-in (--archive-older-than): old files are packed into one zip per
+// category and year (Archive/Documents_2023.zip) instead of being moved
+// loose. Sources are only removed once the archive holding them has been
+// written and read back.
+
+// Folder the archives go in, next to the category folders
+pub const ARCHIVE_ROOT: &str = "Archive";
+
+// Zip for a category's files from one year: ("Other/psd", 2023) -> Other_psd_2023.zip
+pub fn archive_name(category: &str, year: i32) -> String {
+    format!("{}_{year}.zip", category.replace(['/', '\\'], "_"))
+}
+
+// Add `files` (source path, entry name) to the zip at `zip_path`, creating
+// it if needed. The zip is rebuilt in a temporary file next to it, checked
+// (every new entry read back against its CRC and size) and only then put in
+// place, so a failure leaves the old archive as it was. Entry names already
+// in the zip get " (2)", " (3)"… Returns the entry name used for each file.
+pub fn append_to_zip(zip_path: &Path, files: &[(PathBuf, String)]) -> Result<Vec<String>, OrganizeError> {
+    let tmp = zip_path.with_extension("zip.part");
+    let result = write_zip(zip_path, &tmp, files).and_then(|names| {
+        check_zip(&tmp, files, &names)?;
+        fs::rename(&tmp, zip_path).at(zip_path)?;
+        Ok(names)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_zip(zip_path: &Path, tmp: &Path, files: &[(PathBuf, String)]) -> Result<Vec<String>, OrganizeError> {
+    let zip_err = |e: zip::result::ZipError| OrganizeError::Io { path: zip_path.to_path_buf(), source: std::io::Error::other(e) };
+    if let Some(dir) = zip_path.parent() { fs::create_dir_all(dir).at(dir)?; }
+
+    let mut writer = if zip_path.exists() {
+        fs::copy(zip_path, tmp).at(tmp)?;
+        let file = fs::OpenOptions::new().read(true).write(true).open(tmp).at(tmp)?;
+        zip::ZipWriter::new_append(file).map_err(zip_err)?
+    } else {
+        zip::ZipWriter::new(fs::File::create(tmp).at(tmp)?)
+    };
+    let mut taken: HashSet<String> = HashSet::new();
+    if zip_path.exists() {
+        let existing = zip::ZipArchive::new(fs::File::open(zip_path).at(zip_path)?).map_err(zip_err)?;
+        taken.extend(existing.file_names().map(String::from));
+    }
+
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut names = Vec::new();
+    for (source, wanted) in files {
+        let name = unique_entry_name(wanted, &taken);
+        taken.insert(name.clone());
+        writer.start_file(name.as_str(), options).map_err(zip_err)?;
+        std::io::copy(&mut fs::File::open(source).at(source)?, &mut writer).at(tmp)?;
+        names.push(name);
+    }
+    writer.finish().map_err(zip_err)?.sync_all().at(tmp)?;
+    Ok(names)
+}
+
+// Read every new entry back in full; the zip reader checks each CRC
+fn check_zip(tmp: &Path, files: &[(PathBuf, String)], names: &[String]) -> Result<(), OrganizeError> {
+    let bad = |why: String| OrganizeError::Io { path: tmp.to_path_buf(), source: std::io::Error::other(why) };
+    let mut zip = zip::ZipArchive::new(fs::File::open(tmp).at(tmp)?).map_err(|e| bad(e.to_string()))?;
+    for ((source, _), name) in files.iter().zip(names) {
+        let mut entry = zip.by_name(name).map_err(|e| bad(format!("{name}: {e}")))?;
+        let read = std::io::copy(&mut entry, &mut std::io::sink()).at(tmp)?;
+        if read != fs::metadata(source).at(source)?.len() {
+            return Err(bad(format!("{name} came back with a different size")));
+        }
+    }
+    Ok(())
+}
+
+// `name`, or "name (2).ext", "name (3).ext"… if it's taken
+fn unique_entry_name(name: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(name) { return name.to_string(); }
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.contains('/') => (stem, format!(".{ext}")),
+        _ => (name, String::new()),
+    };
+    (2..).map(|n| format!("{stem} ({n}){ext}")).find(|n| !taken.contains(n)).unwrap()
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::{dominant_category, Config};
+
+// ── Categorizer ──────────────────────────────
+// What organize() asks to decide where a file goes. Config is the default,
+// built from config.toml; embedders can plug in their own (a database
+// lookup, a model, …) and keep every other part of a run.
+
+pub trait Categorizer {
+    // Category folder for one file, or None to leave it in place. `ext` is
+    // lowercase and empty for files without an extension.
+    fn category_for(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<String>;
+
+    // The category plus which matcher picked it, for previews. Categorizers
+    // that can't say report MatchReason::Custom.
+    fn explain(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<(String, MatchReason)> {
+        self.category_for(path, ext, meta).map(|c| (c, MatchReason::Custom))
+    }
+
+    // explain(), showing its work for the `explain` command: one line in
+    // `steps` per rule, pattern or list tried. Categorizers that can't say
+    // add nothing.
+    fn trace(&self, path: &Path, ext: &str, meta: &Metadata, _steps: &mut Vec<String>) -> Option<(String, MatchReason)> {
+        self.explain(path, ext, meta)
+    }
+
+    // Folders this categorizer sorts into. They hold earlier output, so
+    // scans skip them.
+    fn folder_names(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    // Whether an uncategorized extension was switched off on purpose
+    // (reported as `excluded` rather than `unmatched-extension`)
+    fn is_excluded(&self, _ext: &str) -> bool {
+        false
+    }
+
+    // With --mime-fallback: a category for an extension nothing else matched
+    fn mime_category(&self, _ext: &str) -> Option<String> {
+        None
+    }
+
+    // With --peek-archives: the category most of an archive's entries belong to
+    fn archive_category(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
+    // Lowercase sidecar extensions that follow the file sharing their stem
+    fn companion_extensions(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+// Which matcher put a file in its category, shown in --dry-run previews
+// as e.g. `[ext:jpg]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MatchReason {
+    // The nth [[rules]] entry, counting from 1
+    Rule(usize),
+    // A [name_patterns] glob
+    NamePattern(String),
+    // The extension, through [categories]
+    Extension(String),
+    // The exact name of an extensionless file, through [filename_categories]
+    FileName(String),
+    // --mime-fallback, by this MIME type
+    Mime(String),
+    // An entry in the --mapping file
+    Mapping,
+    // --by-parent
+    Parent,
+    // --group-unknown-by-extension
+    Unknown(String),
+    // --collect-extensionless
+    Extensionless,
+    // Followed its main file (companions)
+    Companion,
+    // The --rules-script
+    Script,
+    // A Categorizer that doesn't explain itself
+    Custom,
+}
+
+impl fmt::Display for MatchReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchReason::Rule(n) => write!(f, "rule:{n}"),
+            MatchReason::NamePattern(glob) => write!(f, "name:{glob}"),
+            MatchReason::Extension(ext) => write!(f, "ext:{ext}"),
+            MatchReason::FileName(name) => write!(f, "file:{name}"),
+            MatchReason::Mime(mime) => write!(f, "mime:{mime}"),
+            MatchReason::Mapping => f.write_str("mapping"),
+            MatchReason::Parent => f.write_str("parent"),
+            MatchReason::Unknown(ext) => write!(f, "unknown:{ext}"),
+            MatchReason::Extensionless => f.write_str("no-extension"),
+            MatchReason::Companion => f.write_str("companion"),
+            MatchReason::Script => f.write_str("script"),
+            MatchReason::Custom => f.write_str("custom"),
+        }
+    }
+}
+
+impl Categorizer for Config {
+    fn category_for(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<String> {
+        self.explain(path, ext, meta).map(|(category, _)| category)
+    }
+
+    fn explain(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<(String, MatchReason)> {
+        explain_with_steps(self, path, ext, meta, &mut |_| {})
+    }
+
+    fn trace(&self, path: &Path, ext: &str, meta: &Metadata, steps: &mut Vec<String>) -> Option<(String, MatchReason)> {
+        explain_with_steps(self, path, ext, meta, &mut |step| steps.push(step.to_string()))
+    }
+
+    fn folder_names(&self) -> Vec<String> {
+        self.category_names().into_iter().map(String::from).collect()
+    }
+
+    fn is_excluded(&self, ext: &str) -> bool {
+        self.excluded_extensions.contains(ext)
+    }
+
+    fn mime_category(&self, ext: &str) -> Option<String> {
+        Config::mime_category(self, ext).map(String::from)
+    }
+
+    fn archive_category(&self, path: &Path) -> Option<String> {
+        dominant_category(path, self)
+    }
+
+    fn companion_extensions(&self) -> Vec<String> {
+        self.companions.clone()
+    }
+}
+
+fn explain_with_steps(
+    config: &Config,
+    path: &Path,
+    ext: &str,
+    meta: &Metadata,
+    step: &mut dyn FnMut(fmt::Arguments),
+) -> Option<(String, MatchReason)> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Extensionless files are only sorted by exact name
+    if ext.is_empty() && config.filename_category(&file_name).is_none() {
+        step(format_args!("filename_categories: no entry for {file_name:?}, the only list for files without an extension"));
+        return None;
+    }
+    let age = meta.modified().ok()
+        .and_then(|t| SystemTime::now().duration_since(t).ok())
+        .unwrap_or_default();
+    config.categorize_traced(&file_name, ext, meta.len(), age, step).map(|(c, why)| (c.to_string(), why))
+}
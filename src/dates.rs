@@ -0,0 +1,38 @@
+use std::sync::OnceLock;
+
+use chrono::NaiveDate;
+use regex::Regex;
+
+// ── Dates in file names ──────────────────────
+// Opt-in (--date-from-name): files go into <category>/<YYYY>/<MM>/ by the
+// date their name carries, which is usually when a photo was taken or a
+// report written. The modification time, often just the download time, is
+// only the fallback.
+
+// Tried in order; each captures year, month and day
+fn date_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // 2024-03-12 report.pdf, Screenshot 2024_03_12, scan.2024.03.12
+            r"(?:^|\D)(\d{4})[-_.](\d{2})[-_.](\d{2})(?:\D|$)",
+            // IMG_20240312_101500.jpg, VID-20240312-WA0001.mp4, 20240312.pdf
+            r"(?:^|\D)(\d{4})(\d{2})(\d{2})(?:\D|$)",
+        ]
+        .iter()
+        .map(|p| Regex::new(p).unwrap())
+        .collect()
+    })
+}
+
+// The date a file name spells out, if any. Implausible dates (month 13,
+// year 0042) don't count, so a plain number like 12345678 is ignored.
+pub fn date_from_name(file_name: &str) -> Option<NaiveDate> {
+    date_patterns().iter()
+        .flat_map(|re| re.captures_iter(file_name))
+        .find_map(|caps| {
+            let num = |i: usize| caps[i].parse::<u32>().ok();
+            let year = i32::try_from(num(1)?).ok().filter(|y| (1970..=2100).contains(y))?;
+            NaiveDate::from_ymd_opt(year, num(2)?, num(3)?)
+        })
+}
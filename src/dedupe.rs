@@ -0,0 +1,283 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::IoContext;
+use crate::{collect_files, delete_files, duplicate_key, file_hash, is_hidden_or_junk, CancelToken, DeletePolicy, OrganizeError};
+
+// ── Duplicate detection ──────────────────────
+
+// How two files are judged to be the same
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DuplicateStrategy {
+    // Same name + modification date + size (fast, never reads contents)
+    #[default]
+    NameDateSize,
+    // Same name + size, whatever the dates (copies that lost their mtime)
+    NameSize,
+    // Same SHA-256 of the contents (slower, catches renamed copies)
+    Content,
+}
+
+// Key under which duplicates collide for the given strategy
+pub fn fingerprint(
+    path: &Path,
+    meta: &fs::Metadata,
+    strategy: DuplicateStrategy,
+    scope: Option<&str>,
+) -> std::io::Result<String> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    match strategy {
+        DuplicateStrategy::NameDateSize => duplicate_key(&file_name, meta, scope),
+        DuplicateStrategy::NameSize => {
+            let name: String = file_name.nfc().collect();
+            Ok(format!("{}|{name}|{}", scope.unwrap_or(""), meta.len()))
+        }
+        DuplicateStrategy::Content => Ok(content_key(scope, meta.len(), &file_hash(path)?)),
+    }
+}
+
+fn content_key(scope: Option<&str>, len: u64, hash: &str) -> String {
+    format!("{}|{len}|{hash}", scope.unwrap_or(""))
+}
+
+// ── Hashing pass ─────────────────────────────
+// With --dup-strategy content, reading the files can take longer than the
+// rest of the run put together on a big photo library. So the reading is
+// done up front, before anything moves: a chunk at a time, with progress
+// in bytes, and a cancelled run stops between two chunks with nothing
+// moved yet.
+
+// Bytes read between two progress reports (and cancellation checks)
+const HASH_CHUNK: usize = 1 << 20;
+
+// Content hashes worked out before the first move of a run
+#[derive(Debug, Default)]
+pub struct ContentHashes {
+    hashes: HashMap<PathBuf, String>,
+    // Every file's size when it was looked at...
+    sizes: HashMap<PathBuf, u64>,
+    // ...and the sizes more than one file had: only those files can be
+    // copies of each other, so only those are read
+    shared: HashSet<u64>,
+}
+
+impl ContentHashes {
+    // Hash the regular files of `files` that share their size with another,
+    // in path order, calling `progress(done, total)` in bytes as it goes.
+    // None once `cancel` fires. Files that can't be read are left out;
+    // fingerprint() then sends them back to the usual route, which reports
+    // the error.
+    pub fn compute(files: &[PathBuf], cancel: &CancelToken, progress: &mut dyn FnMut(u64, u64)) -> Option<ContentHashes> {
+        let mut sizes = HashMap::new();
+        let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+        for file in files {
+            let Some(meta) = fs::metadata(file).ok().filter(|m| m.is_file()) else { continue };
+            sizes.insert(file.clone(), meta.len());
+            by_size.entry(meta.len()).or_default().push(file);
+        }
+        by_size.retain(|_, group| group.len() > 1);
+        let total = by_size.iter().map(|(size, group)| size * group.len() as u64).sum();
+        let mut queue: Vec<&PathBuf> = by_size.values().flatten().copied().collect();
+        queue.sort();
+
+        let mut hashes = HashMap::new();
+        let mut buf = vec![0; HASH_CHUNK];
+        let mut done = 0;
+        progress(done, total);
+        for file in queue {
+            let read = &mut |n| { done += n; progress(done, total) };
+            match hash_in_chunks(file, &mut buf, cancel, read) {
+                Ok(Some(hash)) => { hashes.insert(file.clone(), hash); }
+                Ok(None) => return None,
+                Err(_) => {}
+            }
+        }
+        Some(ContentHashes { hashes, sizes, shared: by_size.into_keys().collect() })
+    }
+
+    // The key fingerprint() would give `path`, now `len` bytes, under the
+    // content strategy. A file whose size no other file had gets a key
+    // nothing else can match, without being read. None for a file the pass
+    // didn't read, or that changed size since.
+    pub fn fingerprint(&self, path: &Path, len: u64, scope: Option<&str>) -> Option<String> {
+        if self.sizes.get(path) != Some(&len) {
+            return None;
+        }
+        if !self.shared.contains(&len) {
+            return Some(content_key(scope, len, ""));
+        }
+        self.hashes.get(path).map(|hash| content_key(scope, len, hash))
+    }
+}
+
+// file_hash(), reading into `buf` and telling `read` how many bytes each
+// chunk held; Ok(None) when `cancel` fires before the end
+fn hash_in_chunks(
+    path: &Path,
+    buf: &mut [u8],
+    cancel: &CancelToken,
+    read: &mut dyn FnMut(u64),
+) -> std::io::Result<Option<String>> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    loop {
+        if cancel.is_cancelled() {
+            return Ok(None);
+        }
+        let n = match file.read(buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.update(&buf[..n]);
+        read(n as u64);
+    }
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+// Default --dedupe-disk-threshold: runs over this many files keep their
+// fingerprints on disk
+pub const DEDUPE_DISK_THRESHOLD: usize = 200_000;
+
+// Fingerprints seen so far in a run, each with the first file that had it.
+// Small runs keep them in a HashMap; big ones in a DiskSeen, so memory
+// stays flat however many files there are.
+pub enum SeenStore {
+    Memory(HashMap<String, PathBuf>),
+    Disk(DiskSeen),
+}
+
+impl SeenStore {
+    // In memory for up to `threshold` files, on disk beyond (0 = never)
+    pub fn for_run(files: usize, threshold: usize) -> std::io::Result<Self> {
+        if threshold > 0 && files > threshold {
+            Ok(SeenStore::Disk(DiskSeen::new()?))
+        } else {
+            Ok(SeenStore::Memory(HashMap::new()))
+        }
+    }
+
+    // The file first seen with `key`; if there is none yet, `path` becomes it
+    pub fn first_or_insert(&mut self, key: &str, path: &Path) -> std::io::Result<Option<PathBuf>> {
+        match self {
+            SeenStore::Memory(seen) => match seen.get(key) {
+                Some(first) => Ok(Some(first.clone())),
+                None => { seen.insert(key.to_string(), path.to_path_buf()); Ok(None) }
+            },
+            SeenStore::Disk(seen) => seen.first_or_insert(key, path),
+        }
+    }
+
+    pub fn is_on_disk(&self) -> bool {
+        matches!(self, SeenStore::Disk(_))
+    }
+}
+
+// Buckets a DiskSeen spreads its entries over; a lookup reads one of them
+const DISK_BUCKETS_HEX: usize = 3;
+
+// Fingerprints in a temporary folder: each key's SHA-256 picks one of 4096
+// bucket files, which hold `<sha256>\t<json path>` lines. Only the bucket
+// being looked at is ever read. The folder is removed on drop.
+pub struct DiskSeen {
+    dir: PathBuf,
+}
+
+impl DiskSeen {
+    pub fn new() -> std::io::Result<Self> {
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let name = format!("smart-organizer-seen-{}-{}", std::process::id(), RUNS.fetch_add(1, Ordering::Relaxed));
+        let dir = std::env::temp_dir().join(name);
+        fs::create_dir_all(&dir)?;
+        Ok(DiskSeen { dir })
+    }
+
+    // Where the entries live
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn first_or_insert(&mut self, key: &str, path: &Path) -> std::io::Result<Option<PathBuf>> {
+        let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+        let bucket = self.dir.join(&digest[..DISK_BUCKETS_HEX]);
+        if let Ok(file) = fs::File::open(&bucket) {
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                let Some((hash, first)) = line.split_once('\t') else { continue };
+                if hash == digest {
+                    let first: String = serde_json::from_str(first)?;
+                    return Ok(Some(PathBuf::from(first)));
+                }
+            }
+        }
+        let mut out = fs::OpenOptions::new().create(true).append(true).open(&bucket)?;
+        writeln!(out, "{digest}\t{}", serde_json::to_string(&path.to_string_lossy())?)?;
+        Ok(None)
+    }
+}
+
+impl Drop for DiskSeen {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+// A group of files that are all duplicates of each other, sorted by path.
+// The first one is the copy that `dedupe --apply` keeps.
+#[derive(Debug, PartialEq)]
+pub struct DuplicateSet {
+    pub files: Vec<PathBuf>,
+    pub size: u64,
+}
+
+impl DuplicateSet {
+    // Space taken by every copy except the one kept
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+// Group every file under `base` (sorted folders included) into duplicate
+// sets. Read-only. Content hashing only runs on files sharing a size.
+pub fn find_duplicate_sets(base: &Path, strategy: DuplicateStrategy) -> Result<Vec<DuplicateSet>, OrganizeError> {
+    let mut by_size: HashMap<u64, Vec<(PathBuf, fs::Metadata)>> = HashMap::new();
+    for path in collect_files(base, &[])? {
+        if is_hidden_or_junk(&path) { continue; }
+        let meta = fs::metadata(&path).at(&path)?;
+        by_size.entry(meta.len()).or_default().push((path, meta));
+    }
+
+    let mut groups: HashMap<String, DuplicateSet> = HashMap::new();
+    for (size, files) in by_size {
+        if files.len() < 2 { continue; }
+        for (path, meta) in files {
+            let key = fingerprint(&path, &meta, strategy, None).at(&path)?;
+            groups.entry(key).or_insert_with(|| DuplicateSet { files: Vec::new(), size }).files.push(path);
+        }
+    }
+
+    let mut sets: Vec<DuplicateSet> = groups.into_values().filter(|s| s.files.len() > 1).collect();
+    for set in &mut sets {
+        set.files.sort();
+    }
+    sets.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.files.cmp(&b.files)));
+    Ok(sets)
+}
+
+// Delete every copy but the first of each set; returns how many were removed.
+// Big batches go through the policy's confirmation first.
+pub fn remove_duplicates(
+    sets: &[DuplicateSet],
+    policy: &DeletePolicy,
+    confirm: impl FnOnce(&str) -> bool,
+) -> Result<usize, OrganizeError> {
+    let extras: Vec<PathBuf> = sets.iter().flat_map(|s| s.files[1..].iter().cloned()).collect();
+    delete_files(&extras, policy, confirm)
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::IoContext;
+use crate::{format_bytes, OrganizeError};
+
+// ── Deleting ─────────────────────────────────
+// Every feature that removes files goes through here, so the same safety
+// policy applies everywhere: big batches need a confirmation, and files can
+// go to the system trash instead of being destroyed.
+
+#[derive(Clone, Debug)]
+pub struct DeletePolicy {
+    // Ask before deleting more than this many files (0 = never ask on count)
+    pub max_files: usize,
+    // Ask before deleting more than this many bytes (0 = never ask on size)
+    pub max_bytes: u64,
+    // --yes: skip the question
+    pub assume_yes: bool,
+    // Send files to the system trash rather than deleting them outright
+    pub to_trash: bool,
+}
+
+// Ask past 20 files or 1 GiB, delete permanently
+impl Default for DeletePolicy {
+    fn default() -> Self {
+        DeletePolicy { max_files: 20, max_bytes: 1 << 30, assume_yes: false, to_trash: false }
+    }
+}
+
+impl DeletePolicy {
+    // Does a batch of this size need the user's go-ahead?
+    pub fn needs_confirmation(&self, files: usize, bytes: u64) -> bool {
+        !self.assume_yes
+            && ((self.max_files > 0 && files > self.max_files) || (self.max_bytes > 0 && bytes > self.max_bytes))
+    }
+}
+
+// Remove one file, to the trash or for good, per the policy
+pub fn delete_file(path: &Path, policy: &DeletePolicy) -> Result<(), OrganizeError> {
+    if policy.to_trash {
+        trash::delete(path).map_err(|e| OrganizeError::Io {
+            path: path.to_path_buf(),
+            source: std::io::Error::other(e.to_string()),
+        })
+    } else {
+        fs::remove_file(path).at(path)
+    }
+}
+
+// Delete a batch. When it's over the policy's limits, `confirm` is asked
+// first with a description like "35 files (2.1 GB)"; saying no deletes
+// nothing. Returns how many files were removed.
+pub fn delete_files(
+    paths: &[PathBuf],
+    policy: &DeletePolicy,
+    confirm: impl FnOnce(&str) -> bool,
+) -> Result<usize, OrganizeError> {
+    confirm_deletion(paths, policy, confirm)?;
+    for path in paths {
+        delete_file(path, policy)?;
+    }
+    Ok(paths.len())
+}
+
+// The guard delete_files() puts in front of a batch, for callers that
+// delete the files one by one afterwards and carry on past failures: Ok
+// when the batch is within the policy's limits or `confirm` said yes
+pub fn confirm_deletion(
+    paths: &[PathBuf],
+    policy: &DeletePolicy,
+    confirm: impl FnOnce(&str) -> bool,
+) -> Result<(), OrganizeError> {
+    let bytes: u64 = paths.iter().filter_map(|p| fs::metadata(p).ok()).map(|m| m.len()).sum();
+    if policy.needs_confirmation(paths.len(), bytes) {
+        let what = format!("{} files ({})", paths.len(), format_bytes(bytes));
+        if !confirm(&what) {
+            return Err(OrganizeError::Cancelled(format!("deleting {what} was not confirmed")));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+use image::{ImageFormat, ImageReader};
+
+// ── Image dimensions ─────────────────────────
+// Opt-in (--image-split): pictures are sorted one level deeper by their
+// shape or size, e.g. Images/Portrait/ or Images/4K/. Only the header is
+// read; files it can't be read from stay in the plain category. EXIF
+// rotation isn't applied, so a portrait shot stored sideways counts as
+// landscape.
+
+// Which subfolders --image-split sorts pictures into
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImageSplit {
+    // Landscape, Portrait or Square
+    Orientation,
+    // 4K (longer side 3840px or more), 1080p (1920px or more) or Small
+    Resolution,
+}
+
+// Width and height of a picture, from its header alone. None for
+// extensions the image crate can't read and for damaged files.
+pub fn image_dimensions(path: &Path, ext: &str) -> Option<(u32, u32)> {
+    ImageFormat::from_extension(ext).filter(|f| f.reading_enabled())?;
+    ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_dimensions().ok()
+}
+
+// The subfolder a `width` x `height` picture goes in
+pub fn image_subfolder(split: ImageSplit, width: u32, height: u32) -> &'static str {
+    match split {
+        ImageSplit::Orientation if width > height => "Landscape",
+        ImageSplit::Orientation if width < height => "Portrait",
+        ImageSplit::Orientation => "Square",
+        ImageSplit::Resolution => match width.max(height) {
+            3840.. => "4K",
+            1920.. => "1080p",
+            _ => "Small",
+        },
+    }
+}
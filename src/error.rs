@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+// ── Errors ───────────────────────────────────
+// What can go wrong, with the path involved wherever there is one, so both
+// library callers (match on the variant / io kind) and the CLI (print it)
+// get something actionable.
+
+#[derive(Debug, Error)]
+pub enum OrganizeError {
+    // The config file is unreadable as config: bad syntax, unknown format…
+    #[error("config {}: {message}", path.display())]
+    Config { path: PathBuf, message: String },
+
+    // Options that can't work as given, e.g. a backup dir inside the target
+    #[error("{0}")]
+    Options(String),
+
+    // Not enough room on the destination volume for the copies
+    #[error("not enough space for {}: need {} (+{} kept free), {} available",
+        path.display(), crate::format_bytes(*needed), crate::format_bytes(*min_free), crate::format_bytes(*available))]
+    InsufficientSpace { path: PathBuf, needed: u64, min_free: u64, available: u64 },
+
+    // The user said no when asked to confirm
+    #[error("cancelled: {0}")]
+    Cancelled(String),
+
+    // Reading or writing a specific file or folder failed
+    #[error("{}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+
+    // Moving one file failed (permission denied, cross-device copy failed…)
+    #[error("could not move {} to {}: {source}", from.display(), to.display())]
+    Move { from: PathBuf, to: PathBuf, source: std::io::Error },
+
+    // The --on-move command couldn't start or exited unsuccessfully
+    #[error("on-move command `{command}` {message}")]
+    Hook { command: String, message: String },
+
+    // The run finished, but some files couldn't be organized
+    #[error("{} file(s) could not be organized", failures.len())]
+    PartialRun { failures: Vec<(PathBuf, String)> },
+
+    // IO failure with no particular path attached
+    #[error(transparent)]
+    Other(#[from] std::io::Error),
+}
+
+impl OrganizeError {
+    // Underlying io error kind, if this came from the filesystem
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        match self {
+            OrganizeError::Io { source, .. }
+            | OrganizeError::Move { source, .. }
+            | OrganizeError::Other(source) => Some(source.kind()),
+            _ => None,
+        }
+    }
+}
+
+// Attach a path to an io::Result: `fs::read_dir(dir).at(dir)?`
+pub(crate) trait IoContext<T> {
+    fn at(self, path: &Path) -> Result<T, OrganizeError>;
+}
+
+impl<T> IoContext<T> for std::io::Result<T> {
+    fn at(self, path: &Path) -> Result<T, OrganizeError> {
+        self.map_err(|source| OrganizeError::Io { path: path.to_path_buf(), source })
+    }
+}
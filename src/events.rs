@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{MatchReason, SkipReason, Stats};
+
+// ── Progress events ──────────────────────────
+// What organize_with_events() reports as a run goes, for front-ends that
+// would rather not parse the colored output. Events arrive in file order.
+
+#[derive(Debug, Clone)]
+pub enum OrganizeEvent {
+    // Files were collected; `total` of them will be looked at
+    Started { total: usize },
+    // --dup-strategy content: `done` of the `total` bytes to hash before
+    // the first move have been read
+    Hashing { done: u64, total: u64 },
+    // A file was moved (planned, under --dry-run), and why it went there
+    // (None for moves replayed by apply_plan)
+    Moved { from: PathBuf, to: PathBuf, reason: Option<MatchReason> },
+    // A file was left where it was
+    Skipped { path: PathBuf, reason: SkipReason },
+    // A file failed; the run carries on
+    Error { path: PathBuf, message: String },
+    // The run is over. Not sent when the run aborts with an Err.
+    Finished { stats: Stats },
+}
+
+// ── Cancellation ─────────────────────────────
+// A run checks its token between files, and between chunks while hashing,
+// and stops at the next of those once it is cancelled: what already moved
+// stays moved, Stats::cancelled is set and the run still returns Ok.
+
+// Clones share one flag, so a Ctrl-C handler or another thread can stop
+// a run it handed a clone to
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
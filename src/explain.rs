@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::IoContext;
+use crate::{
+    file_extension, is_hidden_or_junk, organize_with_events, Categorizer, MatchReason, OrganizeError, OrganizeEvent,
+    OrganizeOpts, SkipReason,
+};
+
+// ── Explaining one file (explain) ────────────
+// Why one file goes where it goes: the extension as read, every rule,
+// pattern and list the categorizer tried, and where a run with the same
+// options would put it, or why it would leave it. Nothing is moved; the
+// destination comes from a dry run over that file alone.
+
+// Where a run would leave the file
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileOutcome {
+    MovesTo(PathBuf),
+    Stays(SkipReason),
+    Fails(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct FileExplanation {
+    // What was looked at, in order, one line each
+    pub steps: Vec<String>,
+    // The category and the matcher that picked it, as the categorizer
+    // sees it (options like --mapping may still override it)
+    pub matched: Option<(String, MatchReason)>,
+    pub outcome: FileOutcome,
+}
+
+// Walk `file` through `categorizer` and a dry run of `opts`. Options that
+// weigh a file against the rest of the run (duplicates, --keep-newest,
+// the count thresholds) can't be judged from one file, so they are left
+// out, and a step says so.
+pub fn explain_file(opts: &OrganizeOpts, categorizer: &dyn Categorizer, file: &Path) -> Result<FileExplanation, OrganizeError> {
+    let meta = fs::metadata(file).at(file)?;
+    let mut steps = Vec::new();
+
+    if is_hidden_or_junk(file) {
+        steps.push("hidden or junk: runs never look at it".to_string());
+    }
+    let ext = file_extension(file).unwrap_or_default();
+    let raw = file.extension().unwrap_or_default().to_string_lossy();
+    match ext.as_str() {
+        "" => steps.push("extension: none".to_string()),
+        lower if lower != raw => steps.push(format!("extension: {lower:?} (written {raw:?})")),
+        lower => steps.push(format!("extension: {lower:?}")),
+    }
+
+    let matched = categorizer.trace(file, &ext, &meta, &mut steps);
+    match &matched {
+        Some((category, reason)) => steps.push(format!("category: {category} [{reason}]")),
+        None => steps.push("category: none".to_string()),
+    }
+
+    let whole_run = [
+        (opts.find_duplicates, "duplicates"),
+        (opts.keep_newest.is_some() || !opts.keep_newest_per_ext.is_empty(), "--keep-newest"),
+        (opts.min_ext_count > 0 || opts.max_ext_count > 0, "--min/--max-ext-count"),
+        (opts.min_files_to_create_folder > 1, "--min-files-to-create-folder"),
+    ];
+    let ignored: Vec<&str> = whole_run.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+    if !ignored.is_empty() {
+        steps.push(format!("not checked, as they depend on the other files: {}", ignored.join(", ")));
+    }
+
+    let single = OrganizeOpts {
+        file_list: Some(vec![file.to_path_buf()]),
+        dry_run: true,
+        quiet: true,
+        preview_tree_dir: None,
+        find_duplicates: false,
+        keep_newest: None,
+        keep_newest_per_ext: HashMap::new(),
+        min_ext_count: 0,
+        max_ext_count: 0,
+        min_files_to_create_folder: 0,
+        ..opts.clone()
+    };
+    let mut outcome = None;
+    organize_with_events(&single, categorizer, &mut |event| match event {
+        OrganizeEvent::Moved { to, reason, .. } if outcome.is_none() => {
+            // An option (--mapping, --mime-fallback…) beat the categorizer to it
+            if let Some(reason) = reason.filter(|r| matched.as_ref().is_none_or(|(_, m)| m != r)) {
+                steps.push(format!("decided instead by [{reason}]"));
+            }
+            outcome = Some(FileOutcome::MovesTo(to));
+        }
+        OrganizeEvent::Skipped { reason, .. } if outcome.is_none() => outcome = Some(FileOutcome::Stays(reason)),
+        OrganizeEvent::Error { message, .. } if outcome.is_none() => outcome = Some(FileOutcome::Fails(message)),
+        _ => {}
+    })?;
+
+    // Only a snapshot index, the organizer's own file, goes without an event
+    let outcome = outcome.unwrap_or(FileOutcome::Stays(SkipReason::Junk));
+    Ok(FileExplanation { steps, matched, outcome })
+}
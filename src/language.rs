@@ -0,0 +1,23 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// ── Language detection ───────────────────────
+// Opt-in (--detect-language): plain-text documents are sorted one level
+// deeper by the language they're written in, e.g. Documents/French/.
+// Only a small sample is read, and unsure guesses are ignored.
+
+// Extensions whose contents are read as text for detection
+pub const TEXT_EXTENSIONS: &[&str] = &["txt", "md", "markdown", "rst", "text"];
+
+// How much of each file is sampled
+pub const LANGUAGE_SAMPLE_BYTES: u64 = 4 * 1024;
+
+// English name of the language a text file is written in ("English",
+// "French", …), or None when it can't be read or detection isn't reliable
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let mut sample = Vec::new();
+    fs::File::open(path).ok()?.take(LANGUAGE_SAMPLE_BYTES).read_to_end(&mut sample).ok()?;
+    let info = whatlang::detect(&String::from_utf8_lossy(&sample))?;
+    info.is_reliable().then(|| info.lang().eng_name())
+}
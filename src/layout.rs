@@ -0,0 +1,165 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{format_duration, parse_duration};
+
+// ── Layout pipeline ──────────────────────────
+// How a file's destination folder is put together, one step per level:
+// `layout = ["category", "by_date:YearMonth"]` gives Images/2024/03/,
+// `["by_date:Year", "category"]` gives 2024/Images/. Without a layout a
+// file goes straight into its category (plus dates for --date-from-name).
+
+// One level of the destination folder
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum LayoutStep {
+    // The category folder (Images, Documents/en, Other/psd…)
+    Category,
+    // The file's date, from its name or else its modification time
+    ByDate(DateDepth),
+    // The lowercase extension; files without one skip this level
+    Extension,
+    // The folder the file sits in; files directly in the target skip it
+    Parent,
+    // How long ago the file was modified, as one of the age buckets
+    // (Recent, ThisMonth, ThisYear, Older by default)
+    AgeBucket,
+}
+
+// How deep `by_date` nests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateDepth {
+    Year,
+    YearMonth,
+    YearMonthDay,
+}
+
+// What the steps draw on for one file
+pub struct LayoutFile<'a> {
+    pub category: &'a str,
+    pub date: NaiveDate,
+    pub ext: &'a str,
+    pub parent: Option<&'a str>,
+    pub age_bucket: &'a str,
+}
+
+// Bucket for files older than every configured one
+pub const OLDER_BUCKET: &str = "Older";
+
+// One folder of the `age_bucket` step: files modified at most `within`
+// before the run go in `name`. A bucket without `within` takes the rest.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct AgeBucket {
+    pub name: String,
+    #[serde(default, deserialize_with = "age_limit", serialize_with = "write_age_limit", skip_serializing_if = "Option::is_none")]
+    pub within: Option<Duration>,
+}
+
+impl AgeBucket {
+    pub fn new(name: &str, within: Option<Duration>) -> Self {
+        AgeBucket { name: name.to_string(), within }
+    }
+}
+
+// "7d", "6m"… as in --archive-older-than
+fn age_limit<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Duration>, D::Error> {
+    let text = String::deserialize(de)?;
+    parse_duration(&text).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn write_age_limit<S: Serializer>(within: &Option<Duration>, ser: S) -> Result<S::Ok, S::Error> {
+    within.map(format_duration).serialize(ser)
+}
+
+// Recent (7 days), ThisMonth (30), ThisYear (365), Older
+pub fn default_age_buckets() -> Vec<AgeBucket> {
+    const DAY: u64 = 24 * 3600;
+    vec![
+        AgeBucket::new("Recent", Some(Duration::from_secs(7 * DAY))),
+        AgeBucket::new("ThisMonth", Some(Duration::from_secs(30 * DAY))),
+        AgeBucket::new("ThisYear", Some(Duration::from_secs(365 * DAY))),
+        AgeBucket::new(OLDER_BUCKET, None),
+    ]
+}
+
+// The first bucket, in order, whose limit covers a file modified at
+// `modified` as of `now` (limits are inclusive). Files from the future
+// count as brand new; files older than every bucket go in Older.
+pub fn age_bucket(now: SystemTime, modified: SystemTime, buckets: &[AgeBucket]) -> &str {
+    let age = now.duration_since(modified).unwrap_or_default();
+    buckets.iter()
+        .find(|b| b.within.is_none_or(|limit| age <= limit))
+        .map_or(OLDER_BUCKET, |b| b.name.as_str())
+}
+
+impl FromStr for LayoutStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (step, arg) = match s.trim().split_once(':') {
+            Some((step, arg)) => (step, Some(arg)),
+            None => (s.trim(), None),
+        };
+        match (step.to_ascii_lowercase().as_str(), arg.map(str::to_ascii_lowercase).as_deref()) {
+            ("category", None) => Ok(LayoutStep::Category),
+            ("extension", None) => Ok(LayoutStep::Extension),
+            ("parent", None) => Ok(LayoutStep::Parent),
+            ("age_bucket", None) => Ok(LayoutStep::AgeBucket),
+            ("by_date", Some("year")) => Ok(LayoutStep::ByDate(DateDepth::Year)),
+            ("by_date", None | Some("yearmonth")) => Ok(LayoutStep::ByDate(DateDepth::YearMonth)),
+            ("by_date", Some("yearmonthday")) => Ok(LayoutStep::ByDate(DateDepth::YearMonthDay)),
+            _ => Err(format!(
+                "unknown layout step \"{s}\"; use category, extension, parent, age_bucket or by_date:Year|YearMonth|YearMonthDay",
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for LayoutStep {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<LayoutStep> for String {
+    fn from(step: LayoutStep) -> Self {
+        step.to_string()
+    }
+}
+
+impl fmt::Display for LayoutStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutStep::Category => f.write_str("category"),
+            LayoutStep::Extension => f.write_str("extension"),
+            LayoutStep::Parent => f.write_str("parent"),
+            LayoutStep::AgeBucket => f.write_str("age_bucket"),
+            LayoutStep::ByDate(DateDepth::Year) => f.write_str("by_date:Year"),
+            LayoutStep::ByDate(DateDepth::YearMonth) => f.write_str("by_date:YearMonth"),
+            LayoutStep::ByDate(DateDepth::YearMonthDay) => f.write_str("by_date:YearMonthDay"),
+        }
+    }
+}
+
+// Run `file` through the steps, in order: the folder (relative to the
+// output base) it goes in, e.g. "Images/2024/03". A file no step places
+// anywhere (["parent"] for a file at the top) gets its category.
+pub fn layout_folder(steps: &[LayoutStep], file: &LayoutFile) -> String {
+    let levels = steps.iter().filter_map(|step| match step {
+        LayoutStep::Category => Some(file.category.to_string()),
+        LayoutStep::ByDate(DateDepth::Year) => Some(file.date.format("%Y").to_string()),
+        LayoutStep::ByDate(DateDepth::YearMonth) => Some(file.date.format("%Y/%m").to_string()),
+        LayoutStep::ByDate(DateDepth::YearMonthDay) => Some(file.date.format("%Y/%m/%d").to_string()),
+        LayoutStep::Extension => Some(file.ext.to_string()).filter(|e| !e.is_empty()),
+        LayoutStep::Parent => file.parent.map(String::from),
+        LayoutStep::AgeBucket => Some(file.age_bucket.to_string()),
+    });
+    let folder = levels.collect::<Vec<_>>().join("/");
+    if folder.is_empty() { file.category.to_string() } else { folder }
+}
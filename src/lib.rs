@@ -1,42 +1,572 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::io::Write;
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
 
 use chrono::Local;
 use colored::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+
+mod archive;
+mod categorizer;
+mod dates;
+mod dedupe;
+mod delete;
+mod dimensions;
+mod error;
+mod events;
+mod explain;
+mod language;
+mod layout;
+mod manifest;
+mod mapping;
+mod plugin;
+mod rename;
+mod retention;
+mod rules;
+mod script;
+mod snapshot;
+mod volume;
+pub use archive::*;
+pub use categorizer::*;
+pub use dates::*;
+pub use dedupe::*;
+pub use delete::*;
+pub use dimensions::*;
+pub use error::*;
+pub use events::*;
+pub use explain::*;
+pub use language::*;
+pub use layout::*;
+pub use manifest::*;
+pub use mapping::*;
+pub use plugin::*;
+pub use rename::*;
+pub use retention::*;
+pub use rules::*;
+pub use script::*;
+pub use snapshot::*;
+pub use volume::*;
 
 // ── Configuration ────────────────────────────
 // Maps file extensions to category folders
 // e.g. "jpg" -> "Images", "pdf" -> "Documents"
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 pub struct Config {
     #[serde(default = "default_categories")]
     pub categories: HashMap<String, Vec<String>>,
+    // Category -> filename globs, e.g. "Screenshots" = ["Screenshot *"].
+    // Checked before extensions, so a name match wins.
+    #[serde(default)]
+    pub name_patterns: HashMap<String, Vec<String>>,
+    // Ordered rules on extension/size/age/name; the first match wins over
+    // both name patterns and extensions
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    // Exact names of extensionless files -> category, e.g. "Dockerfile" = "DevOps"
+    #[serde(default)]
+    pub filename_categories: HashMap<String, String>,
+    // Which matcher wins when several fit one file
+    #[serde(default)]
+    pub precedence: Precedence,
+    // MIME type -> category for --mime-fallback. Keys are a top-level type
+    // ("image") or a full one ("application/pdf"); the full one wins.
+    #[serde(default = "default_mime_categories")]
+    pub mime_categories: HashMap<String, String>,
+    // Sidecar extensions (xmp, thm, aae…) that follow the file sharing their
+    // stem (photo.CR2 + photo.xmp, or photo.CR2.xmp) instead of being sorted
+    // on their own
+    #[serde(default)]
+    pub companions: Vec<String>,
+    // Steps that build each file's folder (see LayoutStep); empty means just
+    // the category. The CLI copies it into OrganizeOpts::layout.
+    #[serde(default)]
+    pub layout: Vec<LayoutStep>,
+    // Folders of the age_bucket layout step, newest first
+    #[serde(default = "default_age_buckets")]
+    pub age_buckets: Vec<AgeBucket>,
+    // Extension -> how many of its newest files to keep, e.g. log = 5; the
+    // older ones go as --keep-newest-action says. The CLI copies it into
+    // OrganizeOpts::keep_newest_per_ext.
+    #[serde(default)]
+    pub keep_newest: HashMap<String, usize>,
+    // Merge categories whose names differ only by case (Images, images)
+    // when the config is loaded; --case-fold-categories does the same
+    #[serde(default)]
+    pub case_fold_categories: bool,
+    // Categories switched off for this run. Their folders are still treated
+    // as sorted output, so files already in them stay untouched. Shown by
+    // --print-config, but never read from a config file.
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<String>,
+    // Lowercased extensions of excluded categories, to explain skips
+    #[serde(skip)]
+    pub excluded_extensions: HashSet<String>,
+    // Lowercased extension -> category, built from `categories` on first
+    // lookup. Edit categories through Config's methods (or call reindex())
+    // once lookups have started, or the index goes stale.
+    #[serde(skip)]
+    pub ext_index: ExtensionIndex,
+}
+
+// One way of picking a category for a file
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Matcher {
+    // [[rules]] on extension, name, size and age
+    Rules,
+    // [name_patterns] globs
+    NamePatterns,
+    // [categories] extensions, plus [filename_categories] for files without one
+    Extensions,
+}
+
+// [precedence] order = ["rules", "name_patterns", "extensions"]
+// Matchers are tried in this order and the first match wins. Any left out
+// are tried afterwards, in the default order.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Precedence {
+    #[serde(default = "default_order")]
+    pub order: Vec<Matcher>,
+}
+
+impl Default for Precedence {
+    fn default() -> Self {
+        Precedence { order: default_order() }
+    }
+}
+
+fn default_order() -> Vec<Matcher> {
+    vec![Matcher::Rules, Matcher::NamePatterns, Matcher::Extensions]
+}
+
+impl Precedence {
+    // The configured order, completed with any matchers it leaves out
+    pub fn resolved(&self) -> Vec<Matcher> {
+        let mut order: Vec<Matcher> = Vec::new();
+        for m in self.order.iter().chain(&default_order()) {
+            if !order.contains(m) { order.push(*m); }
+        }
+        order
+    }
+}
+
+// Lazily built lookup tables for `categories`; opaque so
+// `..Default::default()` still works when building a Config by hand
+#[derive(Debug, Default)]
+pub struct ExtensionIndex(OnceLock<CategoryIndex>);
+
+#[derive(Debug)]
+struct CategoryIndex {
+    // Lowercased extension -> category
+    extensions: HashMap<String, String>,
+    // The filename globs among the entries, with their category, in
+    // category order
+    globs: Vec<(glob::Pattern, String)>,
+}
+
+// Whether an entry in a category's list is a filename glob ("Screenshot *",
+// "*.backup") rather than a bare extension: it has a wildcard or a dot
+pub fn is_category_glob(entry: &str) -> bool {
+    entry.contains(['*', '?', '[', '.'])
 }
 
 impl Config {
     // Load categories from config.toml, or use defaults if not found
     pub fn load() -> Self {
-        match fs::read_to_string("config.toml") {
-            Ok(text) => toml::from_str(&text).unwrap_or_else(|e| {
-                eprintln!("{} config.toml error ({}), using defaults", "⚠".yellow(), e);
-                Config::default()
-            }),
-            Err(_) => {
-                println!("{} No config.toml, using defaults", "ℹ".blue());
-                Config::default()
+        let path = Path::new("config.toml");
+        if !path.exists() {
+            // On stderr, to keep --print-config's output clean
+            eprintln!("{} No config.toml, using defaults", "ℹ".blue());
+            return Config::default();
+        }
+        Config::load_from(path).unwrap_or_else(|e| {
+            eprintln!("{} config.toml error ({}), using defaults", "⚠".yellow(), e);
+            Config::default()
+        })
+    }
+
+    // Load a config file, picking the parser from its extension:
+    // .toml, .json, .yaml or .yml
+    pub fn load_from(path: &Path) -> Result<Self, OrganizeError> {
+        let config: Config = parse_config_file(path)?;
+        Ok(config.tidied(&path.display().to_string()))
+    }
+
+    // Load several config files as one, each laid over the ones before it
+    // (see merge_config_values): a shared base first, personal overrides
+    // last. Formats can be mixed.
+    pub fn load_layered(paths: &[PathBuf]) -> Result<Self, OrganizeError> {
+        let [.., last] = paths else { return Ok(Config::default()) };
+        if paths.len() == 1 {
+            return Config::load_from(last);
+        }
+        let mut merged = serde_json::Value::Object(Default::default());
+        for path in paths {
+            merge_config_values(&mut merged, parse_config_file(path)?);
+        }
+        let label = paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(" + ");
+        let mut config: Config = serde_json::from_value(merged)
+            .map_err(|e| OrganizeError::Config { path: last.clone(), message: format!("in {label} combined: {e}") })?;
+        config = config.tidied(&label);
+        // ".JPG" in one file and "jpg" in another are the same entry
+        for entries in config.categories.values_mut().chain(config.name_patterns.values_mut()) {
+            let mut seen = HashSet::new();
+            entries.retain(|e| seen.insert(e.clone()));
+        }
+        Ok(config)
+    }
+
+    // Normalize extensions and fold categories if asked to, warning about
+    // anything odd in `source`
+    fn tidied(mut self, source: &str) -> Self {
+        let mut warnings = self.normalize_extensions();
+        if self.case_fold_categories {
+            warnings.extend(self.fold_category_case());
+        }
+        for warning in warnings {
+            eprintln!("{} {}: {}", "⚠".yellow(), source, warning);
+        }
+        self
+    }
+
+    // Merge categories whose names differ only by case into one, under the
+    // alphabetically first spelling (so "Images" beats "images"), with the
+    // extensions of all of them. Name patterns are merged the same way, and
+    // other references to a folded name follow. One warning per merge.
+    pub fn fold_category_case(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let mut names: Vec<String> = self.categories.keys().chain(self.name_patterns.keys()).cloned().collect();
+        names.sort();
+        names.dedup();
+        let mut canonical: HashMap<String, String> = HashMap::new();
+        for name in names {
+            let kept = canonical.entry(name.to_lowercase()).or_insert_with(|| name.clone()).clone();
+            if kept != name {
+                warnings.push(format!("category \"{name}\" differs from \"{kept}\" only by case; merged into \"{kept}\""));
+            }
+        }
+        let fold = |name: &str| canonical.get(&name.to_lowercase()).cloned().unwrap_or_else(|| name.to_string());
+
+        for map in [&mut self.categories, &mut self.name_patterns] {
+            let mut merged: HashMap<String, Vec<String>> = HashMap::new();
+            let mut entries: Vec<_> = map.drain().collect();
+            entries.sort();
+            for (name, items) in entries {
+                let list = merged.entry(fold(&name)).or_default();
+                list.extend(items.into_iter().filter(|i| !list.contains(i)).collect::<Vec<_>>());
+            }
+            *map = merged;
+        }
+        for category in self.filename_categories.values_mut().chain(self.mime_categories.values_mut()) {
+            *category = fold(category);
+        }
+        for rule in &mut self.rules {
+            rule.destination = fold(&rule.destination);
+        }
+        self.reindex();
+        warnings
+    }
+
+    // Tidy extension lists (categories and rules) the way they're usually
+    // mistyped: ".JPG" becomes "jpg". Globs in categories are kept as they
+    // are; elsewhere entries with wildcards can never match an extension, so
+    // they're kept and a warning for each is returned.
+    pub fn normalize_extensions(&mut self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let lists = self.categories.iter_mut()
+            .map(|(name, exts)| (name.clone(), exts, true))
+            .chain(self.rules.iter_mut().map(|r| (format!("rule -> {}", r.destination), &mut r.extensions, false)))
+            .chain(std::iter::once(("companions".to_string(), &mut self.companions, false)));
+        for (owner, exts, globs) in lists {
+            for ext in exts.iter_mut() {
+                if globs && is_category_glob(ext.trim().trim_start_matches('.')) {
+                    *ext = ext.trim().to_string();
+                    continue;
+                }
+                if ext.contains(['*', '?', '[']) {
+                    let bare = ext.trim_start_matches(['*', '.']);
+                    warnings.push(format!(
+                        "\"{ext}\" in {owner} is a wildcard and won't match anything; write \"{bare}\", or use name_patterns for globs",
+                    ));
+                    continue;
+                }
+                *ext = ext.trim().trim_start_matches('.').to_ascii_lowercase();
             }
         }
+        self.reindex();
+        warnings
     }
 
-    // Find which category a file extension belongs to
+    // Find which category a file extension belongs to (globs in the lists
+    // need the whole name; see categorize_file)
     pub fn categorize(&self, ext: &str) -> Option<&str> {
-        self.categories.iter()
-            .find(|(_, exts)| exts.iter().any(|e| e.eq_ignore_ascii_case(ext)))
-            .map(|(cat, _)| cat.as_str())
+        self.ext_index().extensions
+            .get(&ext.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    // The category and the glob from `categories` that match a file name
+    // (case-insensitive)
+    fn match_category_glob(&self, file_name: &str) -> Option<(&str, &str)> {
+        let opts = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+        self.ext_index().globs.iter()
+            .find(|(glob, _)| glob.matches_with(file_name, opts))
+            .map(|(glob, category)| (category.as_str(), glob.as_str()))
+    }
+
+    // The category index, built once. An extension listed under several
+    // categories goes to the alphabetically first, so the pick is stable;
+    // globs are tried in the same order. Invalid globs are left out.
+    fn ext_index(&self) -> &CategoryIndex {
+        self.ext_index.0.get_or_init(|| {
+            let mut names: Vec<&String> = self.categories.keys().collect();
+            names.sort_unstable();
+            let mut index = CategoryIndex { extensions: HashMap::new(), globs: Vec::new() };
+            for name in names.into_iter().rev() {
+                for ext in self.categories[name].iter().filter(|e| !is_category_glob(e)) {
+                    index.extensions.insert(ext.to_ascii_lowercase(), name.clone());
+                }
+            }
+            for (name, entries) in self.categories.iter().collect::<BTreeMap<_, _>>() {
+                let globs = entries.iter().filter(|e| is_category_glob(e)).filter_map(|e| glob::Pattern::new(e).ok());
+                index.globs.extend(globs.map(|glob| (glob, name.clone())));
+            }
+            index
+        })
+    }
+
+    // Throw away the extension index after editing `categories` directly
+    pub fn reindex(&mut self) {
+        self.ext_index = ExtensionIndex::default();
+    }
+
+    // Drop categories (extensions, name patterns and rules) for this run
+    pub fn exclude_categories(&mut self, names: &[String]) {
+        for name in names {
+            if let Some(exts) = self.categories.remove(name) {
+                self.excluded_extensions.extend(exts.iter().filter(|e| !is_category_glob(e)).map(|e| e.to_ascii_lowercase()));
+            }
+            self.name_patterns.remove(name);
+            self.rules.retain(|r| &r.destination != name);
+            self.filename_categories.retain(|_, c| c != name);
+            self.excluded.push(name.clone());
+        }
+        self.reindex();
+    }
+
+    // Keep only the listed categories for this run; the rest are excluded
+    pub fn only_categories(&mut self, names: &[String]) {
+        let others: Vec<String> = self.category_names().into_iter()
+            .filter(|c| !names.iter().any(|n| n == c))
+            .map(String::from)
+            .collect();
+        self.exclude_categories(&others);
+    }
+
+    // Every folder name a file can be (or was) sorted into
+    pub fn category_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.categories.keys()
+            .chain(self.name_patterns.keys())
+            .chain(&self.excluded)
+            .map(String::as_str)
+            .chain(self.rules.iter().map(|r| r.destination.as_str()))
+            .chain(self.filename_categories.values().map(String::as_str))
+            .chain(self.mime_categories.values().map(String::as_str))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    // Find a category from name and extension alone (no rules, which need
+    // size and age), walking name patterns and extensions in precedence order
+    pub fn categorize_file(&self, file_name: &str, ext: &str) -> Option<&str> {
+        self.categorize_in_order(file_name, ext, None)
+    }
+
+    // Category for an extensionless file like `Dockerfile` or `README`
+    pub fn filename_category(&self, file_name: &str) -> Option<&str> {
+        self.filename_categories.get(file_name).map(String::as_str)
+    }
+
+    // Category for an extension by its guessed MIME type (--mime-fallback):
+    // "tiff" is image/tiff, so Images. Excluded categories never match.
+    pub fn mime_category(&self, ext: &str) -> Option<&str> {
+        let mime = mime_guess::from_ext(ext).first()?;
+        let category = [mime.essence_str(), mime.type_().as_str()].into_iter()
+            .find_map(|key| self.mime_categories.get(key))?;
+        Some(category.as_str()).filter(|c| !self.excluded.iter().any(|e| e == c))
+    }
+
+    // Full lookup for a file on disk: every matcher, in precedence order
+    // (by default rules, then name patterns, then extension)
+    pub fn categorize_with_rules(&self, file_name: &str, ext: &str, size: u64, age: Duration) -> Option<&str> {
+        self.categorize_in_order(file_name, ext, Some((size, age)))
+    }
+
+    fn categorize_in_order(&self, file_name: &str, ext: &str, size_age: Option<(u64, Duration)>) -> Option<&str> {
+        self.explain_in_order(file_name, ext, size_age, &mut |_| {}).map(|(category, _)| category)
+    }
+
+    // Same as categorize_with_rules, plus which matcher decided it
+    pub fn categorize_explained(&self, file_name: &str, ext: &str, size: u64, age: Duration) -> Option<(&str, MatchReason)> {
+        self.explain_in_order(file_name, ext, Some((size, age)), &mut |_| {})
+    }
+
+    // Same as categorize_explained, telling `step` about every rule, pattern
+    // and list it tries on the way, one line each
+    pub fn categorize_traced(
+        &self,
+        file_name: &str,
+        ext: &str,
+        size: u64,
+        age: Duration,
+        step: &mut dyn FnMut(std::fmt::Arguments),
+    ) -> Option<(&str, MatchReason)> {
+        self.explain_in_order(file_name, ext, Some((size, age)), step)
+    }
+
+    fn explain_in_order(
+        &self,
+        file_name: &str,
+        ext: &str,
+        size_age: Option<(u64, Duration)>,
+        step: &mut dyn FnMut(std::fmt::Arguments),
+    ) -> Option<(&str, MatchReason)> {
+        self.precedence.resolved().into_iter().find_map(|m| match m {
+            Matcher::Rules => size_age.and_then(|(size, age)| {
+                if self.rules.is_empty() { step(format_args!("rules: none configured")); }
+                self.rules.iter().enumerate().find_map(|(n, rule)| {
+                    let mismatch = rule.mismatch(file_name, ext, size, age);
+                    match mismatch {
+                        Some(key) => step(format_args!("rule {} ({}): no match, fails {key}", n + 1, rule.destination)),
+                        None => step(format_args!("rule {} ({}): matches", n + 1, rule.destination)),
+                    }
+                    mismatch.is_none().then(|| (rule.destination.as_str(), MatchReason::Rule(n + 1)))
+                })
+            }),
+            Matcher::NamePatterns => match self.match_name_glob(file_name) {
+                Some((category, glob)) => {
+                    step(format_args!("name pattern {glob:?} ({category}): matches"));
+                    Some((category, MatchReason::NamePattern(glob.to_string())))
+                }
+                None => {
+                    match self.name_patterns.values().map(Vec::len).sum::<usize>() {
+                        0 => step(format_args!("name patterns: none configured")),
+                        n => step(format_args!("name patterns: none of {n} match")),
+                    }
+                    None
+                }
+            },
+            // A glob in the lists names the file more closely than its
+            // extension does, so it goes first
+            Matcher::Extensions => match self.match_category_glob(file_name) {
+                Some((category, glob)) => {
+                    step(format_args!("category glob {glob:?} ({category}): matches"));
+                    Some((category, MatchReason::NamePattern(glob.to_string())))
+                }
+                None if ext.is_empty() => {
+                    let found = self.filename_category(file_name);
+                    match found {
+                        Some(category) => step(format_args!("filename_categories: {file_name:?} is in {category}")),
+                        None => step(format_args!("filename_categories: no entry for {file_name:?}")),
+                    }
+                    found.map(|c| (c, MatchReason::FileName(file_name.to_string())))
+                }
+                None => {
+                    let found = self.categorize(ext);
+                    match found {
+                        Some(category) => step(format_args!("categories: extension {ext:?} is in {category}")),
+                        None => step(format_args!("categories: no category lists {ext:?}")),
+                    }
+                    found.map(|c| (c, MatchReason::Extension(ext.to_string())))
+                }
+            },
+        })
+    }
+
+    // Find which category's name patterns match a file name (case-insensitive)
+    pub fn match_name(&self, file_name: &str) -> Option<&str> {
+        self.match_name_glob(file_name).map(|(category, _)| category)
+    }
+
+    // The category and the pattern that matched
+    fn match_name_glob(&self, file_name: &str) -> Option<(&str, &str)> {
+        let opts = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+        self.name_patterns.iter().find_map(|(cat, globs)| {
+            let glob = globs.iter().find(|g| glob::Pattern::new(g).is_ok_and(|p| p.matches_with(file_name, opts)))?;
+            Some((cat.as_str(), glob.as_str()))
+        })
+    }
+}
+
+// Read a config file as `T` (a Config, or a raw value to merge), picking
+// the parser from its extension
+fn parse_config_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, OrganizeError> {
+    let invalid = |message: String| OrganizeError::Config { path: path.to_path_buf(), message };
+    let parse: fn(&str) -> Result<T, String> = match file_extension(path).as_deref() {
+        Some("toml") => |t| toml::from_str(t).map_err(|e| e.to_string()),
+        Some("json") => |t| serde_json::from_str(t).map_err(|e| e.to_string()),
+        Some("yaml" | "yml") => |t| serde_yaml::from_str(t).map_err(|e| e.to_string()),
+        _ => return Err(invalid("unsupported config format (use .toml, .json, .yaml or .yml)".into())),
+    };
+    parse(&fs::read_to_string(path).at(path)?).map_err(invalid)
+}
+
+// Lay config file `over` on top of `base`, as load_layered does:
+// - `categories` and `name_patterns` lists are merged per category, the
+//   later file's new entries added after the earlier ones
+// - `companions` are merged the same way
+// - `[[rules]]` from the later file are tried before the earlier ones
+// - other tables (filename_categories, mime_categories, precedence,
+//   keep_newest…) are merged key by key, the later value winning
+// - anything else (layout, age_buckets, case_fold_categories) is replaced
+// Keys a file leaves out keep the earlier value, or the default when no
+// file sets them.
+pub fn merge_config_values(base: &mut serde_json::Value, over: serde_json::Value) {
+    use serde_json::Value;
+    let (Value::Object(base), Value::Object(over)) = (base, over) else { return };
+    for (key, value) in over {
+        let Some(earlier) = base.get_mut(&key) else {
+            base.insert(key, value);
+            continue;
+        };
+        match (key.as_str(), earlier, value) {
+            ("categories" | "name_patterns", Value::Object(lists), Value::Object(more)) => {
+                for (category, entries) in more {
+                    match lists.get_mut(&category) {
+                        Some(existing) => union_values(existing, entries),
+                        None => { lists.insert(category, entries); }
+                    }
+                }
+            }
+            ("companions", existing, entries) => union_values(existing, entries),
+            ("rules", Value::Array(earlier), Value::Array(mut rules)) => {
+                rules.append(earlier);
+                *earlier = rules;
+            }
+            (_, Value::Object(table), Value::Object(more)) => table.extend(more),
+            (_, earlier, value) => *earlier = value,
+        }
+    }
+}
+
+// Add the entries of list `more` missing from `list`; anything that isn't
+// two lists is replaced
+fn union_values(list: &mut serde_json::Value, more: serde_json::Value) {
+    match (list, more) {
+        (serde_json::Value::Array(list), serde_json::Value::Array(more)) => {
+            for entry in more {
+                if !list.contains(&entry) { list.push(entry); }
+            }
+        }
+        (list, more) => *list = more,
     }
 }
 
@@ -55,6 +585,52 @@ impl Default for Config {
                 cat("Music",     &["mp3", "wav", "flac", "aac", "ogg"]),
                 cat("Archives",  &["zip", "rar", "7z", "tar", "gz"]),
             ]),
+            name_patterns: HashMap::new(),
+            rules: Vec::new(),
+            filename_categories: HashMap::new(),
+            precedence: Precedence::default(),
+            mime_categories: HashMap::from([
+                ("image".into(), "Images".into()),
+                ("audio".into(), "Music".into()),
+                ("video".into(), "Videos".into()),
+            ]),
+            companions: Vec::new(),
+            layout: Vec::new(),
+            age_buckets: default_age_buckets(),
+            keep_newest: HashMap::new(),
+            case_fold_categories: false,
+            excluded: Vec::new(),
+            excluded_extensions: HashSet::new(),
+            ext_index: ExtensionIndex::default(),
+        }
+    }
+}
+
+// How --print-config writes the config out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Config {
+    // The config as it stands, after defaults, the file and any command
+    // line changes, in a form load_from reads back. Keys come out sorted.
+    pub fn render(&self, format: ConfigFormat) -> Result<String, String> {
+        match format {
+            ConfigFormat::Toml => toml::Value::try_from(self)
+                .and_then(|v| toml::to_string_pretty(&v))
+                .map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::to_value(self)
+                .and_then(|v| serde_json::to_string_pretty(&v))
+                .map(|text| text + "\n")
+                .map_err(|e| e.to_string()),
+            // Through a JSON value, whose maps are sorted
+            ConfigFormat::Yaml => serde_json::to_value(self)
+                .map_err(|e| e.to_string())
+                .and_then(|v| serde_yaml::to_string(&v).map_err(|e| e.to_string())),
         }
     }
 }
@@ -63,173 +639,2597 @@ fn default_categories() -> HashMap<String, Vec<String>> {
     Config::default().categories
 }
 
+fn default_mime_categories() -> HashMap<String, String> {
+    Config::default().mime_categories
+}
+
 // ── Options & Stats ──────────────────────────
 
+// Umbrella folder for --group-unknown-by-extension (Other/psd/, Other/heic/…)
+pub const UNKNOWN_ROOT: &str = "Other";
+
+// Default folder for --collect-extensionless
+pub const EXTENSIONLESS_ROOT: &str = "NoExtension";
+
+// Umbrella folder for --by-parent (Projects/ClientA/, …)
+pub const PARENT_ROOT: &str = "Projects";
+
+// Which files are compared when looking for duplicates
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DedupeScope {
+    // Any two files in the run
+    #[default]
+    Global,
+    // Only files headed for the same category
+    PerCategory,
+}
+
+// How the extension of a moved file is written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExtensionCase {
+    // Leave it as it was (photo.JPG stays photo.JPG)
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+}
+
+// How a new name is picked when the destination is taken
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollisionScheme {
+    // photo_2026-02-12.jpg, then photo_2026-02-12_v2.jpg, …
+    #[default]
+    DateThenVersion,
+    // photo (1).jpg, photo (2).jpg, … (Finder/Explorer style)
+    Numeric,
+    // photo_20260212_143000.jpg, then photo_20260212_143000_2.jpg, …
+    Timestamp,
+}
+
+#[derive(Clone)]
 pub struct OrganizeOpts {
     pub path: PathBuf,
     pub dry_run: bool,
     pub find_duplicates: bool,
+    pub dedupe_scope: DedupeScope,
+    pub dup_strategy: DuplicateStrategy,
     pub keep_structure: bool,
+    pub extension_case: ExtensionCase,
+    // Name moved files from this template ({date}_{stem}…); see RenameTemplate
+    pub rename_template: Option<RenameTemplate>,
+    // Naming for files whose destination is taken
+    pub collision_scheme: CollisionScheme,
+    // Hash-check copies before deleting the source (cross-device moves)
+    pub verify: bool,
+    // Flush each moved file and the folders it left and entered to disk
+    // before going on; a copy is flushed before its source is deleted
+    pub fsync: bool,
+    // Pause between moves, in milliseconds (0 = no pause)
+    pub throttle_ms: u64,
+    // Cap on files per destination folder; overflow goes to Images_2/, Images_3/… (0 = no cap)
+    pub max_per_folder: usize,
+    // Send files with an unmatched extension to Other/<ext>/ instead of skipping them
+    pub group_unknown: bool,
+    // Gather files without an extension (that no file name matched) in
+    // this folder instead of skipping them
+    pub collect_extensionless: Option<String>,
+    // JSON-lines manifest of every move, used by undo (None = don't write one)
+    pub manifest: Option<PathBuf>,
+    // Human-readable run log, appended to (None = no log)
+    pub log_file: Option<PathBuf>,
+    // Don't print progress to stdout/stderr
+    pub quiet: bool,
+    // JSON index of new path -> original path, merged into across runs
+    pub index_out: Option<PathBuf>,
+    // Before the first real run, list every file in the target here (see
+    // take_snapshot), for `restore`; an existing snapshot is kept as is
+    pub snapshot: Option<PathBuf>,
+    // Explicit list of files to organize instead of scanning `path`
+    pub file_list: Option<Vec<PathBuf>>,
+    // Shell command run after each move; {src} and {dst} become quoted absolute paths
+    pub on_move: Option<String>,
+    // Copy each file here (keeping its relative path) before moving it
+    pub backup_dir: Option<PathBuf>,
+    // Dry-run prints at most this many planned moves (0 = all of them)
+    pub preview_limit: usize,
+    // Folder inside `path` that holds every category, e.g. ".organized"
+    pub root: Option<PathBuf>,
+    // Record each moved file's SHA-256 in the log and manifest
+    pub hash_log: bool,
+    // With keep_structure, fold subfolders into existing ones that differ
+    // only in case or Unicode form
+    pub merge_into_existing: bool,
+    // Sort plain-text files into <category>/<Language>/ by their contents
+    pub detect_language: bool,
+    // Sort pictures into <category>/<Orientation or resolution tier>/
+    pub image_split: Option<ImageSplit>,
+    // Dry-run only: build the planned layout here out of links to the originals
+    pub preview_tree_dir: Option<PathBuf>,
+    // Treat `Photo.jpg` and `photo.jpg` as the same name when picking
+    // destinations (on by default on macOS and Windows)
+    pub case_insensitive_names: bool,
+    // Make destination names fit FAT, exFAT and NTFS (see sanitize_filename)
+    // even when the destination volume isn't one
+    pub sanitize_names: bool,
+    // Route archives by what's inside: a zip of photos -> Images/Archives/
+    pub peek_archives: bool,
+    // Also print a line for every file left in place
+    pub verbose: bool,
+    // Only organize the loose files directly in `path`, not in subfolders
+    pub no_recurse: bool,
+    // Extra free space (bytes) that must remain after copying into backup_dir
+    pub min_free: u64,
+    // Unix: give copied files their original owner and group (needs privileges)
+    pub preserve_owner: bool,
+    // Continue the latest run in the manifest, skipping files it already moved
+    pub resume: bool,
+    // Fall back to the extension's MIME type when no matcher fits
+    pub mime_fallback: bool,
+    // Group files by the folder they sit in (Projects/<parent>/) instead of
+    // by extension; files directly in `path` are categorized as usual
+    pub by_parent: bool,
+    // Hard-link files into the category folders instead of moving them; the
+    // originals stay put and no space is used (copies across filesystems)
+    pub hardlink: bool,
+    // After moving a file, leave a symlink at its old path pointing to the
+    // new one, for tools that still look for it there
+    pub leave_symlink: bool,
+    // After the run, collapse single-child folder chains in the categories
+    // that got files, keeping this many levels of each (0 = off)
+    pub collapse_chains: usize,
+    // When the destination name is taken by a file with the same contents,
+    // leave the source alone instead of moving it in under a new name
+    pub skip_identical: bool,
+    // Leave files another program has open instead of failing on them
+    pub skip_locked: bool,
+    // Steps that build each file's folder, e.g. [ByDate(Year), Category]
+    // for 2024/Images/; empty means the category (with --date-from-name's dates)
+    pub layout: Vec<LayoutStep>,
+    // Folders of the age_bucket layout step, newest first
+    pub age_buckets: Vec<AgeBucket>,
+    // --mapping: explicit categories for listed files (see read_mapping),
+    // checked before anything else
+    pub mapping: HashMap<PathBuf, String>,
+    // Pack files at least this old into Archive/<category>_<year>.zip
+    // instead of moving them loose
+    pub archive_older_than: Option<Duration>,
+    // Leave files modified less than this long ago where they are; they may
+    // still be in use
+    pub quiet_period: Option<Duration>,
+    // With find_duplicates: runs over this many files keep fingerprints on
+    // disk instead of in memory (0 = always in memory)
+    pub dedupe_disk_threshold: usize,
+    // Stops the run at the next file (or hashing chunk) once cancelled
+    pub cancel: CancelToken,
+    // Keep only this many of the newest files of each extension...
+    pub keep_newest: Option<usize>,
+    // ...or of these extensions (lowercase, no dot), which win over it
+    pub keep_newest_per_ext: HashMap<String, usize>,
+    // What happens to the older ones
+    pub keep_newest_action: RetentionAction,
+    // How files the run removes are removed (--keep-newest, and originals
+    // packed by --archive-older-than): the size of batch that needs a
+    // go-ahead, and whether they go to the trash
+    pub delete_policy: DeletePolicy,
+    // Asked for that go-ahead with a description like "35 files (2.1 GB)";
+    // the default says no, so an embedder never deletes a big batch unasked
+    pub confirm_delete: fn(&str) -> bool,
+    // Sort into <category>/<YYYY>/<MM>/ by the date in the file name,
+    // falling back to the modification time
+    pub date_from_name: bool,
+    // How many of the largest moved files to keep in Stats::largest (0 = none)
+    pub top_files: usize,
+    // Allow targets is_dangerous_target() refuses (home, /, system folders)
+    pub force: bool,
+    // Only sort extensions found at least this many times in the run (0 = no minimum)
+    pub min_ext_count: usize,
+    // ...and at most this many times (0 = no maximum)
+    pub max_ext_count: usize,
+    // Only create a category folder that at least this many files would go
+    // into; the files of smaller ones stay put (0 = no minimum)
+    pub min_files_to_create_folder: usize,
+}
+
+// Defaults match the CLI: current directory, log in the working directory,
+// every option off
+impl Default for OrganizeOpts {
+    fn default() -> Self {
+        OrganizeOpts {
+            path: PathBuf::from("."),
+            dry_run: false,
+            find_duplicates: false,
+            dedupe_scope: DedupeScope::Global,
+            dup_strategy: DuplicateStrategy::NameDateSize,
+            keep_structure: false,
+            extension_case: ExtensionCase::Preserve,
+            rename_template: None,
+            collision_scheme: CollisionScheme::DateThenVersion,
+            verify: false,
+            fsync: false,
+            throttle_ms: 0,
+            max_per_folder: 0,
+            group_unknown: false,
+            collect_extensionless: None,
+            manifest: None,
+            log_file: Some(PathBuf::from("organizer_log.txt")),
+            quiet: false,
+            index_out: None,
+            snapshot: None,
+            file_list: None,
+            on_move: None,
+            backup_dir: None,
+            preview_limit: 0,
+            root: None,
+            hash_log: false,
+            merge_into_existing: false,
+            detect_language: false,
+            image_split: None,
+            preview_tree_dir: None,
+            case_insensitive_names: CASE_INSENSITIVE_FS,
+            sanitize_names: false,
+            peek_archives: false,
+            verbose: false,
+            no_recurse: false,
+            min_free: 0,
+            preserve_owner: false,
+            resume: false,
+            mime_fallback: false,
+            force: false,
+            top_files: 0,
+            date_from_name: false,
+            skip_identical: false,
+            skip_locked: false,
+            layout: Vec::new(),
+            age_buckets: default_age_buckets(),
+            mapping: HashMap::new(),
+            archive_older_than: None,
+            quiet_period: None,
+            dedupe_disk_threshold: DEDUPE_DISK_THRESHOLD,
+            cancel: CancelToken::new(),
+            keep_newest: None,
+            keep_newest_per_ext: HashMap::new(),
+            keep_newest_action: RetentionAction::Trash,
+            delete_policy: DeletePolicy::default(),
+            confirm_delete: |_| false,
+            collapse_chains: 0,
+            hardlink: false,
+            leave_symlink: false,
+            by_parent: false,
+            min_ext_count: 0,
+            max_ext_count: 0,
+            min_files_to_create_folder: 0,
+        }
+    }
+}
+
+impl OrganizeOpts {
+    // Start from the defaults and set only what you need:
+    // OrganizeOpts::builder().path(dir).dry_run(true).build()
+    pub fn builder() -> OrganizeOptsBuilder {
+        OrganizeOptsBuilder { opts: OrganizeOpts::default() }
+    }
+
+    // The delete policy for --keep-newest: --keep-newest-action trash sends
+    // files to the trash whatever the policy says; delete goes by it, so
+    // --trash still wins
+    pub fn prune_policy(&self) -> DeletePolicy {
+        let to_trash = self.delete_policy.to_trash || self.keep_newest_action == RetentionAction::Trash;
+        DeletePolicy { to_trash, ..self.delete_policy.clone() }
+    }
+}
+
+pub struct OrganizeOptsBuilder {
+    opts: OrganizeOpts,
+}
+
+impl OrganizeOptsBuilder {
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self { self.opts.path = path.into(); self }
+    pub fn dry_run(mut self, on: bool) -> Self { self.opts.dry_run = on; self }
+    pub fn find_duplicates(mut self, on: bool) -> Self { self.opts.find_duplicates = on; self }
+    pub fn dedupe_scope(mut self, scope: DedupeScope) -> Self { self.opts.dedupe_scope = scope; self }
+    pub fn dup_strategy(mut self, strategy: DuplicateStrategy) -> Self { self.opts.dup_strategy = strategy; self }
+    pub fn keep_structure(mut self, on: bool) -> Self { self.opts.keep_structure = on; self }
+    pub fn extension_case(mut self, case: ExtensionCase) -> Self { self.opts.extension_case = case; self }
+    pub fn rename_template(mut self, template: RenameTemplate) -> Self { self.opts.rename_template = Some(template); self }
+    pub fn collision_scheme(mut self, scheme: CollisionScheme) -> Self { self.opts.collision_scheme = scheme; self }
+    pub fn verify(mut self, on: bool) -> Self { self.opts.verify = on; self }
+    pub fn fsync(mut self, on: bool) -> Self { self.opts.fsync = on; self }
+    pub fn throttle_ms(mut self, ms: u64) -> Self { self.opts.throttle_ms = ms; self }
+    pub fn max_per_folder(mut self, cap: usize) -> Self { self.opts.max_per_folder = cap; self }
+    pub fn group_unknown(mut self, on: bool) -> Self { self.opts.group_unknown = on; self }
+    pub fn collect_extensionless(mut self, folder: impl Into<String>) -> Self { self.opts.collect_extensionless = Some(folder.into()); self }
+    pub fn manifest(mut self, path: impl Into<PathBuf>) -> Self { self.opts.manifest = Some(path.into()); self }
+    pub fn log_file(mut self, path: Option<PathBuf>) -> Self { self.opts.log_file = path; self }
+    pub fn quiet(mut self, on: bool) -> Self { self.opts.quiet = on; self }
+    pub fn index_out(mut self, path: impl Into<PathBuf>) -> Self { self.opts.index_out = Some(path.into()); self }
+    pub fn snapshot(mut self, path: impl Into<PathBuf>) -> Self { self.opts.snapshot = Some(path.into()); self }
+    pub fn file_list(mut self, files: Vec<PathBuf>) -> Self { self.opts.file_list = Some(files); self }
+    pub fn on_move(mut self, cmd: impl Into<String>) -> Self { self.opts.on_move = Some(cmd.into()); self }
+    pub fn backup_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.opts.backup_dir = Some(dir.into()); self }
+    pub fn preview_limit(mut self, lines: usize) -> Self { self.opts.preview_limit = lines; self }
+    pub fn root(mut self, dir: impl Into<PathBuf>) -> Self { self.opts.root = Some(dir.into()); self }
+    pub fn hash_log(mut self, on: bool) -> Self { self.opts.hash_log = on; self }
+    pub fn merge_into_existing(mut self, on: bool) -> Self { self.opts.merge_into_existing = on; self }
+    pub fn detect_language(mut self, on: bool) -> Self { self.opts.detect_language = on; self }
+    pub fn image_split(mut self, split: ImageSplit) -> Self { self.opts.image_split = Some(split); self }
+    pub fn preview_tree_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.opts.preview_tree_dir = Some(dir.into()); self }
+    pub fn case_insensitive_names(mut self, on: bool) -> Self { self.opts.case_insensitive_names = on; self }
+    pub fn sanitize_names(mut self, on: bool) -> Self { self.opts.sanitize_names = on; self }
+    pub fn peek_archives(mut self, on: bool) -> Self { self.opts.peek_archives = on; self }
+    pub fn verbose(mut self, on: bool) -> Self { self.opts.verbose = on; self }
+    pub fn no_recurse(mut self, on: bool) -> Self { self.opts.no_recurse = on; self }
+    pub fn min_free(mut self, bytes: u64) -> Self { self.opts.min_free = bytes; self }
+    pub fn preserve_owner(mut self, on: bool) -> Self { self.opts.preserve_owner = on; self }
+    pub fn resume(mut self, on: bool) -> Self { self.opts.resume = on; self }
+    pub fn by_parent(mut self, on: bool) -> Self { self.opts.by_parent = on; self }
+    pub fn hardlink(mut self, on: bool) -> Self { self.opts.hardlink = on; self }
+    pub fn leave_symlink(mut self, on: bool) -> Self { self.opts.leave_symlink = on; self }
+    pub fn collapse_chains(mut self, keep: usize) -> Self { self.opts.collapse_chains = keep; self }
+    pub fn skip_identical(mut self, on: bool) -> Self { self.opts.skip_identical = on; self }
+    pub fn skip_locked(mut self, on: bool) -> Self { self.opts.skip_locked = on; self }
+    pub fn layout(mut self, steps: Vec<LayoutStep>) -> Self { self.opts.layout = steps; self }
+    pub fn age_buckets(mut self, buckets: Vec<AgeBucket>) -> Self { self.opts.age_buckets = buckets; self }
+    pub fn mapping(mut self, mapping: HashMap<PathBuf, String>) -> Self { self.opts.mapping = mapping; self }
+    pub fn archive_older_than(mut self, age: Duration) -> Self { self.opts.archive_older_than = Some(age); self }
+    pub fn keep_newest(mut self, n: usize) -> Self { self.opts.keep_newest = Some(n); self }
+    pub fn keep_newest_per_ext(mut self, counts: HashMap<String, usize>) -> Self { self.opts.keep_newest_per_ext = counts; self }
+    pub fn keep_newest_action(mut self, action: RetentionAction) -> Self { self.opts.keep_newest_action = action; self }
+    pub fn delete_policy(mut self, policy: DeletePolicy) -> Self { self.opts.delete_policy = policy; self }
+    pub fn confirm_delete(mut self, confirm: fn(&str) -> bool) -> Self { self.opts.confirm_delete = confirm; self }
+    pub fn quiet_period(mut self, period: Duration) -> Self { self.opts.quiet_period = Some(period); self }
+    pub fn dedupe_disk_threshold(mut self, files: usize) -> Self { self.opts.dedupe_disk_threshold = files; self }
+    pub fn cancel(mut self, token: CancelToken) -> Self { self.opts.cancel = token; self }
+    pub fn date_from_name(mut self, on: bool) -> Self { self.opts.date_from_name = on; self }
+    pub fn top_files(mut self, n: usize) -> Self { self.opts.top_files = n; self }
+    pub fn force(mut self, on: bool) -> Self { self.opts.force = on; self }
+    pub fn mime_fallback(mut self, on: bool) -> Self { self.opts.mime_fallback = on; self }
+    pub fn min_ext_count(mut self, n: usize) -> Self { self.opts.min_ext_count = n; self }
+    pub fn max_ext_count(mut self, n: usize) -> Self { self.opts.max_ext_count = n; self }
+    pub fn min_files_to_create_folder(mut self, n: usize) -> Self { self.opts.min_files_to_create_folder = n; self }
+
+    pub fn build(self) -> OrganizeOpts {
+        self.opts
+    }
+}
+
+// Print a progress line unless the caller asked for silence
+macro_rules! say {
+    ($opts:expr, $($arg:tt)*) => { if !$opts.quiet { println!($($arg)*); } };
 }
 
+#[derive(Debug, Default, Clone)]
 pub struct Stats {
     pub moved: usize,
     pub duplicates: usize,
     pub skipped: usize,
     pub errors: usize,
+    // Bytes moved (or that would be moved) into each category
+    pub bytes_by_category: HashMap<String, u64>,
+    // Every file counted in `errors`, with what went wrong
+    pub failures: Vec<(PathBuf, String)>,
+    // Bytes copied into --backup-dir
+    pub backup_bytes: u64,
+    // Under --dry-run: bytes the run would copy rather than move (the
+    // --backup-dir copies, and --hardlink files bound for another
+    // filesystem), so how much more disk it would take up
+    pub would_copy_bytes: u64,
+    // Total size of the files skipped as duplicates of others in the run:
+    // the space keeping one copy saves
+    pub duplicate_bytes: u64,
+    // Every file the run looked at but left where it was, and why
+    pub untouched: Vec<(PathBuf, SkipReason)>,
+    // With --top-files: the biggest files moved (size, new path), biggest first
+    pub largest: Vec<(u64, PathBuf)>,
+    // Files moved under a new name because theirs was taken
+    pub renamed: usize,
+    // Files left alone because another program had them open (--skip-locked)
+    pub locked: usize,
+    // Files packed into archives by --archive-older-than
+    pub archived: usize,
+    // Files left for a later run by --quiet-period
+    pub recent: usize,
+    // Files left because their category folder would have held fewer than
+    // --min-files-to-create-folder files
+    pub sparse: usize,
+    // Files trashed or deleted by --keep-newest (archived ones count in
+    // `archived`)
+    pub pruned: usize,
+    // Bytes read by the --dup-strategy content hashing pass
+    pub hashed_bytes: u64,
+    // The run was cancelled (see CancelToken) before it got through every
+    // file
+    pub cancelled: bool,
+}
+
+// Why a file was left in place
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipReason {
+    // Name starts with a dot
+    Hidden,
+    // OS litter or the organizer's own files (Thumbs.db, organizer_log.txt…)
+    Junk,
+    // No extension and no filename_categories entry
+    NoExtension,
+    // Extension isn't in any category
+    Unmatched,
+    // Belongs to a category switched off with --exclude/--only-category
+    Excluded,
+    // Same fingerprint as a file seen earlier in the run
+    Duplicate,
+    // --resume: its destination already holds a file of the same size
+    AlreadyMoved,
+    // Its extension occurs fewer than --min-ext-count or more than --max-ext-count times
+    ExtensionCount,
+    // --skip-identical: the destination already holds the same contents
+    Identical,
+    // --skip-locked: another program has it open
+    Locked,
+    // A sidecar whose main file stayed put
+    Companion,
+    // --quiet-period: modified too recently to be sorted yet
+    TooRecent,
+    // --min-files-to-create-folder: too few files for its category to get
+    // a folder
+    SparseCategory,
+    // Not a regular file: a named pipe, socket or device. Reading one can
+    // block forever, and moving it means nothing to the user.
+    Special,
+    // A symlink to a file inside the target, such as one --leave-symlink
+    // left behind; the file it points to is sorted on its own
+    Symlink,
+}
+
+impl SkipReason {
+    // Short stable code for reports
+    pub fn code(self) -> &'static str {
+        match self {
+            SkipReason::Hidden => "hidden",
+            SkipReason::Junk => "junk",
+            SkipReason::NoExtension => "no-extension",
+            SkipReason::Unmatched => "unmatched-extension",
+            SkipReason::Excluded => "excluded",
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::AlreadyMoved => "already-moved",
+            SkipReason::ExtensionCount => "extension-count",
+            SkipReason::Identical => "identical",
+            SkipReason::Locked => "in-use",
+            SkipReason::Companion => "companion",
+            SkipReason::TooRecent => "too-recent",
+            SkipReason::SparseCategory => "sparse-category",
+            SkipReason::Special => "special-file",
+            SkipReason::Symlink => "symlink",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Stats {
+    // Whether the run moved (or under --dry-run, would move) anything:
+    // files moved, packed into archives or removed by --keep-newest
+    pub fn has_changes(&self) -> bool {
+        self.moved > 0 || self.archived > 0 || self.pruned > 0
+    }
+
+    // Turn a run with failed files into an error, for callers that want
+    // all-or-nothing semantics
+    pub fn into_result(self) -> Result<Stats, OrganizeError> {
+        if self.failures.is_empty() {
+            Ok(self)
+        } else {
+            Err(OrganizeError::PartialRun { failures: self.failures })
+        }
+    }
+
+    // Leave a file where it is: count it, remember why, and with --verbose
+    // say so in a dim line
+    fn leave(&mut self, opts: &OrganizeOpts, path: &Path, reason: SkipReason) {
+        match reason {
+            SkipReason::Duplicate | SkipReason::Identical => self.duplicates += 1,
+            SkipReason::Locked => self.locked += 1,
+            SkipReason::TooRecent => self.recent += 1,
+            SkipReason::SparseCategory => self.sparse += 1,
+            SkipReason::Hidden | SkipReason::Junk => {}
+            _ => self.skipped += 1,
+        }
+        if opts.verbose {
+            let shown = path.strip_prefix(&opts.path).unwrap_or(path).display();
+            say!(opts, "  {} {}", "·".dimmed(), format!("{shown} ({reason})").dimmed());
+        }
+        self.untouched.push((path.to_path_buf(), reason));
+    }
+
+    // Keep `path` among the `cap` largest moves; ties keep the earlier file
+    fn note_size(&mut self, cap: usize, bytes: u64, path: &Path) {
+        let at = self.largest.partition_point(|(b, _)| *b >= bytes);
+        if at < cap {
+            self.largest.insert(at, (bytes, path.to_path_buf()));
+            self.largest.truncate(cap);
+        }
+    }
+
+    // Report a per-file failure and keep going with the rest of the run
+    fn fail(&mut self, quiet: bool, path: &Path, shown: impl std::fmt::Display, e: impl std::fmt::Display) {
+        if !quiet { eprintln!("  {} {} — {}", "✗".red(), shown, e); }
+        self.errors += 1;
+        self.failures.push((path.to_path_buf(), e.to_string()));
+    }
 }
 
 // ── Main organize function ───────────────────
 // Scans a folder, sorts files into category subfolders
 
-pub fn organize(opts: &OrganizeOpts, config: &Config) -> std::io::Result<Stats> {
+// Owned-argument entry point for embedding: the config is whatever the caller
+// built (config.toml is never read). With `log_file: None` and `quiet: true`
+// nothing is written outside `opts.path` and nothing is printed.
+pub fn organize_with(config: Config, opts: OrganizeOpts) -> Result<Stats, OrganizeError> {
+    organize(&opts, &config)
+}
+
+// `categorizer` decides each file's folder; pass a `&Config` for the usual
+// config.toml behaviour, or any other `Categorizer`.
+pub fn organize(opts: &OrganizeOpts, categorizer: &dyn Categorizer) -> Result<Stats, OrganizeError> {
+    organize_with_events(opts, categorizer, &mut |_| {})
+}
+
+// organize(), reporting progress to `events` as it goes (see OrganizeEvent)
+pub fn organize_with_events(
+    opts: &OrganizeOpts,
+    categorizer: &dyn Categorizer,
+    events: &mut dyn FnMut(OrganizeEvent),
+) -> Result<Stats, OrganizeError> {
     let base = &opts.path;
-    let category_names: Vec<&str> = config.categories.keys().map(String::as_str).collect();
 
-    // Find all files in the folder (recursively)
-    let files = collect_files(base, &category_names)?;
+    // Home, / and system folders are never sorted by accident
+    if !opts.force {
+        let target = fs::canonicalize(base).or_else(|_| std::path::absolute(base)).at(base)?;
+        if is_dangerous_target(&target) {
+            return Err(OrganizeError::Options(format!(
+                "{} looks like a home, root or system folder; pass --force if you really mean it",
+                target.display(),
+            )));
+        }
+    }
+
+    if opts.date_from_name && !opts.layout.is_empty() {
+        return Err(OrganizeError::Options("--date-from-name and --layout can't be combined; add a by_date step to the layout".into()));
+    }
+    if opts.by_parent && opts.keep_structure {
+        return Err(OrganizeError::Options("--by-parent and --keep-structure can't be combined".into()));
+    }
+    if opts.max_ext_count > 0 && opts.min_ext_count > opts.max_ext_count {
+        return Err(OrganizeError::Options(format!(
+            "--min-ext-count {} is larger than --max-ext-count {}", opts.min_ext_count, opts.max_ext_count,
+        )));
+    }
+
+    // A backup or preview tree inside the target would be scanned (and
+    // sorted) next run
+    for (what, dir) in [("backup dir", &opts.backup_dir), ("preview tree dir", &opts.preview_tree_dir)] {
+        let Some(dir) = dir else { continue };
+        if std::path::absolute(dir).at(dir)?.starts_with(std::path::absolute(base).at(base)?) {
+            return Err(OrganizeError::Options(format!(
+                "{what} {} is inside {}; pick a folder outside the target",
+                dir.display(), base.display(),
+            )));
+        }
+    }
+
+    // Spill folders (Images_2/, …) from earlier capped runs are sorted output too
+    let mut skip_dirs = categorizer.folder_names();
+    if opts.max_per_folder > 0 {
+        skip_dirs.extend(spill_dirs(base, &skip_dirs)?);
+    }
+    if opts.group_unknown {
+        skip_dirs.push(UNKNOWN_ROOT.to_string());
+    }
+    if let Some(folder) = &opts.collect_extensionless {
+        skip_dirs.push(folder.clone());
+    }
+    if opts.by_parent {
+        skip_dirs.push(PARENT_ROOT.to_string());
+    }
+    skip_dirs.extend(opts.mapping.values().cloned());
+    if opts.archive_older_than.is_some() || (retains(opts) && opts.keep_newest_action == RetentionAction::Archive) {
+        skip_dirs.push(ARCHIVE_ROOT.to_string());
+    }
+    // Everything under --root is sorted output (a dot-root is skipped anyway)
+    if let Some(root) = &opts.root {
+        skip_dirs.extend(root_inside(base, root)?);
+    }
+    let category_names: Vec<&str> = skip_dirs.iter().map(String::as_str).collect();
+
+    // The layout before anything moves, unless an earlier run recorded it
+    if let Some(index) = opts.snapshot.as_ref().filter(|p| !opts.dry_run && !p.exists()) {
+        let snapshot = take_snapshot(base, Some(index))?;
+        write_snapshot(&snapshot, index)?;
+        say!(opts, "Snapshot of {} file(s) saved to {}", snapshot.files.len(), index.display());
+    }
+
+    // Use the given file list, or find all files in the folder (recursively)
+    let files = match &opts.file_list {
+        Some(list) => list.clone(),
+        None => collect_files_with(base, &category_names, !opts.no_recurse)?,
+    };
+    // A snapshot kept inside the target is the organizer's own file
+    let files: Vec<PathBuf> = match opts.snapshot.as_ref().and_then(|p| p.canonicalize().ok()) {
+        Some(index) => files.into_iter()
+            .filter(|f| f.file_name() != index.file_name() || f.canonicalize().ok().as_ref() != Some(&index))
+            .collect(),
+        None => files,
+    };
     if files.is_empty() {
-        println!("No files to organize.");
-        return Ok(Stats { moved: 0, duplicates: 0, skipped: 0, errors: 0 });
-    }
-    println!("Found {} file(s)\n", files.len());
-
-    // Open log file (only in real mode)
-    let mut log = if !opts.dry_run {
-        let f = fs::OpenOptions::new().create(true).append(true).open("organizer_log.txt")?;
-        let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
-        let mut f = f;
-        writeln!(f, "\n{0}\nRun started:  {ts}\nDirectory:    {1}\nDry-run:      {2}\n{0}\n",
-            "=".repeat(40), base.display(), opts.dry_run)?;
-        Some(f)
+        say!(opts, "No files to organize.");
+        events(OrganizeEvent::Started { total: 0 });
+        events(OrganizeEvent::Finished { stats: Stats::default() });
+        return Ok(Stats::default());
+    }
+    say!(opts, "Found {} file(s)\n", files.len());
+
+    // Moves within a volume need no space, but backup copies do
+    if let Some(backup) = opts.backup_dir.as_ref().filter(|_| !opts.dry_run) {
+        let needed: u64 = files.iter().filter_map(|f| fs::metadata(f).ok()).map(|m| m.len()).sum();
+        check_free_space(backup, needed, opts.min_free)?;
+    }
+
+    // How often each extension occurs, for --min/--max-ext-count
+    let ext_counts = if opts.min_ext_count > 0 || opts.max_ext_count > 0 {
+        count_extensions(&files)
     } else {
-        None
+        HashMap::new()
     };
 
-    let mut stats = Stats { moved: 0, duplicates: 0, skipped: 0, errors: 0 };
-    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    // Log, manifest and index are opened here, and only in real mode
+    let mut mover = Mover::open(opts, events)?;
+    (mover.events)(OrganizeEvent::Started { total: files.len() });
 
-    for file_path in &files {
-        // Skip hidden/junk files
-        if is_hidden_or_junk(file_path) { continue; }
+    let mut stats = Stats::default();
+    // One clock reading for the whole run, so age buckets agree
+    let now = SystemTime::now();
+    let mut seen = SeenStore::for_run(if opts.find_duplicates { files.len() } else { 0 }, opts.dedupe_disk_threshold)
+        .map_err(OrganizeError::Other)?;
+    let mut folder_counts: HashMap<PathBuf, usize> = HashMap::new();
+    // Files named into each folder so far, for {counter}
+    let mut counters: HashMap<PathBuf, usize> = HashMap::new();
+    let mut reserved: HashSet<PathBuf> = HashSet::new();
+    // Category folders that received files, for --collapse-chains
+    let mut filled: BTreeSet<PathBuf> = BTreeSet::new();
+    // --min-files-to-create-folder: files held back per new category folder,
+    // and the new folders that got enough of them
+    let mut held: BTreeMap<PathBuf, Vec<Destined>> = BTreeMap::new();
+    let mut opened: HashSet<PathBuf> = HashSet::new();
+    // --keep-newest: files to trash or delete, in one batch after the loop
+    let mut to_prune: Vec<PathBuf> = Vec::new();
+    // --archive-older-than: files waiting to go into each zip (source, entry name)
+    let mut to_archive: BTreeMap<PathBuf, Vec<(PathBuf, String)>> = BTreeMap::new();
+    // Sidecars ride along with their main file instead of being sorted alone
+    let (files, mut companions) = group_companions(&files, &categorizer.companion_extensions());
 
-        // Get file extension, skip if none
-        let ext = match file_path.extension() {
-            Some(e) => e.to_string_lossy().to_lowercase(),
-            None => { stats.skipped += 1; continue; }
+    // Category folders go directly in the base, or under --root
+    let out_base = match &opts.root {
+        Some(root) => base.join(root),
+        None => base.to_path_buf(),
+    };
+    // What the destination volume can store: names are fitted to a FAT,
+    // exFAT or NTFS volume, and matched case-insensitively on FAT and exFAT
+    let volume = probe_volume(&out_base);
+    let sanitize = opts.sanitize_names || volume.windows_names;
+    let case_insensitive = opts.case_insensitive_names || volume.case_insensitive;
+    if let Some(fs_type) = volume.fs_type.as_ref().filter(|_| volume.windows_names) {
+        say!(opts, "{} {} is on {}: names with characters it can't store will be changed\n", "ℹ".blue(), out_base.display(), fs_type);
+    }
+
+    // --keep-newest: the files past each extension's count, over the whole
+    // run. Only files the run wouldn't leave alone anyway are counted.
+    let expired: HashSet<PathBuf> = if retains(opts) {
+        let candidates: Vec<PathBuf> = files.iter().filter(|f| prunable(opts, categorizer, now, f)).cloned().collect();
+        expired_files(&candidates, |ext| keep_count(opts.keep_newest, &opts.keep_newest_per_ext, ext)).into_iter().collect()
+    } else {
+        HashSet::new()
+    };
+    // Symlinks into here stand in for files sorted on their own
+    let real_base = fs::canonicalize(base).ok();
+    // --dup-strategy content: every file that could be a copy is read now,
+    // while cancelling still leaves everything where it was
+    let hashes = if opts.find_duplicates && opts.dup_strategy == DuplicateStrategy::Content {
+        let candidates: Vec<PathBuf> = files.iter()
+            .filter(|f| !is_hidden_or_junk(f) && !expired.contains(*f))
+            .cloned()
+            .collect();
+        let terminal = !opts.quiet && std::io::stderr().is_terminal();
+        let mut shown = None;
+        let hashes = ContentHashes::compute(&candidates, &opts.cancel, &mut |done, total| {
+            stats.hashed_bytes = done;
+            (mover.events)(OrganizeEvent::Hashing { done, total });
+            let percent = (done * 100).checked_div(total).unwrap_or(100);
+            if terminal && shown != Some(percent) {
+                shown = Some(percent);
+                eprint!("\rHashing for duplicates: {} / {} ({percent}%)", format_bytes(done), format_bytes(total));
+            }
+        });
+        if shown.is_some() { eprintln!(); }
+        let Some(hashes) = hashes else {
+            stats.cancelled = true;
+            say!(opts, "{}", "Cancelled while hashing; nothing was moved.".yellow());
+            mover.finish(&stats)?;
+            return Ok(stats);
         };
+        if stats.hashed_bytes > 0 {
+            say!(opts, "Hashed {} to look for duplicates\n", format_bytes(stats.hashed_bytes));
+        }
+        hashes
+    } else {
+        ContentHashes::default()
+    };
 
-        let meta = fs::metadata(file_path)?;
-        let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+    for file_path in &files {
+        mover.report(&stats);
+        if opts.cancel.is_cancelled() {
+            stats.cancelled = true;
+            break;
+        }
 
-        // Check for duplicates (same name + date + size = duplicate)
-        if opts.find_duplicates {
-            let date = chrono::DateTime::<Local>::from(meta.modified()?)
-                .format("%Y-%m-%d").to_string();
-            let key = format!("{file_name}|{date}|{}", meta.len());
+        // Skip hidden/junk files
+        if is_hidden_or_junk(file_path) {
+            stats.leave(opts, file_path, if is_junk(file_path) { SkipReason::Junk } else { SkipReason::Hidden });
+            continue;
+        }
 
-            if let Some(original) = seen.get(&key) {
-                println!("{} {} (duplicate of {})", "⚠ SKIP:".yellow(), file_name, original.display());
-                stats.duplicates += 1;
+        // Get file extension (empty for extensionless files)
+        let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+        let ext = file_extension(file_path).unwrap_or_default();
+        if let Some(&n) = ext_counts.get(&ext) {
+            if n < opts.min_ext_count || (opts.max_ext_count > 0 && n > opts.max_ext_count) {
+                stats.leave(opts, file_path, SkipReason::ExtensionCount);
                 continue;
             }
-            seen.insert(key, file_path.clone());
         }
 
-        // Find category for this extension
-        let category = match config.categorize(&ext) {
-            Some(c) => c,
-            None => { stats.skipped += 1; continue; }
+        // The file may have changed or vanished since it was collected;
+        // that only fails this file, not the whole run
+        let src = file_path.strip_prefix(base).unwrap_or(file_path).display();
+        let meta = match fs::metadata(file_path) {
+            Ok(m) => m,
+            Err(e) => { stats.fail(opts.quiet, file_path, src, e); continue; }
         };
+        if !meta.is_file() {
+            say!(opts, "  {} {}", "⚠".yellow(), format!("{src} is a pipe, socket or device; left alone").yellow());
+            stats.leave(opts, file_path, SkipReason::Special);
+            continue;
+        }
+        if links_inside(file_path, real_base.as_deref()) {
+            stats.leave(opts, file_path, SkipReason::Symlink);
+            continue;
+        }
+        if let Some(period) = opts.quiet_period {
+            if in_quiet_period(now, meta.modified().unwrap_or(now), period) {
+                stats.leave(opts, file_path, SkipReason::TooRecent);
+                continue;
+            }
+        }
+        if expired.contains(file_path) {
+            if opts.keep_newest_action == RetentionAction::Archive {
+                let modified = meta.modified().unwrap_or(now);
+                let year = chrono::Datelike::year(&chrono::DateTime::<Local>::from(modified));
+                let zip = out_base.join(ARCHIVE_ROOT).join(archive_name(&ext, year));
+                let entry = file_path.strip_prefix(base).unwrap_or(file_path).to_string_lossy().replace('\\', "/");
+                to_archive.entry(zip).or_default().push((file_path.clone(), entry));
+            } else {
+                to_prune.push(file_path.clone());
+            }
+            continue;
+        }
 
-        // Build destination path
-        let dest_dir = if opts.keep_structure {
-            let rel = file_path.strip_prefix(base).unwrap_or(file_path);
-            match rel.parent() {
-                Some(p) if p.components().next().is_some() => base.join(category).join(p),
-                _ => base.join(category),
+        // Find category for this file (with a Config: rules, name patterns,
+        // then extension; extensionless files only by exact name)
+        let matched = match categorizer.explain(file_path, &ext, &meta) {
+            None if opts.mime_fallback && !ext.is_empty() && !categorizer.is_excluded(&ext) => {
+                let mime = mime_guess::from_ext(&ext).first_raw().unwrap_or_default();
+                categorizer.mime_category(&ext).map(|c| (c, MatchReason::Mime(mime.to_string())))
             }
-        } else {
-            base.join(category)
+            m => m,
         };
+        // --by-parent: the folder a file sits in beats its extension
+        let matched = match opts.by_parent.then(|| parent_category(base, file_path)).flatten() {
+            Some(by_parent) => Some((by_parent, MatchReason::Parent)),
+            None => matched,
+        };
+        // --mapping: an explicit entry beats every other matcher
+        let matched = match opts.mapping.get(file_path) {
+            Some(mapped) => Some((mapped.clone(), MatchReason::Mapping)),
+            None => matched,
+        };
+        // --collect-extensionless: what [filename_categories] didn't place
+        // goes in one folder
+        let matched = match (matched, &opts.collect_extensionless) {
+            (None, Some(folder)) if ext.is_empty() => Some((folder.clone(), MatchReason::Extensionless)),
+            (matched, _) => matched,
+        };
+        let (category, reason) = matched.unzip();
+        if category.is_none() && ext.is_empty() {
+            stats.leave(opts, file_path, SkipReason::NoExtension);
+            continue;
+        }
 
-        let dest_file = resolve_collision(&dest_dir, &file_name, &ext);
-
-        // Display paths relative to the base folder
-        let src = file_path.strip_prefix(base).unwrap_or(file_path).display();
-        let dst = dest_file.strip_prefix(base).unwrap_or(&dest_file).display();
+        // Check for duplicates (by default: same name + date + size = duplicate).
+        // This comes before the destination is worked out, so a copy of a
+        // file moved earlier in the run is always left as a duplicate and
+        // never moved in under a dated name; only files that differ are
+        // renamed to get past a taken name.
+        if opts.find_duplicates {
+            let scope = match opts.dedupe_scope {
+                DedupeScope::Global => None,
+                DedupeScope::PerCategory => category.as_deref(),
+            };
+            // Read by the hashing pass already, if there was one
+            let cached = hashes.fingerprint(file_path, meta.len(), scope);
+            let key = match cached.map_or_else(|| fingerprint(file_path, &meta, opts.dup_strategy, scope), Ok) {
+                Ok(k) => k,
+                Err(e) => { stats.fail(opts.quiet, file_path, src, e); continue; }
+            };
 
-        if opts.dry_run {
-            println!("  {} {} {} {}", "→".cyan(), src, "→".dimmed(), dst.to_string().green());
-            stats.moved += 1;
-        } else {
-            fs::create_dir_all(&dest_dir)?;
-            match move_file(file_path, &dest_file) {
-                Ok(()) => {
-                    println!("  {} {} {} {}", "✓".green(), src, "→".dimmed(), dst.to_string().cyan());
-                    if let Some(ref mut f) = log { writeln!(f, "{src} -> {dst}").ok(); }
-                    stats.moved += 1;
-                }
-                Err(e) => {
-                    eprintln!("  {} {} — {}", "✗".red(), src, e);
-                    stats.errors += 1;
+            match seen.first_or_insert(&key, file_path) {
+                Ok(Some(original)) => {
+                    let original = original.strip_prefix(base).unwrap_or(&original).display();
+                    say!(opts, "  {} {}", "⚠".yellow(), format!("{src} (duplicate of {original})").yellow());
+                    stats.leave(opts, file_path, SkipReason::Duplicate);
+                    stats.duplicate_bytes += meta.len();
+                    continue;
                 }
+                Ok(None) => {}
+                Err(e) => { stats.fail(opts.quiet, file_path, src, e); continue; }
             }
         }
-    }
-    Ok(stats)
-}
-
-// ── Helper functions ─────────────────────────
-
-// Recursively find all files, skipping hidden and category folders
-pub fn collect_files(dir: &Path, skip: &[&str]) -> std::io::Result<Vec<PathBuf>> {
-    let mut out = Vec::new();
-    for entry in fs::read_dir(dir)? {
-        let path = entry?.path();
-        let name = path.file_name().unwrap_or_default().to_string_lossy();
-
-        // Skip hidden files and category folders
-        if name.starts_with('.') || (path.is_dir() && skip.contains(&name.as_ref())) {
-            continue;
-        }
 
-        if path.is_dir() {
-            out.append(&mut collect_files(&path, skip)?);
-        } else {
-            out.push(path);
+        let (category, reason) = match category.zip(reason) {
+            Some(matched) => matched,
+            None if categorizer.is_excluded(&ext) => { stats.leave(opts, file_path, SkipReason::Excluded); continue; }
+            None if opts.group_unknown => (format!("{UNKNOWN_ROOT}/{ext}"), MatchReason::Unknown(ext.clone())),
+            None => { stats.leave(opts, file_path, SkipReason::Unmatched); continue; }
+        };
+        // Before subfolders and layout are added, for {category}
+        let sorted_as = category.clone();
+        // Old files are packed into a zip per category and year after the loop
+        if let Some(min_age) = opts.archive_older_than {
+            let modified = meta.modified().unwrap_or(now);
+            if now.duration_since(modified).unwrap_or_default() >= min_age {
+                let year = chrono::Datelike::year(&chrono::DateTime::<Local>::from(modified));
+                let zip = out_base.join(ARCHIVE_ROOT).join(archive_name(&category, year));
+                let entry = file_path.strip_prefix(base).unwrap_or(file_path).to_string_lossy().replace('\\', "/");
+                to_archive.entry(zip).or_default().push((file_path.clone(), entry));
+                continue;
+            }
         }
+        let category = match detect_language_for(opts, file_path, &ext) {
+            Some(lang) => format!("{category}/{lang}"),
+            None => category,
+        };
+        let category = match image_subfolder_for(opts, file_path, &ext) {
+            Some(sub) => format!("{category}/{sub}"),
+            None => category,
+        };
+        // Archives nest under whatever they mostly contain; unreadable ones stay put
+        let category = match opts.peek_archives.then(|| categorizer.archive_category(file_path)).flatten() {
+            Some(inner) if inner != category => format!("{inner}/{category}"),
+            _ => category,
+        };
+        // Compose the folder from the layout; --date-from-name is short for
+        // category, by_date:YearMonth
+        let dated = [LayoutStep::Category, LayoutStep::ByDate(DateDepth::YearMonth)];
+        let steps = if opts.date_from_name { &dated[..] } else { &opts.layout[..] };
+        let category = match steps {
+            [] => category,
+            steps => {
+                let parent = file_path.strip_prefix(base).ok().and_then(Path::parent)
+                    .and_then(Path::file_name).map(|p| p.to_string_lossy());
+                let modified = meta.modified().unwrap_or(now);
+                layout_folder(steps, &LayoutFile {
+                    category: &category, date: file_date(&file_name, &meta), ext: &ext, parent: parent.as_deref(),
+                    age_bucket: age_bucket(now, modified, &opts.age_buckets),
+                })
+            }
+        };
+        let category = category.as_str();
+
+        // Build destination path
+        let sub = if opts.keep_structure {
+            let rel = file_path.strip_prefix(base).unwrap_or(file_path);
+            rel.parent().filter(|p| p.components().next().is_some())
+        } else {
+            None
+        };
+        // Reuse subfolders already in the category, whatever their spelling
+        let merged = match sub {
+            Some(p) if opts.merge_into_existing => Some(merge_existing(&out_base.join(category), p)),
+            _ => None,
+        };
+        let sub = merged.as_deref().or(sub);
+        let dest_dir = if opts.max_per_folder > 0 {
+            pick_capped_dir(&out_base, category, sub, opts.max_per_folder, &mut folder_counts)
+        } else {
+            match sub {
+                Some(p) => out_base.join(category).join(p),
+                None => out_base.join(category),
+            }
+        };
+        let dest_dir = match sanitize {
+            true => out_base.join(sanitize_path(dest_dir.strip_prefix(&out_base).unwrap_or(&dest_dir))),
+            false => dest_dir,
+        };
+
+        // --min-files-to-create-folder: files bound for a category folder
+        // that doesn't exist yet wait there until enough of them have come
+        // up, then go together; what is still waiting after the loop stays
+        let destined = Destined { file: file_path.clone(), dest_dir, category: category.to_string(), sorted_as, reason, meta };
+        let batch = match new_category_folder(&out_base, &destined.dest_dir, &opened) {
+            Some(top) if opts.min_files_to_create_folder > 1 => {
+                let waiting = held.entry(top.clone()).or_default();
+                waiting.push(destined);
+                if waiting.len() < opts.min_files_to_create_folder { continue; }
+                opened.insert(top.clone());
+                held.remove(&top).unwrap_or_default()
+            }
+            _ => vec![destined],
+        };
+
+        for Destined { file, dest_dir, category, sorted_as, reason, meta } in batch {
+            let file_path = &file;
+            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+            let src = file_path.strip_prefix(base).unwrap_or(file_path).display();
+            let category = category.as_str();
+
+            if let Some(why) = destination_conflict(base, file_path, &dest_dir) {
+                stats.fail(opts.quiet, file_path, src, why);
+                continue;
+            }
+
+            let dest_name = apply_extension_case(&file_name, opts.extension_case);
+            let dest_name = match &opts.rename_template {
+                Some(template) => {
+                    let counter = counters.entry(dest_dir.clone()).or_default();
+                    *counter += 1;
+                    let named = Path::new(&dest_name);
+                    let text = |p: Option<&std::ffi::OsStr>| p.unwrap_or_default().to_string_lossy().into_owned();
+                    template.render(&RenameFile {
+                        stem: &text(named.file_stem()), ext: &text(named.extension()),
+                        date: file_date(&file_name, &meta), category: &sorted_as, counter: *counter,
+                    })
+                }
+                None => dest_name,
+            };
+            let dest_name = if sanitize { sanitize_filename(&dest_name) } else { dest_name };
+            let dest_ext = Path::new(&dest_name).extension().unwrap_or_default().to_string_lossy();
+            if opts.skip_locked && is_file_locked(file_path) {
+                stats.leave(opts, file_path, SkipReason::Locked);
+                continue;
+            }
+            // An interrupted run may have got this far already; with --hardlink,
+            // so has any earlier run (the source stays where it was)
+            let planned = dest_dir.join(&dest_name);
+            if (opts.resume && mover.already_moved(file_path, &planned, meta.len()))
+                || (opts.hardlink && same_file(file_path, &planned))
+            {
+                stats.leave(opts, file_path, SkipReason::AlreadyMoved);
+                continue;
+            }
+            let outcome = resolve_collision_checked(
+                file_path, &dest_dir, &dest_name, &dest_ext, &mut reserved,
+                case_insensitive, opts.collision_scheme, opts.skip_identical,
+            );
+            let (dest_file, renamed) = match outcome {
+                CollisionOutcome::Fresh(p) => (p, false),
+                CollisionOutcome::Renamed(p) => (p, true),
+                CollisionOutcome::IdenticalSkip => { stats.leave(opts, file_path, SkipReason::Identical); continue; }
+            };
+
+            let moved_before = stats.moved;
+            mover.relocate(&mut stats, file_path, &dest_file, category, meta.len(), Some(&reason));
+            if stats.moved > moved_before {
+                if renamed { stats.renamed += 1; }
+                if let Some(top) = dest_file.strip_prefix(&out_base).ok().and_then(|rel| rel.components().next()) {
+                    filled.insert(out_base.join(top));
+                }
+                // Sidecars land next to the file under its new name
+                for companion in companions.remove(file_path).unwrap_or_default() {
+                    if opts.skip_locked && is_file_locked(&companion) {
+                        stats.leave(opts, &companion, SkipReason::Locked);
+                        continue;
+                    }
+                    let name = companion_name(&companion, file_path, &dest_file);
+                    let name = if sanitize { sanitize_filename(&name) } else { name };
+                    let ext = file_extension(&companion).unwrap_or_default();
+                    let outcome = resolve_collision_checked(
+                        &companion, &dest_dir, &name, &ext, &mut reserved,
+                        case_insensitive, opts.collision_scheme, opts.skip_identical,
+                    );
+                    let to = match outcome {
+                        CollisionOutcome::Fresh(p) | CollisionOutcome::Renamed(p) => p,
+                        CollisionOutcome::IdenticalSkip => { stats.leave(opts, &companion, SkipReason::Identical); continue; }
+                    };
+                    let len = fs::metadata(&companion).map(|m| m.len()).unwrap_or(0);
+                    mover.relocate(&mut stats, &companion, &to, category, len, Some(&MatchReason::Companion));
+                }
+            }
+        }
+    }
+    if stats.cancelled {
+        say!(opts, "{}", "Cancelled; the files not reached yet were left where they are.".yellow());
+        mover.finish(&stats)?;
+        return Ok(stats);
+    }
+
+    // Too few files came up for these folders to be worth creating
+    for file in held.into_values().flatten() {
+        stats.leave(opts, &file.file, SkipReason::SparseCategory);
+    }
+
+    // Main files that stayed put keep their sidecars
+    let mut stranded: Vec<PathBuf> = companions.into_values().flatten().collect();
+    stranded.sort();
+    for companion in stranded {
+        stats.leave(opts, &companion, SkipReason::Companion);
+    }
+
+    mover.prune(&mut stats, &to_prune);
+    mover.archive(&mut stats, &to_archive);
+
+    mover.collapse(&mut stats, &filled)?;
+
+    mover.finish(&stats)?;
+    Ok(stats)
+}
+
+// ── Planning ─────────────────────────────────
+// A run in two steps, for reviewing moves before they happen (--tui):
+// plan_moves() works out every (from, to) without touching anything, and
+// apply_plan() carries out the ones that were kept.
+
+// The moves organize() would make with these options, in order
+pub fn plan_moves(opts: &OrganizeOpts, categorizer: &dyn Categorizer) -> Result<Vec<(PathBuf, PathBuf)>, OrganizeError> {
+    let dry = OrganizeOpts { dry_run: true, quiet: true, preview_tree_dir: None, ..opts.clone() };
+    let mut moves = Vec::new();
+    organize_with_events(&dry, categorizer, &mut |event| {
+        if let OrganizeEvent::Moved { from, to, .. } = event { moves.push((from, to)); }
+    })?;
+    Ok(moves)
+}
+
+// Planned moves counted per top-level category folder (Images for
+// Images/2024/a.jpg; under --root, the folder inside the root), by name
+pub fn plan_by_category(opts: &OrganizeOpts, moves: &[(PathBuf, PathBuf)]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for (_, to) in moves {
+        *counts.entry(planned_category(opts, to)).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+// The planned moves into the `chosen` categories (case-insensitive), for
+// applying part of a plan
+pub fn moves_in_categories(opts: &OrganizeOpts, moves: &[(PathBuf, PathBuf)], chosen: &[String]) -> Vec<(PathBuf, PathBuf)> {
+    let chosen: Vec<String> = chosen.iter().map(|c| c.to_lowercase()).collect();
+    moves.iter()
+        .filter(|(_, to)| chosen.contains(&planned_category(opts, to).to_lowercase()))
+        .cloned()
+        .collect()
+}
+
+// Whether --keep-newest (or a per-extension count) is in force
+fn retains(opts: &OrganizeOpts) -> bool {
+    opts.keep_newest.is_some() || !opts.keep_newest_per_ext.is_empty()
+}
+
+// Whether --keep-newest may let `file` go: not if its extension is excluded
+// (in the config or by --only/--exclude-category) or it is still inside
+// --quiet-period, as the run leaves those files where they are
+fn prunable(opts: &OrganizeOpts, categorizer: &dyn Categorizer, now: SystemTime, file: &Path) -> bool {
+    if categorizer.is_excluded(&file_extension(file).unwrap_or_default()) {
+        return false;
+    }
+    match opts.quiet_period {
+        Some(period) => fs::metadata(file).is_ok_and(|m| !in_quiet_period(now, m.modified().unwrap_or(now), period)),
+        None => true,
+    }
+}
+
+// A file whose folder is worked out, on its way to being moved
+struct Destined {
+    file: PathBuf,
+    dest_dir: PathBuf,
+    category: String,
+    // Before subfolders and layout are added, for {category}
+    sorted_as: String,
+    reason: MatchReason,
+    meta: fs::Metadata,
+}
+
+// The top-level category folder `dest_dir` is in, when it would be new: not
+// on disk and not `opened` earlier in the run. A folder that already exists
+// is never sparse, as nothing would be created.
+fn new_category_folder(out_base: &Path, dest_dir: &Path, opened: &HashSet<PathBuf>) -> Option<PathBuf> {
+    let top = out_base.join(dest_dir.strip_prefix(out_base).ok()?.components().next()?);
+    (!opened.contains(&top) && !top.is_dir()).then_some(top)
+}
+
+// Top-level folder a planned destination is in, below the output folder
+fn planned_category(opts: &OrganizeOpts, to: &Path) -> String {
+    let out_base = match &opts.root {
+        Some(root) => opts.path.join(root),
+        None => opts.path.clone(),
+    };
+    let rel = to.strip_prefix(&out_base).unwrap_or(to);
+    match rel.parent().and_then(|p| p.components().next()) {
+        Some(top) => top.as_os_str().to_string_lossy().into_owned(),
+        None => String::new(),
+    }
+}
+
+// Make planned moves through the same Mover as organize(), so the log,
+// manifest, hooks and backups all apply (and --dry-run still only previews).
+// A destination taken since planning gets a fresh name.
+pub fn apply_plan(opts: &OrganizeOpts, moves: &[(PathBuf, PathBuf)]) -> Result<Stats, OrganizeError> {
+    let mut no_events = |_| {};
+    let mut mover = Mover::open(opts, &mut no_events)?;
+    let mut stats = Stats::default();
+    let mut reserved: HashSet<PathBuf> = HashSet::new();
+
+    for (from, to) in moves {
+        let src = from.strip_prefix(&opts.path).unwrap_or(from).display();
+        let bytes = match fs::metadata(from) {
+            Ok(m) => m.len(),
+            Err(e) => { stats.fail(opts.quiet, from, src, e); continue; }
+        };
+        let dir = to.parent().unwrap_or(&opts.path);
+        let name = to.file_name().unwrap_or_default().to_string_lossy();
+        let ext = Path::new(name.as_ref()).extension().unwrap_or_default().to_string_lossy();
+        let dest = resolve_collision_using(dir, &name, &ext, &mut reserved, opts.case_insensitive_names, opts.collision_scheme);
+        let category = dir.strip_prefix(&opts.path).unwrap_or(dir).to_string_lossy();
+        mover.relocate(&mut stats, from, &dest, &category, bytes, None);
+    }
+
+    mover.finish(&stats)?;
+    Ok(stats)
+}
+
+// ── Moving ───────────────────────────────────
+// The one place that touches the filesystem for a run. Every destination
+// feature hands its (from, to) pair to `relocate`, which previews under
+// --dry-run and moves otherwise — new features can't forget the guard.
+
+struct Mover<'a> {
+    opts: &'a OrganizeOpts,
+    events: &'a mut dyn FnMut(OrganizeEvent),
+    // Skips and failures in Stats already sent as events
+    reported: (usize, usize),
+    abs_base: PathBuf,
+    run_id: String,
+    log: Option<fs::File>,
+    manifest: Option<fs::File>,
+    index: Option<BTreeMap<String, String>>,
+    any_moves: bool,
+    // Dry-run moves planned so far, printed or not
+    previewed: usize,
+    // --resume: where the resumed run sent each file (absolute src → dst)
+    resumed: HashMap<PathBuf, PathBuf>,
+    // Top-level output folders already given a CATEGORY_MARKER
+    marked: HashSet<PathBuf>,
+    // For the elapsed time in the log footer
+    started: Instant,
+    // Backup copies need names a FAT, exFAT or NTFS volume can store
+    sanitize_backup: bool,
+}
+
+impl<'a> Mover<'a> {
+    // Open the log, manifest and provenance index; in dry-run none are touched
+    fn open(opts: &'a OrganizeOpts, events: &'a mut dyn FnMut(OrganizeEvent)) -> Result<Self, OrganizeError> {
+        let base = &opts.path;
+        let real = |p: &'a Option<PathBuf>| p.as_ref().filter(|_| !opts.dry_run);
+
+        let log = match real(&opts.log_file) {
+            Some(p) => {
+                let mut f = fs::OpenOptions::new().create(true).append(true).open(p).at(p)?;
+                let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+                writeln!(f, "\n{0}\nRun started:  {ts}\nDirectory:    {1}\nDry-run:      {2}\n{0}\n",
+                    "=".repeat(40), base.display(), opts.dry_run).at(p)?;
+                Some(f)
+            }
+            None => None,
+        };
+        let manifest = match real(&opts.manifest) {
+            Some(p) => Some(fs::OpenOptions::new().create(true).append(true).open(p).at(p)?),
+            None => None,
+        };
+        // Provenance index: start from the existing one so runs accumulate
+        let index = match real(&opts.index_out) {
+            Some(p) => Some(read_index(p).at(p)?),
+            None => None,
+        };
+
+        // Resuming carries on under the latest run id, so one undo covers both
+        let mut run_id = new_run_id();
+        let mut resumed = HashMap::new();
+        if let Some(p) = opts.manifest.as_ref().filter(|_| opts.resume) {
+            let entries = read_manifest(p).at(p)?;
+            if let Some(last) = entries.last() {
+                run_id = last.run_id.clone();
+            }
+            resumed = entries.into_iter().filter(|e| e.run_id == run_id).map(|e| (e.src, e.dst)).collect();
+        }
+
+        Ok(Mover {
+            opts,
+            events,
+            reported: (0, 0),
+            abs_base: std::path::absolute(base).at(base)?,
+            run_id,
+            log,
+            manifest,
+            index,
+            any_moves: false,
+            previewed: 0,
+            resumed,
+            marked: HashSet::new(),
+            started: Instant::now(),
+            sanitize_backup: opts.backup_dir.as_ref().is_some_and(|b| opts.sanitize_names || probe_volume(b).windows_names),
+        })
+    }
+
+    // Drop a CATEGORY_MARKER in the top-level folder `to` landed in, so
+    // later scans leave it alone even if the category is renamed
+    fn mark_folder(&mut self, to: &Path) {
+        let Some(top) = to.strip_prefix(&self.opts.path).ok().and_then(|rel| rel.components().next()) else { return };
+        let top = self.opts.path.join(top);
+        if self.marked.insert(top.clone()) {
+            let _ = fs::OpenOptions::new().write(true).create_new(true).open(top.join(CATEGORY_MARKER))
+                .and_then(|mut f| writeln!(f, "Sorted by smart-organizer; this folder is skipped when scanning."));
+        }
+    }
+
+    // Whether an interrupted run already put `from` in place: its recorded
+    // destination, or the one planned now, holds a file of the same size
+    fn already_moved(&self, from: &Path, planned: &Path, len: u64) -> bool {
+        let abs_src = self.abs_base.join(from.strip_prefix(&self.opts.path).unwrap_or(from));
+        self.resumed.get(&abs_src).map(PathBuf::as_path).into_iter()
+            .chain([planned])
+            .any(|dst| dst != from && fs::metadata(dst).is_ok_and(|m| m.is_file() && m.len() == len))
+    }
+
+    // Send `from` to `to`, or just say so under --dry-run (with why it goes
+    // there, when that's known)
+    fn relocate(&mut self, stats: &mut Stats, from: &Path, to: &Path, category: &str, bytes: u64, reason: Option<&MatchReason>) {
+        let moved = if self.opts.dry_run {
+            self.would_move(stats, from, to, reason);
+            let backed_up = self.opts.backup_dir.is_some();
+            let copied = self.opts.hardlink && !same_device(from, to);
+            stats.would_copy_bytes += bytes * (u64::from(backed_up) + u64::from(copied));
+            true
+        } else {
+            self.do_move(stats, from, to)
+        };
+        self.report(stats);
+        if moved {
+            *stats.bytes_by_category.entry(category.to_string()).or_default() += bytes;
+            stats.moved += 1;
+            stats.note_size(self.opts.top_files, bytes, to);
+            (self.events)(OrganizeEvent::Moved { from: from.to_path_buf(), to: to.to_path_buf(), reason: reason.cloned() });
+        }
+    }
+
+    // Pack each zip's files into it, then remove them under the run's
+    // delete policy. The whole batch is confirmed first, before any zip is
+    // written; a file is only removed once its archive was written and
+    // checked. Under --dry-run, just say so.
+    fn archive(&mut self, stats: &mut Stats, to_archive: &BTreeMap<PathBuf, Vec<(PathBuf, String)>>) {
+        let opts = self.opts;
+        let originals: Vec<PathBuf> = to_archive.values().flatten().map(|(file, _)| file.clone()).collect();
+        if !opts.dry_run && !originals.is_empty() {
+            if let Err(e) = confirm_deletion(&originals, &opts.delete_policy, opts.confirm_delete) {
+                for file in &originals {
+                    stats.fail(opts.quiet, file, file.strip_prefix(&opts.path).unwrap_or(file).display(), &e);
+                }
+                return;
+            }
+        }
+        for (zip, files) in to_archive {
+            self.archive_into(stats, zip, files);
+        }
+    }
+
+    // One zip of archive(): pack, check, then remove the originals
+    fn archive_into(&mut self, stats: &mut Stats, zip: &Path, files: &[(PathBuf, String)]) {
+        let opts = self.opts;
+        if !opts.dry_run {
+            if let Err(e) = append_to_zip(zip, files) {
+                for (file, _) in files {
+                    stats.fail(opts.quiet, file, self.shown(file, zip).0, &e);
+                }
+                return;
+            }
+            self.mark_folder(zip);
+        }
+        for (file, _) in files {
+            let (src, dst) = self.shown(file, zip);
+            if !opts.dry_run {
+                if let Err(e) = delete_file(file, &opts.delete_policy) {
+                    stats.fail(opts.quiet, file, &src, format!("archived in {dst}, but the original stayed: {e}"));
+                    continue;
+                }
+                if let Some(ref mut f) = self.log { writeln!(f, "{src} => {dst}").ok(); }
+            }
+            say!(opts, "  {} {} {} {}", "⇢".cyan(), src, "→".dimmed(), dst.yellow());
+            stats.archived += 1;
+        }
+    }
+
+    // Trash or delete the files --keep-newest let go of, as one batch under
+    // the run's delete policy: past its limits nothing goes unless
+    // confirm_delete agrees. A file that fails doesn't stop the others.
+    // Under --dry-run, just say so.
+    fn prune(&mut self, stats: &mut Stats, files: &[PathBuf]) {
+        let opts = self.opts;
+        let policy = opts.prune_policy();
+        let gone = if policy.to_trash { "(trash)" } else { "(deleted)" };
+        let refused = match opts.dry_run || files.is_empty() {
+            true => None,
+            false => confirm_deletion(files, &policy, opts.confirm_delete).err(),
+        };
+        for file in files {
+            let src = file.strip_prefix(&opts.path).unwrap_or(file).display().to_string();
+            if let Some(e) = &refused {
+                stats.fail(opts.quiet, file, &src, e);
+                continue;
+            }
+            if !opts.dry_run {
+                if let Err(e) = delete_file(file, &policy) {
+                    stats.fail(opts.quiet, file, &src, e);
+                    continue;
+                }
+                if let Some(ref mut f) = self.log { writeln!(f, "{src} => {gone}").ok(); }
+            }
+            say!(opts, "  {} {} {} {}", "✗".dimmed(), src, "→".dimmed(), gone.dimmed());
+            stats.pruned += 1;
+        }
+    }
+
+    // Send the skips and failures recorded since the last call as events
+    fn report(&mut self, stats: &Stats) {
+        let (skips, fails) = self.reported;
+        for (path, reason) in &stats.untouched[skips..] {
+            (self.events)(OrganizeEvent::Skipped { path: path.clone(), reason: *reason });
+        }
+        for (path, message) in &stats.failures[fails..] {
+            (self.events)(OrganizeEvent::Error { path: path.clone(), message: message.clone() });
+        }
+        self.reported = (stats.untouched.len(), stats.failures.len());
+    }
+
+    // Print the planned move (up to --preview-limit lines) and mirror it
+    // into the preview tree, if one was asked for
+    fn would_move(&mut self, stats: &mut Stats, from: &Path, to: &Path, reason: Option<&MatchReason>) {
+        let opts = self.opts;
+        let (src, dst) = self.shown(from, to);
+        if let Some(tree) = &opts.preview_tree_dir {
+            let link = tree.join(&dst);
+            let target = self.abs_base.join(from.strip_prefix(&opts.path).unwrap_or(from));
+            if let Err(e) = link_into_preview(&target, &link).at(&link) {
+                stats.fail(opts.quiet, from, &src, e);
+            }
+        }
+
+        self.previewed += 1;
+        if opts.preview_limit > 0 && self.previewed > opts.preview_limit { return; }
+        let why = reason.map(|r| format!(" [{r}]")).unwrap_or_default();
+        say!(opts, "  {} {} {} {}{}", "→".cyan(), src, "→".dimmed(), dst.green(), why.dimmed());
+    }
+
+    // Move for real and record it everywhere; false if the move failed
+    fn do_move(&mut self, stats: &mut Stats, from: &Path, to: &Path) -> bool {
+        let opts = self.opts;
+        let (src, dst) = self.shown(from, to);
+
+        // Be gentle with NAS / cloud-synced folders: space out the moves
+        if self.any_moves && opts.throttle_ms > 0 {
+            std::thread::sleep(Duration::from_millis(opts.throttle_ms));
+        }
+        self.any_moves = true;
+
+        // Owner and group are read up front; a copy can't keep them on its own
+        let original = if opts.preserve_owner { fs::metadata(from).ok() } else { None };
+
+        // Snapshot the original first; no backup, no move
+        if let Some(backup) = &opts.backup_dir {
+            let rel = from.strip_prefix(&opts.path).unwrap_or(from);
+            let copy = match self.sanitize_backup {
+                true => backup.join(sanitize_path(rel)),
+                false => backup.join(rel),
+            };
+            let copied = copy.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::copy(from, &copy));
+            match copied.at(&copy) {
+                Ok(bytes) => stats.backup_bytes += bytes,
+                Err(e) => { stats.fail(opts.quiet, from, src, e); return false; }
+            }
+            if let Some(meta) = &original { restore_owner(meta, &copy); }
+        }
+
+        let dest_dir = to.parent().unwrap_or(Path::new("."));
+        // With --fsync, folders about to be created; their parents gain an entry
+        let new_dirs: Vec<&Path> = match opts.fsync {
+            true => dest_dir.ancestors().take_while(|d| !d.as_os_str().is_empty() && !d.exists()).collect(),
+            false => Vec::new(),
+        };
+        let moved = fs::create_dir_all(dest_dir).at(dest_dir)
+            .and_then(|_| new_dirs.iter().try_for_each(|d| sync_dir(parent_dir(d)).at(d)))
+            .and_then(|_| match (opts.hardlink, opts.hash_log) {
+                (true, hash) => link_or_copy(from, to)
+                    .and_then(|_| if opts.fsync { sync_move(from, to).at(to) } else { Ok(()) })
+                    .and_then(|_| if hash { file_hash(to).at(to).map(Some) } else { Ok(None) }),
+                (false, hash) if opts.fsync => move_file_durable(from, to, opts.verify, hash),
+                (false, true) => move_file_hashed(from, to, opts.verify).map(Some),
+                (false, false) => move_file_verified(from, to, opts.verify).map(|_| None),
+            });
+        let sha256 = match moved {
+            Ok(hash) => hash,
+            Err(e) => { stats.fail(opts.quiet, from, src, e); return false; }
+        };
+        if let Some(meta) = &original { restore_owner(meta, to); }
+        self.mark_folder(to);
+        let abs_src = self.abs_base.join(from.strip_prefix(&opts.path).unwrap_or(from));
+        let abs_dst = self.abs_base.join(to.strip_prefix(&opts.path).unwrap_or(to));
+        if opts.leave_symlink {
+            // The move itself stands; a link the filesystem won't take is
+            // only reported
+            if let Err(e) = leave_symlink(&abs_src, &abs_dst).at(from) {
+                stats.fail(opts.quiet, from, &src, format!("moved, but no symlink was left: {e}"));
+            }
+        }
+
+        say!(opts, "  {} {} {} {}", "✓".green(), src, "→".dimmed(), dst.cyan());
+        if let Some(ref mut f) = self.log {
+            match &sha256 {
+                Some(hash) => writeln!(f, "{src} -> {dst}  sha256:{hash}").ok(),
+                None => writeln!(f, "{src} -> {dst}").ok(),
+            };
+        }
+        if let Some(cmd) = &opts.on_move {
+            // The move itself stands; a failed hook is only reported
+            if let Err(e) = run_on_move(cmd, &abs_src, &abs_dst) {
+                stats.fail(opts.quiet, from, &src, e);
+            }
+        }
+        if let Some(ref mut m) = self.manifest {
+            let entry = ManifestEntry {
+                run_id: self.run_id.clone(),
+                src: abs_src,
+                dst: abs_dst,
+                timestamp: Local::now().to_rfc3339(),
+                sha256,
+                linked: opts.hardlink,
+            };
+            write_manifest(m, &entry).ok();
+        }
+        if let Some(ref mut idx) = self.index {
+            idx.insert(dst, src);
+        }
+        true
+    }
+
+    // --collapse-chains: fold the single-child folder chains in each category
+    // folder that got files. Under --dry-run nothing was created to fold.
+    fn collapse(&mut self, stats: &mut Stats, filled: &BTreeSet<PathBuf>) -> Result<(), OrganizeError> {
+        if self.opts.collapse_chains == 0 || self.opts.dry_run {
+            return Ok(());
+        }
+        for dir in filled {
+            let renames = collapse_single_child_dirs(dir, self.opts.collapse_chains)?;
+            self.follow_renames(stats, &renames)?;
+        }
+        Ok(())
+    }
+
+    // Folders were moved after files went into them (--collapse-chains):
+    // point the manifest, the index and Stats at where the files are now
+    fn follow_renames(&mut self, stats: &mut Stats, renames: &[(PathBuf, PathBuf)]) -> Result<(), OrganizeError> {
+        let follow = |p: &Path| renames.iter()
+            .find_map(|(old, new)| p.strip_prefix(old).ok().map(|rest| new.join(rest)));
+        let abs = |p: &PathBuf| self.abs_base.join(p.strip_prefix(&self.opts.path).unwrap_or(p));
+        let abs_renames: Vec<(PathBuf, PathBuf)> = renames.iter().map(|(old, new)| (abs(old), abs(new))).collect();
+        let follow_abs = |p: &Path| abs_renames.iter()
+            .find_map(|(old, new)| p.strip_prefix(old).ok().map(|rest| new.join(rest)));
+
+        if let (Some(_), Some(path)) = (&self.manifest, &self.opts.manifest) {
+            let mut entries = read_manifest(path).at(path)?;
+            for entry in &mut entries {
+                if let Some(moved) = follow_abs(&entry.dst) { entry.dst = moved; }
+            }
+            let mut out = fs::File::create(path).at(path)?;
+            for entry in &entries {
+                write_manifest(&mut out, entry).at(path)?;
+            }
+        }
+        if let Some(idx) = self.index.take() {
+            self.index = Some(idx.into_iter().map(|(dst, src)| {
+                let moved = follow(&self.opts.path.join(&dst))
+                    .map(|p| p.strip_prefix(&self.opts.path).unwrap_or(&p).display().to_string());
+                (moved.unwrap_or(dst), src)
+            }).collect());
+        }
+        for (_, path) in &mut stats.largest {
+            if let Some(moved) = follow(path) { *path = moved; }
+        }
+        Ok(())
+    }
+
+    // Both paths as shown to the user: relative to the base folder
+    fn shown(&self, from: &Path, to: &Path) -> (String, String) {
+        let rel = |p: &Path| p.strip_prefix(&self.opts.path).unwrap_or(p).display().to_string();
+        (rel(from), rel(to))
+    }
+
+    // Note the preview lines held back, save the provenance index and send
+    // the last events
+    fn finish(mut self, stats: &Stats) -> Result<(), OrganizeError> {
+        self.report(stats);
+        let limit = self.opts.preview_limit;
+        if limit > 0 && self.previewed > limit {
+            say!(self.opts, "  {}", format!("… and {} more", format_count(self.previewed - limit)).dimmed());
+        }
+        if let (Some(idx), Some(p)) = (&self.index, &self.opts.index_out) {
+            fs::write(p, serde_json::to_string_pretty(idx).map_err(std::io::Error::from).at(p)?).at(p)?;
+        }
+        // Close the run's block in the log with its totals, framed like the header
+        if let (Some(f), Some(p)) = (&mut self.log, &self.opts.log_file) {
+            let ts = Local::now().format("%Y-%m-%d %H:%M:%S");
+            writeln!(f, "\n{0}\nRun finished: {ts}\nMoved:        {1}\nDuplicates:   {2}\nSkipped:      {3}\nErrors:       {4}\nElapsed:      {5:.2}s\n{0}",
+                "=".repeat(40), stats.moved, stats.duplicates, stats.skipped, stats.failures.len(),
+                self.started.elapsed().as_secs_f64()).at(p)?;
+        }
+        (self.events)(OrganizeEvent::Finished { stats: stats.clone() });
+        Ok(())
+    }
+}
+
+// Load a provenance index (new path -> original path, relative to the base)
+pub fn read_index(path: &Path) -> std::io::Result<BTreeMap<String, String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+// ── Analysis ─────────────────────────────────
+// Read-only scan: which extensions exist and where they would go
+
+pub struct ExtensionCount {
+    pub ext: String,
+    pub category: Option<String>,
+    pub count: usize,
+}
+
+// Tally every extension under `base` (most common first). Nothing is moved
+// or created. Extensionless files are counted under an empty `ext`. Each
+// file is categorized the way organize would (rules, name patterns and
+// file names included), so one extension gets a row per category its
+// files go to.
+pub fn analyze(base: &Path, config: &Config) -> Result<Vec<ExtensionCount>, OrganizeError> {
+    let category_names = config.category_names();
+    let mut counts: HashMap<(String, Option<String>), usize> = HashMap::new();
+
+    for file_path in collect_files(base, &category_names)? {
+        if is_hidden_or_junk(&file_path) { continue; }
+        let ext = file_extension(&file_path).unwrap_or_default();
+        let category = match fs::metadata(&file_path) {
+            Ok(meta) => config.category_for(&file_path, &ext, &meta),
+            // Rules need size and age; without them go by name alone
+            Err(_) => {
+                let name = file_path.file_name().unwrap_or_default().to_string_lossy();
+                config.categorize_file(&name, &ext).map(String::from)
+            }
+        };
+        *counts.entry((ext, category)).or_default() += 1;
+    }
+
+    let mut report: Vec<ExtensionCount> = counts.into_iter()
+        .map(|((ext, category), count)| ExtensionCount { ext, category, count })
+        .collect();
+    report.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.ext.cmp(&b.ext)).then_with(|| a.category.cmp(&b.category)));
+    Ok(report)
+}
+
+// Quick look at how big a tree is before touching it
+#[derive(Debug, Default, PartialEq)]
+pub struct TreeSummary {
+    pub dirs: usize,
+    pub files: usize,
+    // Deepest folder level below the base (0 = no subfolders)
+    pub max_depth: usize,
+}
+
+// Walk the same tree collect_files would (hidden and category folders
+// excluded) but only count entries. Never opens a file.
+pub fn scan_tree(base: &Path, config: &Config) -> std::io::Result<TreeSummary> {
+    let skip = config.category_names();
+    let mut summary = TreeSummary::default();
+    let mut stack = vec![(base.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = stack.pop() {
+        summary.max_depth = summary.max_depth.max(depth);
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') { continue; }
+
+            if entry.file_type()?.is_dir() {
+                let rel = path.strip_prefix(base).unwrap_or(&path);
+                if skip.iter().any(|root| rel.starts_with(root)) { continue; }
+                summary.dirs += 1;
+                stack.push((path, depth + 1));
+            } else {
+                summary.files += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+// ── Helper functions ─────────────────────────
+
+// Find all files below `dir`, skipping hidden and category folders.
+// Folders are walked from an explicit stack rather than by recursion, so
+// any depth of nesting fits.
+// `skip` holds category roots relative to `dir` ("Images", "Archive/Old"):
+// everything below them is already sorted and never re-collected.
+pub fn collect_files(dir: &Path, skip: &[&str]) -> Result<Vec<PathBuf>, OrganizeError> {
+    collect_files_with(dir, skip, true)
+}
+
+// collect_files, optionally without descending: with `recurse` off only the
+// loose files directly in `dir` are returned (hidden ones still skipped)
+pub fn collect_files_with(base: &Path, skip: &[&str], recurse: bool) -> Result<Vec<PathBuf>, OrganizeError> {
+    let mut out = Vec::new();
+    let mut stack = vec![base.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let mut subdirs = Vec::new();
+        for entry in fs::read_dir(&dir).at(&dir)? {
+            let path = entry.at(&dir)?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+            // Skip hidden files
+            if name.starts_with('.') { continue; }
+
+            if path.is_dir() {
+                // OS folders like $RECYCLE.BIN hold nothing of the user's to sort
+                if !recurse || is_junk(&path) { continue; }
+                // Skip anything inside a category root, named now or marked by an earlier run
+                let rel = path.strip_prefix(base).unwrap_or(&path);
+                if skip.iter().any(|root| rel.starts_with(root)) || path.join(CATEGORY_MARKER).exists() { continue; }
+                subdirs.push(path);
+            } else {
+                out.push(path);
+            }
+        }
+        // Reversed, so subfolders are walked in the order they were listed
+        stack.extend(subdirs.into_iter().rev());
     }
     Ok(out)
 }
 
-// Check if file is hidden or system junk
+// Why `dest_dir` is no place for `file`, if it isn't: a category or layout
+// that climbs out with `..`, resolves to the target itself, or lands inside
+// the file's own folder (Trip/a.jpg -> Trip/Images/). That last one would
+// mark Trip/ as sorted output, hiding everything else in it from later runs.
+pub fn destination_conflict(base: &Path, file: &Path, dest_dir: &Path) -> Option<String> {
+    let rel = dest_dir.strip_prefix(base).unwrap_or(dest_dir);
+    if rel.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Some(format!("destination {} climbs out with `..`", dest_dir.display()));
+    }
+    if rel.components().all(|c| matches!(c, std::path::Component::CurDir)) && dest_dir.starts_with(base) {
+        return Some("destination is the target folder itself".into());
+    }
+    let own = file.parent().filter(|dir| *dir != base && dir.starts_with(base))?;
+    dest_dir.starts_with(own).then(|| format!(
+        "destination {} is inside the file's own folder {}; later runs would skip that folder as sorted output",
+        dest_dir.display(), own.display(),
+    ))
+}
+
+// --root relative to `base`, for the scan to skip, or None when it's
+// outside the target altogether. A root that is `base` itself or climbs
+// out with `..` can't be told apart from unsorted files, so it's refused.
+fn root_inside(base: &Path, root: &Path) -> Result<Option<String>, OrganizeError> {
+    let rel = match root.is_absolute() {
+        true => match root.strip_prefix(std::path::absolute(base).at(base)?) {
+            Ok(rel) => rel,
+            Err(_) => return Ok(None),
+        },
+        false => root,
+    };
+    let normal = rel.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    if !normal || rel.as_os_str().is_empty() {
+        return Err(OrganizeError::Options(format!(
+            "--root {} must be a folder inside the target (no `..`), or one outside it", root.display(),
+        )));
+    }
+    Ok(Some(rel.to_string_lossy().into_owned()))
+}
+
+// Left in every top-level folder a run sorts into. Scans skip folders that
+// have one, so renaming a category in the config doesn't re-sort its old folder.
+pub const CATEGORY_MARKER: &str = ".smart-organizer";
+
+// Remove `dir` if nothing but a CATEGORY_MARKER is left in it
+pub fn remove_dir_if_empty(dir: &Path) {
+    let only_marker = fs::read_dir(dir).is_ok_and(|entries| {
+        entries.filter_map(Result::ok).all(|e| e.file_name() == CATEGORY_MARKER)
+    });
+    if only_marker {
+        let _ = fs::remove_file(dir.join(CATEGORY_MARKER));
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+// Folders that are refused as targets themselves: everything lives under
+// them, so only the folder itself is off limits
+const DANGEROUS_EXACT: &[&str] = &[
+    "/", "/home", "/Users", "/root", "/var", "/private", "/private/var", "/opt", "/Applications", "/Library", "/Volumes",
+    r"C:\Users",
+];
+
+// System folders refused along with everything inside them
+const DANGEROUS_TREES: &[&str] = &[
+    "/bin", "/boot", "/dev", "/etc", "/lib", "/lib64", "/proc", "/sbin", "/sys", "/usr", "/System", "/private/etc",
+    r"C:\Windows", r"C:\Program Files", r"C:\Program Files (x86)",
+];
+
+// Whether `path` (absolute, ideally canonical) is a place no one wants
+// sorted: a filesystem or drive root, the user's home, or a system folder.
+// Compared ignoring case on macOS and Windows.
+pub fn is_dangerous_target(path: &Path) -> bool {
+    let norm = |p: &Path| {
+        let s = p.to_string_lossy();
+        let s = s.strip_prefix(r"\\?\").unwrap_or(&s);
+        let s = if s.len() > 1 { s.trim_end_matches(['/', '\\']) } else { s };
+        if CASE_INSENSITIVE_FS { s.to_lowercase() } else { s.to_string() }
+    };
+    let target = norm(path);
+    if path.parent().is_none() {
+        return true;
+    }
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+    if home.is_some_and(|h| norm(Path::new(&h)) == target) {
+        return true;
+    }
+    DANGEROUS_EXACT.iter().any(|d| norm(Path::new(d)) == target)
+        || DANGEROUS_TREES.iter().any(|d| Path::new(&target).starts_with(norm(Path::new(d))))
+}
+
+// --by-parent destination for `file`: `Projects/<name of the folder it's
+// in>`, whatever the depth (base/ClientA/src/main.rs -> Projects/src). Files
+// directly in `base` have no parent worth naming, so None.
+pub fn parent_category(base: &Path, file: &Path) -> Option<String> {
+    let rel = file.strip_prefix(base).ok()?;
+    let parent = rel.parent()?.file_name()?;
+    Some(format!("{PARENT_ROOT}/{}", parent.to_string_lossy()))
+}
+
+// Collapse chains of folders that hold nothing but one subfolder, keeping
+// the first `keep` folders of each chain: with keep = 1,
+// a/b/c/report.pdf becomes a/report.pdf. `root` itself is never collapsed.
+// Returns (old folder, new folder) for each chain whose contents were lifted.
+pub fn collapse_single_child_dirs(root: &Path, keep: usize) -> Result<Vec<(PathBuf, PathBuf)>, OrganizeError> {
+    let mut renames = Vec::new();
+    collapse_under(root, keep.max(1), &mut renames)?;
+    Ok(renames)
+}
+
+fn collapse_under(dir: &Path, keep: usize, renames: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), OrganizeError> {
+    let mut subdirs: Vec<PathBuf> = fs::read_dir(dir).at(dir)?
+        .map(|e| e.map(|e| e.path()).at(dir))
+        .collect::<Result<_, _>>()?;
+    subdirs.retain(|p| p.is_dir() && !p.is_symlink());
+    subdirs.sort();
+
+    for start in subdirs {
+        let mut chain = vec![start];
+        while let Some(only) = only_subdir(chain.last().unwrap())? {
+            chain.push(only);
+        }
+        let last = chain.last().unwrap().clone();
+        if chain.len() <= keep {
+            collapse_under(&last, keep, renames)?;
+            continue;
+        }
+
+        // Park the deepest folder in the one we keep, drop the now-empty
+        // folders in between, then lift its contents up. Nothing else lives
+        // in a chain, so only the parked name could clash.
+        let target = chain[keep - 1].clone();
+        let parked = (0..).map(|n| target.join(format!(".collapse-{n}"))).find(|p| !p.exists()).unwrap();
+        fs::rename(&last, &parked).at(&last)?;
+        for between in chain[keep..chain.len() - 1].iter().rev() {
+            fs::remove_dir(between).at(between)?;
+        }
+        for entry in fs::read_dir(&parked).at(&parked)? {
+            let from = entry.at(&parked)?.path();
+            let to = target.join(from.file_name().unwrap_or_default());
+            fs::rename(&from, &to).at(&from)?;
+        }
+        fs::remove_dir(&parked).at(&parked)?;
+        renames.push((last, target.clone()));
+        collapse_under(&target, keep, renames)?;
+    }
+    Ok(())
+}
+
+// The single entry of `dir` when that entry is a (real) folder
+fn only_subdir(dir: &Path) -> Result<Option<PathBuf>, OrganizeError> {
+    let mut entries = fs::read_dir(dir).at(dir)?;
+    let first = match entries.next() {
+        Some(e) => e.at(dir)?.path(),
+        None => return Ok(None),
+    };
+    let only = entries.next().is_none() && first.is_dir() && !first.is_symlink();
+    Ok(only.then_some(first))
+}
+
+// Top-level spill folders (`<category>_<n>`) left by --max-per-folder runs
+fn spill_dirs(base: &Path, categories: &[String]) -> Result<Vec<String>, OrganizeError> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(base).at(base)? {
+        let path = entry.at(base)?.path();
+        if !path.is_dir() { continue; }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let is_spill = name.rsplit_once('_').is_some_and(|(cat, n)| {
+            categories.iter().any(|c| c == cat) && !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())
+        });
+        if is_spill { out.push(name); }
+    }
+    Ok(out)
+}
+
+// First of Images/, Images_2/, Images_3/… whose destination holds fewer than
+// `cap` files. Counts start from what's already on disk and grow as we go.
+fn pick_capped_dir(
+    base: &Path,
+    category: &str,
+    sub: Option<&Path>,
+    cap: usize,
+    counts: &mut HashMap<PathBuf, usize>,
+) -> PathBuf {
+    for n in 1.. {
+        let root = if n == 1 { category.to_string() } else { format!("{category}_{n}") };
+        let dir = match sub {
+            Some(p) => base.join(root).join(p),
+            None => base.join(root),
+        };
+        let count = counts.entry(dir.clone()).or_insert_with(|| {
+            fs::read_dir(&dir)
+                .map(|entries| entries.flatten().filter(|e| e.path().is_file() && e.file_name() != CATEGORY_MARKER).count())
+                .unwrap_or(0)
+        });
+        if *count < cap {
+            *count += 1;
+            return dir;
+        }
+    }
+    unreachable!()
+}
+
+// Link `link` to `target`: a hard link when possible (same filesystem),
+// otherwise a symlink. The original is never touched.
+fn link_into_preview(target: &Path, link: &Path) -> std::io::Result<()> {
+    if let Some(dir) = link.parent() { fs::create_dir_all(dir)?; }
+    match fs::hard_link(target, link) {
+        Ok(()) => Ok(()),
+        #[cfg(unix)]
+        Err(_) => std::os::unix::fs::symlink(target, link),
+        #[cfg(windows)]
+        Err(_) => std::os::windows::fs::symlink_file(target, link),
+    }
+}
+
+// --leave-symlink: a link at `link`, where a file was, to `target`, where
+// it went (both absolute). The link is relative, so it still works when
+// the whole tree is moved or mounted elsewhere.
+fn leave_symlink(link: &Path, target: &Path) -> std::io::Result<()> {
+    let target = relative_path(parent_dir(link), target);
+    #[cfg(unix)]
+    { std::os::unix::fs::symlink(target, link) }
+    #[cfg(windows)]
+    { std::os::windows::fs::symlink_file(target, link) }
+}
+
+// `target` as reached from `dir`, both absolute: Documents/a.pdf seen from
+// Work/ is ../Documents/a.pdf. Paths with nothing in common (another
+// drive) stay absolute.
+fn relative_path(dir: &Path, target: &Path) -> PathBuf {
+    let common = dir.components().zip(target.components()).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return target.to_path_buf();
+    }
+    let up = dir.components().skip(common).map(|_| std::path::Component::ParentDir);
+    up.chain(target.components().skip(common)).collect()
+}
+
+// Whether `path` is a symlink to something inside `real_base` (the target,
+// symlinks resolved). Checked on every run, not only with --leave-symlink,
+// so a later run without the flag doesn't sort the links as files. Only
+// symlinks are resolved.
+fn links_inside(path: &Path, real_base: Option<&Path>) -> bool {
+    let Some(real_base) = real_base else { return false };
+    path.is_symlink() && fs::canonicalize(path).is_ok_and(|to| to.starts_with(real_base))
+}
+
+// Whether `link` is a symlink resolving to the same file as `target`, like
+// the one --leave-symlink leaves behind. undo and restore clear it to put
+// the file back.
+pub fn is_link_to(link: &Path, target: &Path) -> bool {
+    link.is_symlink() && fs::canonicalize(link).is_ok_and(|a| fs::canonicalize(target).is_ok_and(|b| a == b))
+}
+
+// Whether another program has `path` open in a way that would break a move.
+// On Windows that's a handle opened without sharing (an Office document,
+// a file being downloaded); elsewhere open files can be moved freely, so
+// only a held lock (flock) counts.
+pub fn is_file_locked(path: &Path) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+        match fs::OpenOptions::new().read(true).write(true).share_mode(0).open(path) {
+            Ok(_) => false,
+            Err(e) => matches!(e.raw_os_error(), Some(32 | 33)),
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        use fs2::FileExt;
+        match fs::File::open(path) {
+            Ok(f) => f.try_lock_exclusive().and_then(|_| f.unlock()).is_err(),
+            Err(_) => false,
+        }
+    }
+}
+
+// --hardlink: link `to` to `from`, leaving `from` where it is. Across
+// filesystems (or where links aren't supported) it's a plain copy instead.
+pub fn link_or_copy(from: &Path, to: &Path) -> Result<(), OrganizeError> {
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map(drop)
+        .map_err(|source| OrganizeError::Move { from: from.to_path_buf(), to: to.to_path_buf(), source })
+}
+
+// Whether `to` (or the nearest folder of it that exists yet) is on the same
+// filesystem as `from`, so a hard link can join them. Assumed true where
+// that can't be told.
+pub fn same_device(from: &Path, to: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let existing = to.ancestors().find_map(|p| fs::metadata(p).ok());
+        match (fs::metadata(from), existing) {
+            (Ok(a), Some(b)) => a.dev() == b.dev(),
+            _ => true,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (from, to);
+        true
+    }
+}
+
+// Whether two paths are the same file on disk (hard links included).
+// Always false where that can't be told.
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => (a.dev(), a.ino()) == (b.dev(), b.ino()),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        false
+    }
+}
+
+// Whether a file modified at `modified` is still inside a quiet period
+// as of `now`: younger than `period`, or dated in the future. A file
+// exactly `period` old is sorted.
+pub fn in_quiet_period(now: SystemTime, modified: SystemTime, period: Duration) -> bool {
+    now.duration_since(modified).map_or(true, |age| age < period)
+}
+
+// The date a file belongs to for --date-from-name: from its name when it
+// has one, otherwise its modification time
+fn file_date(file_name: &str, meta: &fs::Metadata) -> chrono::NaiveDate {
+    date_from_name(file_name).unwrap_or_else(|| {
+        let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        chrono::DateTime::<Local>::from(modified).date_naive()
+    })
+}
+
+// Orientation or resolution subfolder for a picture, with --image-split
+fn image_subfolder_for(opts: &OrganizeOpts, path: &Path, ext: &str) -> Option<&'static str> {
+    let split = opts.image_split?;
+    let (width, height) = image_dimensions(path, ext)?;
+    Some(image_subfolder(split, width, height))
+}
+
+// Language subfolder for a text file, when --detect-language is on
+fn detect_language_for(opts: &OrganizeOpts, path: &Path, ext: &str) -> Option<&'static str> {
+    if !opts.detect_language || !TEXT_EXTENSIONS.contains(&ext) { return None; }
+    detect_language(path)
+}
+
+// Map `sub` onto folders already under `dir`: each component reuses an
+// existing folder whose name matches ignoring case and Unicode form, so
+// `work/Reports` lands in an existing `Work/Reports/` instead of beside it.
+// Components with no match are kept as given.
+pub fn merge_existing(dir: &Path, sub: &Path) -> PathBuf {
+    let fold = |s: &str| s.nfc().collect::<String>().to_lowercase();
+    let mut out = PathBuf::new();
+    for comp in sub.components() {
+        let name = comp.as_os_str();
+        let here = dir.join(&out);
+        let existing = if here.join(name).is_dir() {
+            None
+        } else {
+            let wanted = fold(&name.to_string_lossy());
+            fs::read_dir(&here).ok().and_then(|entries| {
+                entries.flatten()
+                    .filter(|e| e.path().is_dir())
+                    .map(|e| e.file_name())
+                    .find(|n| fold(&n.to_string_lossy()) == wanted)
+            })
+        };
+        out.push(existing.as_deref().unwrap_or(name));
+    }
+    out
+}
+
+// Read newline-separated paths (e.g. piped from `find`), keeping only
+// existing files under `base`. Relative paths are taken from `base`, not
+// the working directory. Returned paths are rebuilt on top of `base` so they
+// line up with what collect_files would have produced.
+pub fn read_file_list(reader: impl BufRead, base: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let root = base.canonicalize()?;
+    let mut out = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() { continue; }
+
+        let full = match base.join(line).canonicalize() {
+            Ok(p) if p.is_file() => p,
+            _ => {
+                eprintln!("{} {} is not a file, ignoring", "⚠".yellow(), line);
+                continue;
+            }
+        };
+        match full.strip_prefix(&root) {
+            Ok(rel) => out.push(base.join(rel)),
+            Err(_) => eprintln!("{} {} is outside {}, ignoring", "⚠".yellow(), line, base.display()),
+        }
+    }
+    Ok(out)
+}
+
+// Check if file is hidden or system junk.
+// Any name starting with a dot is hidden, whatever follows it: `.gitignore`,
+// `.env.local` and `.backup.tar` are all skipped before extensions matter.
 pub fn is_hidden_or_junk(path: &Path) -> bool {
     let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
-    name.starts_with('.') || matches!(name.as_ref(), "Thumbs.db" | "desktop.ini" | "organizer_log.txt")
+    name.starts_with('.') || is_junk(path)
+}
+
+// Files and folders operating systems (and the organizer itself) leave
+// around, matched by exact name
+pub const JUNK_NAMES: &[&str] = &[
+    // macOS
+    ".DS_Store", ".AppleDouble", ".localized", ".Spotlight-V100", ".Trashes", ".fseventsd",
+    // Windows
+    "Thumbs.db", "ehthumbs.db", "desktop.ini", "$RECYCLE.BIN", "$Recycle.Bin", "System Volume Information",
+    // Linux desktops (KDE's folder settings)
+    ".directory",
+    // Ours
+    "organizer_log.txt", "organizer_manifest.jsonl",
+];
+
+// Junk matched by pattern: AppleDouble resource forks (`._photo.jpg`, left
+// by macOS on non-Mac drives) and per-run logs
+pub const JUNK_PATTERNS: &[&str] = &["._*", RUN_LOG_PATTERN];
+
+// Whether `path` is OS litter or one of the organizer's own files (reported
+// as `junk` rather than `hidden`, even when the name starts with a dot)
+pub fn is_junk(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    JUNK_NAMES.contains(&name.as_ref())
+        || JUNK_PATTERNS.iter().any(|p| glob::Pattern::new(p).is_ok_and(|p| p.matches(&name)))
+}
+
+// Name of the logs written with --log-per-run
+pub const RUN_LOG_PATTERN: &str = "organizer_*.log";
+
+// Log file for one run, next to `log_file`:
+// organizer_log.txt -> organizer_2026-02-12_143000.log
+pub fn per_run_log_path(log_file: &Path) -> PathBuf {
+    let name = format!("organizer_{}.log", Local::now().format("%Y-%m-%d_%H%M%S"));
+    log_file.with_file_name(name)
+}
+
+// Fingerprint for duplicate detection: name + modification date + size,
+// namespaced by category when duplicates are only compared within one.
+// The name is NFC-normalized so NFD and NFC spellings match.
+pub fn duplicate_key(file_name: &str, meta: &fs::Metadata, scope: Option<&str>) -> std::io::Result<String> {
+    let date = chrono::DateTime::<Local>::from(meta.modified()?).format("%Y-%m-%d");
+    let name: String = file_name.nfc().collect();
+    Ok(format!("{}|{name}|{date}|{}", scope.unwrap_or(""), meta.len()))
+}
+
+// Files per (lowercase) extension, leaving out hidden, junk and extensionless ones
+pub fn count_extensions(files: &[PathBuf]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for ext in files.iter().filter(|f| !is_hidden_or_junk(f)).filter_map(|f| file_extension(f)) {
+        *counts.entry(ext).or_default() += 1;
+    }
+    counts
+}
+
+// Split sidecars off the file list. A file whose extension is in
+// `companions` goes with the file in the same folder that shares its stem
+// (photo.xmp -> photo.CR2) or whose whole name it extends (photo.CR2.xmp);
+// names compare case-insensitively. Returns the files to sort and the
+// sidecars of each. Sidecars without a main file are sorted as usual.
+pub fn group_companions(files: &[PathBuf], companions: &[String]) -> (Vec<PathBuf>, HashMap<PathBuf, Vec<PathBuf>>) {
+    let is_companion = |f: &PathBuf| file_extension(f).is_some_and(|e| companions.contains(&e));
+    let key = |f: &Path, name: &std::ffi::OsStr| (f.parent().map(Path::to_path_buf), name.to_string_lossy().to_lowercase());
+
+    // Main files by (folder, stem) and (folder, full name); the first one wins
+    let mut mains: HashMap<(Option<PathBuf>, String), &PathBuf> = HashMap::new();
+    for f in files.iter().filter(|f| !is_companion(f)) {
+        for name in [f.file_stem(), f.file_name()].into_iter().flatten() {
+            mains.entry(key(f, name)).or_insert(f);
+        }
+    }
+
+    let mut rest = Vec::new();
+    let mut grouped: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    for f in files {
+        let main = f.file_stem().filter(|_| is_companion(f)).and_then(|stem| mains.get(&key(f, stem)));
+        match main {
+            Some(main) => grouped.entry((*main).clone()).or_default().push(f.clone()),
+            None => rest.push(f.clone()),
+        }
+    }
+    (rest, grouped)
+}
+
+// New name for a sidecar whose main file moved from `main` to `main_dest`:
+// the part it shares with the main file's name follows that file's renaming
+// (photo.xmp -> photo_2026-02-12.xmp when photo.CR2 became photo_2026-02-12.CR2)
+fn companion_name(companion: &Path, main: &Path, main_dest: &Path) -> String {
+    let name = companion.file_name().unwrap_or_default().to_string_lossy();
+    let text = |p: Option<&std::ffi::OsStr>| p.unwrap_or_default().to_string_lossy().into_owned();
+    let [main_name, main_stem] = [text(main.file_name()), text(main.file_stem())];
+    let [dest_name, dest_stem] = [text(main_dest.file_name()), text(main_dest.file_stem())];
+    for (from, to) in [(main_name, dest_name), (main_stem, dest_stem)] {
+        if let Some(rest) = name.get(from.len()..).filter(|rest| rest.starts_with('.')) {
+            if name[..from.len()].eq_ignore_ascii_case(&from) {
+                return format!("{to}{rest}");
+            }
+        }
+    }
+    name.into_owned()
+}
+
+// Lowercased extension, or None if there isn't a real one.
+// A leading dot belongs to the name, not the extension, so `.gitignore` has
+// none while `.env.local` has "local". A trailing dot (`notes.`) has none.
+pub fn file_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .filter(|e| !e.is_empty())
+}
+
+// Rewrite the extension of a file name: ("Photo.JPG", Lower) -> "Photo.jpg".
+// Names without a real extension are returned unchanged.
+pub fn apply_extension_case(file_name: &str, case: ExtensionCase) -> String {
+    let path = Path::new(file_name);
+    let (Some(stem), Some(ext)) = (path.file_stem(), file_extension(path)) else {
+        return file_name.to_string();
+    };
+    let ext = match case {
+        ExtensionCase::Preserve => return file_name.to_string(),
+        ExtensionCase::Lower => ext,
+        ExtensionCase::Upper => ext.to_uppercase(),
+    };
+    format!("{}.{ext}", stem.to_string_lossy())
 }
 
 // Handle filename collisions: photo.jpg -> photo_2026-02-12.jpg -> photo_2026-02-12_v2.jpg
 pub fn resolve_collision(dir: &Path, original_name: &str, ext: &str) -> PathBuf {
-    let candidate = dir.join(original_name);
-    if !candidate.exists() { return candidate; }
+    resolve_collision_reserved(dir, original_name, ext, &mut HashSet::new())
+}
+
+// Same as resolve_collision, but names already handed out this run count as
+// taken even before anything is written there. The chosen path is reserved.
+pub fn resolve_collision_reserved(
+    dir: &Path,
+    original_name: &str,
+    ext: &str,
+    reserved: &mut HashSet<PathBuf>,
+) -> PathBuf {
+    resolve_collision_with(dir, original_name, ext, reserved, CASE_INSENSITIVE_FS)
+}
+
+// Whether this platform's usual filesystems ignore case in file names
+pub const CASE_INSENSITIVE_FS: bool = cfg!(any(target_os = "macos", windows));
+
+// resolve_collision_reserved with explicit case handling. With
+// `ignore_case`, `Photo.jpg` and `photo.jpg` are one name: existing files in
+// `dir` are compared case-insensitively too, so neither overwrites the other.
+pub fn resolve_collision_with(
+    dir: &Path,
+    original_name: &str,
+    ext: &str,
+    reserved: &mut HashSet<PathBuf>,
+    ignore_case: bool,
+) -> PathBuf {
+    resolve_collision_using(dir, original_name, ext, reserved, ignore_case, CollisionScheme::DateThenVersion)
+}
 
+// resolve_collision_with, naming the alternatives by `scheme`
+pub fn resolve_collision_using(
+    dir: &Path,
+    original_name: &str,
+    ext: &str,
+    reserved: &mut HashSet<PathBuf>,
+    ignore_case: bool,
+    scheme: CollisionScheme,
+) -> PathBuf {
+    // Reservations are compared in NFC so `café` typed two ways is one name
+    let key = |p: &Path| match ignore_case {
+        true => PathBuf::from(nfc_path(p).to_string_lossy().to_lowercase()),
+        false => nfc_path(p),
+    };
+    // Case-insensitively, a plain exists() isn't enough on a case-sensitive
+    // disk: reserve what's already in `dir` once (the dir's own key marks it)
+    if ignore_case && reserved.insert(key(dir)) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            reserved.extend(entries.flatten().map(|e| key(&e.path())));
+        }
+    }
+    let taken = |p: &PathBuf| p.exists() || reserved.contains(&key(p));
     let stem = Path::new(original_name).file_stem().unwrap_or_default().to_string_lossy();
-    let today = Local::now().format("%Y-%m-%d");
+    let dot_ext = if ext.is_empty() { String::new() } else { format!(".{ext}") };
+
+    // Keep trying numbered names until we find one that doesn't exist
+    let fallbacks: Box<dyn Iterator<Item = String>> = match scheme {
+        CollisionScheme::DateThenVersion => {
+            let today = Local::now().format("%Y-%m-%d");
+            Box::new(std::iter::once(format!("{stem}_{today}{dot_ext}"))
+                .chain((2..).map(move |n| format!("{stem}_{today}_v{n}{dot_ext}"))))
+        }
+        CollisionScheme::Numeric => Box::new((1..).map(|n| format!("{stem} ({n}){dot_ext}"))),
+        CollisionScheme::Timestamp => {
+            let now = Local::now().format("%Y%m%d_%H%M%S");
+            Box::new(std::iter::once(format!("{stem}_{now}{dot_ext}"))
+                .chain((2..).map(move |n| format!("{stem}_{now}_{n}{dot_ext}"))))
+        }
+    };
+    let chosen = std::iter::once(dir.join(original_name))
+        .chain(fallbacks.map(|name| dir.join(name)))
+        .find(|p| !taken(p))
+        .unwrap();
+    reserved.insert(key(&chosen));
+    chosen
+}
+
+// Where a file should go, as decided by resolve_collision_checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollisionOutcome {
+    // The name was free
+    Fresh(PathBuf),
+    // The name holds the same contents already; nothing needs moving
+    IdenticalSkip,
+    // The name was taken by something else; this is the new name
+    Renamed(PathBuf),
+}
+
+// resolve_collision_using, telling apart the three cases. With
+// `skip_identical`, a taken name whose file matches `source` byte for byte
+// (same size, same SHA-256) is IdenticalSkip instead of a rename.
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_collision_checked(
+    source: &Path,
+    dir: &Path,
+    original_name: &str,
+    ext: &str,
+    reserved: &mut HashSet<PathBuf>,
+    ignore_case: bool,
+    scheme: CollisionScheme,
+    skip_identical: bool,
+) -> CollisionOutcome {
+    let wanted = dir.join(original_name);
+    if skip_identical && same_contents(source, &wanted) {
+        return CollisionOutcome::IdenticalSkip;
+    }
+    let chosen = resolve_collision_using(dir, original_name, ext, reserved, ignore_case, scheme);
+    if chosen == wanted { CollisionOutcome::Fresh(chosen) } else { CollisionOutcome::Renamed(chosen) }
+}
 
-    let dated = dir.join(format!("{stem}_{today}.{ext}"));
-    if !dated.exists() { return dated; }
+// Whether two distinct files hold the same bytes (sizes first, then hashes)
+fn same_contents(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) if ma.is_file() && mb.is_file() && ma.len() == mb.len() => {
+            !same_file(a, b) && matches!((file_hash(a), file_hash(b)), (Ok(ha), Ok(hb)) if ha == hb)
+        }
+        _ => false,
+    }
+}
 
-    // Keep trying version numbers until we find one that doesn't exist
-    (2..).map(|n| dir.join(format!("{stem}_{today}_v{n}.{ext}")))
-        .find(|p| !p.exists())
-        .unwrap()
+// Unicode NFC form of a path, so composed and decomposed names compare equal
+// (macOS hands out NFD names; configs and user input are usually NFC)
+pub fn nfc_path(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().nfc().collect::<String>())
 }
 
 // Move a file — try rename first (fast), fall back to copy+delete
-pub fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
-    fs::rename(from, to).or_else(|_| {
-        fs::copy(from, to)?;
-        fs::remove_file(from)
-    })
+pub fn move_file(from: &Path, to: &Path) -> Result<(), OrganizeError> {
+    move_file_verified(from, to, false)
+}
+
+// Same as move_file, but with `verify` the copy fallback is hash-checked
+pub fn move_file_verified(from: &Path, to: &Path, verify: bool) -> Result<(), OrganizeError> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(_) => copy_then_remove(from, to, verify),
+    }
+}
+
+// Copy, then delete the source. With `verify`, the source is only deleted
+// if both files hash the same; a bad copy is removed and the source kept.
+pub fn copy_then_remove(from: &Path, to: &Path, verify: bool) -> Result<(), OrganizeError> {
+    if verify {
+        return copy_hashed_then_remove(from, to, true, false).map(drop);
+    }
+    fs::copy(from, to).map_err(|source| OrganizeError::Move { from: from.to_path_buf(), to: to.to_path_buf(), source })?;
+    fs::remove_file(from).at(from)
+}
+
+// Move and return the SHA-256 of what was moved. After a rename the file is
+// hashed where it landed; the copy fallback hashes while copying, so the
+// data is only read once.
+pub fn move_file_hashed(from: &Path, to: &Path, verify: bool) -> Result<String, OrganizeError> {
+    match fs::rename(from, to) {
+        Ok(()) => file_hash(to).at(to),
+        Err(_) => copy_hashed_then_remove(from, to, verify, false),
+    }
+}
+
+// Move for --fsync: a copy is flushed to disk before the source goes, and
+// once the file is in place it and both folders are flushed too, so a
+// crash leaves either the old state or the new one. Returns the SHA-256
+// when `hash` is set.
+pub fn move_file_durable(from: &Path, to: &Path, verify: bool, hash: bool) -> Result<Option<String>, OrganizeError> {
+    let sha256 = match fs::rename(from, to) {
+        Ok(()) if hash => Some(file_hash(to).at(to)?),
+        Ok(()) => None,
+        Err(_) => Some(copy_hashed_then_remove(from, to, verify, true)?).filter(|_| hash),
+    };
+    sync_move(from, to).at(to)?;
+    Ok(sha256)
+}
+
+// Flush a moved file, the folder it's in now and the one it left
+pub fn sync_move(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::File::open(to)?.sync_all()?;
+    sync_dir(parent_dir(to))?;
+    if parent_dir(from) != parent_dir(to) {
+        sync_dir(parent_dir(from))?;
+    }
+    Ok(())
+}
+
+// Flush a folder's entries (new, removed and renamed names) to disk. Only
+// Unix can open a folder for this; NTFS journals its entries anyway.
+pub fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    { fs::File::open(dir)?.sync_all() }
+    #[cfg(not(unix))]
+    { let _ = dir; Ok(()) }
+}
+
+// The folder a path sits in; "." for a bare name
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+fn copy_hashed_then_remove(from: &Path, to: &Path, verify: bool, fsync: bool) -> Result<String, OrganizeError> {
+    let failed = |source| OrganizeError::Move { from: from.to_path_buf(), to: to.to_path_buf(), source };
+    let hash = copy_hashing(from, to).map_err(failed)?;
+    if verify && file_hash(to).at(to)? != hash {
+        let _ = fs::remove_file(to);
+        return Err(failed(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "checksum mismatch after copy, source kept",
+        )));
+    }
+    // The copy must be on disk before the only other one is deleted
+    if fsync {
+        fs::File::open(to).and_then(|f| f.sync_all()).and_then(|_| sync_dir(parent_dir(to))).map_err(failed)?;
+    }
+    fs::remove_file(from).at(from)?;
+    Ok(hash)
+}
+
+// fs::copy, but the bytes are hashed on their way through
+fn copy_hashing(from: &Path, to: &Path) -> std::io::Result<String> {
+    let mut reader = fs::File::open(from)?;
+    let mut writer = fs::File::create(to)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = std::io::Read::read(&mut reader, &mut buf)?;
+        if n == 0 { break; }
+        hasher.update(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+    }
+    fs::set_permissions(to, reader.metadata()?.permissions())?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Give `to` the owner and group `original` had. Changing the owner needs
+// privileges (root, or CAP_CHOWN), so without them this quietly does as much
+// as it can: the group is kept when the user belongs to it. Returns whether
+// both were restored. Permission bits are kept by every move path anyway.
+pub fn restore_owner(original: &fs::Metadata, to: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let Ok(now) = fs::metadata(to) else { return false };
+        if (now.uid(), now.gid()) == (original.uid(), original.gid()) { return true; }
+        if std::os::unix::fs::chown(to, Some(original.uid()), Some(original.gid())).is_ok() { return true; }
+        let _ = std::os::unix::fs::chown(to, None, Some(original.gid()));
+        false
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (original, to);
+        false
+    }
+}
+
+// Run the --on-move command for one moved file. The command goes through the
+// shell, so it can do anything the user can — only pass commands you trust.
+// Paths are quoted before substitution, so odd file names can't inject.
+pub fn run_on_move(cmd: &str, src: &Path, dst: &Path) -> Result<(), OrganizeError> {
+    // Substitute in one pass, so a file literally named "{dst}" stays put
+    let dst = shell_quote(&dst.to_string_lossy());
+    let line = cmd
+        .split("{src}")
+        .map(|part| part.replace("{dst}", &dst))
+        .collect::<Vec<_>>()
+        .join(&shell_quote(&src.to_string_lossy()));
+    let hook_err = |message: String| OrganizeError::Hook { command: line.clone(), message };
+
+    #[cfg(windows)]
+    let status = std::process::Command::new("cmd").args(["/C", &line]).status();
+    #[cfg(not(windows))]
+    let status = std::process::Command::new("sh").args(["-c", &line]).status();
+
+    match status {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(hook_err(format!("exited with {s}"))),
+        Err(e) => Err(hook_err(e.to_string())),
+    }
+}
+
+// Quote one argument for the platform shell
+pub fn shell_quote(arg: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", arg.replace('"', "\"\""))
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+// Count with thousands separators: 49950 -> "49,950"
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, d) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) { out.push(','); }
+        out.push(d);
+    }
+    out
+}
+
+// Fail unless `needed` bytes plus a `min_free` buffer fit on the volume
+// holding `dir` (which may not exist yet: its nearest existing parent counts)
+pub fn check_free_space(dir: &Path, needed: u64, min_free: u64) -> Result<(), OrganizeError> {
+    let abs = std::path::absolute(dir).at(dir)?;
+    let probe = abs.ancestors().find(|p| p.exists()).unwrap_or(&abs);
+    let available = fs2::available_space(probe).at(probe)?;
+    if needed.saturating_add(min_free) > available {
+        return Err(OrganizeError::InsufficientSpace { path: dir.to_path_buf(), needed, min_free, available });
+    }
+    Ok(())
+}
+
+// Parse a size like "500M", "1.5G", "2GB" or "4096" (bytes), in 1024 steps
+pub fn parse_size(text: &str) -> Result<u64, String> {
+    let t = text.trim().to_ascii_uppercase();
+    let t = t.strip_suffix("IB").or_else(|| t.strip_suffix('B')).unwrap_or(&t);
+    let (num, shift) = match t.chars().last() {
+        Some('K') => (&t[..t.len() - 1], 10),
+        Some('M') => (&t[..t.len() - 1], 20),
+        Some('G') => (&t[..t.len() - 1], 30),
+        Some('T') => (&t[..t.len() - 1], 40),
+        _ => (t, 0),
+    };
+    let value: f64 = num.trim().parse().map_err(|_| format!("invalid size \"{text}\" (try 500M or 2G)"))?;
+    if value < 0.0 { return Err(format!("invalid size \"{text}\"")); }
+    Ok((value * (1u64 << shift) as f64) as u64)
+}
+
+// Parse an age like "90d", "6w", "18m" (30-day months), "2y" or "12h";
+// a bare number is days
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let t = text.trim().to_ascii_lowercase();
+    let (num, unit) = match t.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&t[..i], c),
+        _ => (t.as_str(), 'd'),
+    };
+    let hours = match unit {
+        'h' => 1,
+        'd' => 24,
+        'w' => 24 * 7,
+        'm' => 24 * 30,
+        'y' => 24 * 365,
+        _ => return Err(format!("invalid age \"{text}\" (try 90d, 6m or 2y)")),
+    };
+    let value: u64 = num.trim().parse().map_err(|_| format!("invalid age \"{text}\" (try 90d, 6m or 2y)"))?;
+    Ok(Duration::from_secs(value * hours * 3600))
+}
+
+// An age as parse_duration reads it, in the largest unit that fits
+// exactly: 1y, 6m, 2w, 3d, 12h
+pub fn format_duration(age: Duration) -> String {
+    let hours = age.as_secs() / 3600;
+    let units = [('y', 24 * 365), ('m', 24 * 30), ('w', 24 * 7), ('d', 24)];
+    match units.iter().find(|(_, size)| hours > 0 && hours.is_multiple_of(*size)) {
+        Some((unit, size)) => format!("{}{unit}", hours / size),
+        None => format!("{hours}h"),
+    }
+}
+
+// Human-readable size: 512 B, 1.5 KB, 3.2 MB …
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{bytes} B") } else { format!("{size:.1} {}", UNITS[unit]) }
+}
+
+// SHA-256 of a file's contents as lowercase hex
+pub fn file_hash(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
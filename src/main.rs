@@ -1,37 +1,509 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use colored::*;
-use smart_organizer::{organize, Config, OrganizeOpts};
-use std::path::PathBuf;
+use smart_organizer::{
+    analyze, apply_plan, explain_file, find_duplicate_sets, format_bytes, move_script, moves_in_categories, restore_snapshot, organize, parse_duration, parse_size, per_run_log_path, plan_by_category, plan_moves, read_file_list, read_mapping, remove_duplicates,
+    scan_tree, undo, verify_manifest, CancelToken, Categorizer, CollisionScheme, Config, ConfigFormat, DedupeScope, DeletePolicy, DuplicateStrategy, ExtensionCase, FileOutcome, ImageSplit, LayoutStep, OrganizeError, OrganizeOpts, RenameTemplate, RetentionAction, ScriptCategorizer, ScriptShell,
+    SkipReason, Stats, VerifyStatus,
+};
+use std::fs::{self, File};
+use std::io::{self, BufReader, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+mod tui;
+
+// Exit status of a --dry-run that found files to move, so scripts can
+// use a dry run as a check
+const EXIT_WOULD_CHANGE: i32 = 10;
+
+// Exit status of a run stopped with Ctrl-C (128 + SIGINT, as shells report it)
+const EXIT_INTERRUPTED: i32 = 130;
+
+const EXIT_STATUS: &str = "\
+Exit status:
+  0   success; with --dry-run, nothing would change
+  1   the run failed
+  10  --dry-run only: at least one file would be moved or archived
+  130 stopped with Ctrl-C; files already moved stay moved";
 
 // Command-line arguments the user can type
 #[derive(Parser, Debug)]
-#[command(name = "smart-organizer", version, about)]
+#[command(name = "smart-organizer", version, about, after_help = EXIT_STATUS)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     // Which folder to organize
-    #[arg(short, long, default_value = ".")]
+    #[arg(short, long, default_value = ".", global = true)]
     path: PathBuf,
 
+    // Print the config this run would use (defaults, config file and
+    // category/layout flags combined) as toml, json or yaml, and exit
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "toml")]
+    print_config: Option<ConfigFormat>,
+
+    // Category config to use instead of ./config.toml (.toml, .json, .yaml);
+    // repeat it to lay later files over earlier ones
+    #[arg(long, value_name = "FILE", global = true)]
+    config: Vec<PathBuf>,
+
     // Preview mode — don't actually move files
     #[arg(short, long)]
     dry_run: bool,
 
+    // List the N largest files moved in the summary
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    top_files: usize,
+
+    // Organize even a home, root or system folder
+    #[arg(long)]
+    force: bool,
+
+    // Also print a line for every file left in place (hidden, unmatched…)
+    #[arg(short, long)]
+    verbose: bool,
+
+    // When to use colors: auto (only on a terminal), always or never
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto, global = true)]
+    color: ColorMode,
+
     // Skip duplicate files
     #[arg(long)]
     find_duplicates: bool,
 
+    // Compare duplicates across the whole run or only within a category
+    #[arg(long, value_enum, default_value_t = DedupeScope::Global)]
+    dedupe_scope: DedupeScope,
+
+    // What makes two files duplicates: name+date+size, or identical content
+    #[arg(long, value_enum, default_value_t = DuplicateStrategy::NameDateSize, global = true)]
+    dup_strategy: DuplicateStrategy,
+
+    // Shorthand for --dup-strategy name-size: same name + size is a duplicate
+    // even when the modification dates differ
+    #[arg(long, global = true, conflicts_with = "dup_strategy")]
+    dup_ignore_date: bool,
+
+    // Only tidy the loose files directly in the folder; leave subfolders alone
+    #[arg(long, conflicts_with = "keep_structure")]
+    no_recurse: bool,
+
     // Keep subfolder layout inside categories
     #[arg(long)]
     keep_structure: bool,
+
+    // Group files by the folder they're in (ClientA/x.pdf -> Projects/ClientA/)
+    // instead of by extension
+    #[arg(long, conflicts_with_all = ["keep_structure", "no_recurse"])]
+    by_parent: bool,
+
+    // With --keep-structure, merge into existing subfolders that differ only
+    // in case (work/ -> Work/) instead of creating a parallel tree
+    #[arg(long, requires = "keep_structure")]
+    merge_into_existing: bool,
+
+    // Sort plain-text files (.txt, .md, …) into <category>/<Language>/
+    #[arg(long)]
+    detect_language: bool,
+
+    // Sort pictures one level deeper by orientation (Landscape, Portrait,
+    // Square) or resolution (4K, 1080p, Small), read from their headers
+    #[arg(long, value_enum, value_name = "BY")]
+    image_split: Option<ImageSplit>,
+
+    // Route .zip/.tar archives by their contents (a zip of photos goes to
+    // Images/Archives/); only entry names are read
+    #[arg(long)]
+    peek_archives: bool,
+
+    // Rewrite moved files' extensions: keep as-is, lowercase or uppercase
+    #[arg(long, value_enum, default_value_t = ExtensionCase::Preserve)]
+    extension_case: ExtensionCase,
+
+    // Name moved files from a template: {stem}, {ext}, {date}, {category},
+    // {counter}, e.g. "{date}_{stem}"
+    #[arg(long, value_name = "TEMPLATE")]
+    rename_template: Option<RenameTemplate>,
+
+    // How to rename a file whose destination is taken
+    #[arg(long, value_enum, default_value_t = CollisionScheme::DateThenVersion)]
+    collision_scheme: CollisionScheme,
+
+    // Treat Photo.jpg and photo.jpg as one name when picking destinations
+    // (always on for macOS and Windows; use on case-insensitive mounts)
+    #[arg(long)]
+    case_insensitive_names: bool,
+
+    // Replace characters FAT, exFAT and NTFS can't store (: ? * …) in
+    // destination names; automatic when the destination is such a volume
+    #[arg(long)]
+    sanitize_names: bool,
+
+    // After sorting, collapse folder chains where each folder holds just one
+    // subfolder, keeping the first N levels (Documents/a/b/c/x.pdf -> Documents/a/x.pdf)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "1", default_value_t = 0)]
+    collapse_chains: usize,
+
+    // Leave a file alone when its destination already holds the same
+    // contents, instead of moving it in under a new name
+    #[arg(long)]
+    skip_identical: bool,
+
+    // Leave files that another program has open (reported as in use)
+    // instead of failing halfway through moving them
+    #[arg(long)]
+    skip_locked: bool,
+
+    // Sort into <category>/<YYYY>/<MM>/ by the date in the file name
+    // (IMG_20240312_…, 2024-03-12 …), or the modification time without one
+    #[arg(long)]
+    date_from_name: bool,
+
+    // Hard-link files into the category folders and leave the originals in
+    // place (copies across filesystems)
+    #[arg(long, conflicts_with = "backup_dir")]
+    hardlink: bool,
+
+    // After each move, leave a symlink at the old path pointing to the new
+    // one, so tools that remember the old path still find the file
+    #[arg(long, conflicts_with = "hardlink")]
+    leave_symlink: bool,
+
+    // Review the planned moves in a full-screen list and apply only the
+    // ones left checked
+    #[arg(long, conflicts_with_all = ["preview_tree_dir", "resume"])]
+    tui: bool,
+
+    // Show how many files each category would get, then apply only the
+    // categories listed here (comma-separated) or, with none, the ones
+    // picked on the terminal
+    #[arg(long, value_name = "CATEGORIES", num_args = 0.., value_delimiter = ',',
+          conflicts_with_all = ["tui", "dry_run", "resume"])]
+    pick_categories: Option<Vec<String>>,
+
+    // Move nothing; write the plan to FILE as a shell script (mv on Unix,
+    // move on Windows) to review and run yourself
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tui", "dry_run"])]
+    emit_script: Option<PathBuf>,
+
+    // Build each file's folder from these steps, in order: category,
+    // extension, parent, by_date:Year|YearMonth|YearMonthDay
+    // (e.g. by_date:Year,category gives 2024/Images/); overrides `layout` in the config
+    #[arg(long, value_delimiter = ',', conflicts_with = "date_from_name")]
+    layout: Vec<LayoutStep>,
+
+    // Sort into how recently files were modified, then category:
+    // Recent/Images, ThisYear/Documents… (short for --layout age_bucket,category)
+    #[arg(long, conflicts_with_all = ["layout", "date_from_name"])]
+    group_by_date_modified_bucket: bool,
+
+    // Carry on an interrupted run: files whose destination already holds a
+    // same-sized copy are skipped, and new moves join the latest run
+    #[arg(long)]
+    resume: bool,
+
+    // Keep owner and group when a move has to copy (Unix; needs root to
+    // change the owner, otherwise only the group is kept)
+    #[arg(long)]
+    preserve_owner: bool,
+
+    // Hash-check cross-device copies before deleting the original
+    #[arg(long)]
+    verify: bool,
+
+    // Flush every move to disk before the next one (slower, but a crash
+    // can't lose or half-finish a move)
+    #[arg(long)]
+    fsync: bool,
+
+    // Record each moved file's SHA-256 in the log and manifest
+    #[arg(long)]
+    hash_log: bool,
+
+    // Milliseconds to wait between moves (gentler on network drives)
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    throttle_ms: u64,
+
+    // Most files per destination folder; extras spill into Images_2/, Images_3/…
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    max_per_folder: usize,
+
+    // Sort extensions no category lists by their MIME type (image/* -> Images, …)
+    #[arg(long)]
+    mime_fallback: bool,
+
+    // Only sort extensions that occur at least N times; one-off files stay put
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    min_ext_count: usize,
+
+    // Only sort extensions that occur at most N times (0 = no limit)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    max_ext_count: usize,
+
+    // Only create a category folder at least N files would go into; the
+    // rest stay put (0 = no minimum)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    min_files_to_create_folder: usize,
+
+    // Move files with unknown extensions into Other/<ext>/ instead of skipping them
+    #[arg(long)]
+    group_unknown_by_extension: bool,
+
+    // Gather files without an extension in one folder (NoExtension/ by
+    // default) instead of skipping them
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = smart_organizer::EXTENSIONLESS_ROOT)]
+    collect_extensionless: Option<String>,
+
+    // Leave this category's files alone for this run (repeatable)
+    #[arg(long, value_name = "CATEGORY")]
+    exclude_category: Vec<String>,
+
+    // Organize only this category, skip everything else (repeatable)
+    #[arg(long, value_name = "CATEGORY", conflicts_with = "exclude_category")]
+    only_category: Vec<String>,
+
+    // After the run, list every file left in place and why
+    #[arg(long)]
+    explain: bool,
+
+    // Same as the `explain FILE` command: show how FILE is categorized and
+    // where it would go, then stop
+    #[arg(long, value_name = "FILE", conflicts_with = "explain")]
+    explain_config: Option<PathBuf>,
+
+    // Ask before deleting more than N files at once (0 = don't ask)
+    #[arg(long, value_name = "N", default_value_t = 20, global = true)]
+    confirm_large_deletes: usize,
+
+    // Answer yes to confirmation prompts
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    // Send deleted files to the system trash instead of removing them
+    #[arg(long, global = true)]
+    trash: bool,
+
+    // Human-readable log of every run
+    #[arg(long, value_name = "FILE", default_value = "organizer_log.txt")]
+    log_file: PathBuf,
+
+    // Write each run to its own organizer_<date>_<time>.log next to --log-file
+    #[arg(long)]
+    log_per_run: bool,
+
+    // Where each move is recorded for `undo`
+    #[arg(long, value_name = "FILE", default_value = "organizer_manifest.jsonl", global = true)]
+    manifest: PathBuf,
+
+    // Write a JSON map of new path -> original path for every move
+    #[arg(long, value_name = "FILE")]
+    index_out: Option<PathBuf>,
+
+    // Before the first run, save a listing of every file in the target to
+    // INDEX, so `restore --from INDEX` can put the original layout back
+    #[arg(long, value_name = "INDEX")]
+    snapshot: Option<PathBuf>,
+
+    // Organize only the newline-separated paths read from stdin
+    #[arg(long, conflicts_with = "files_from")]
+    stdin_list: bool,
+
+    // Organize only the newline-separated paths listed in this file
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    // Pack files older than this (90d, 6m, 2y…) into Archive/<category>_<year>.zip
+    // and remove them, instead of moving them loose
+    #[arg(long, value_name = "AGE", value_parser = parse_duration, conflicts_with = "hardlink")]
+    archive_older_than: Option<std::time::Duration>,
+
+    // Categorize with this Rhai script first; files it returns nothing for
+    // go by the config
+    #[arg(long, value_name = "FILE")]
+    rules_script: Option<PathBuf>,
+
+    // Leave files modified within this long (24h, 3d…) alone; they're
+    // reported as too recent and sorted by a later run
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    quiet_period: Option<std::time::Duration>,
+
+    // Keep only the N newest files of each extension (per-extension counts
+    // go in [keep_newest] in the config); older ones are trashed
+    #[arg(long, value_name = "N")]
+    keep_newest: Option<usize>,
+
+    // What --keep-newest does with the older files
+    #[arg(long, value_enum, value_name = "ACTION", default_value_t = RetentionAction::Trash)]
+    keep_newest_action: RetentionAction,
+
+    // With --find-duplicates, keep fingerprints on disk rather than in
+    // memory once a run has more than N files (0 = always in memory)
+    #[arg(long, value_name = "N", default_value_t = smart_organizer::DEDUPE_DISK_THRESHOLD)]
+    dedupe_disk_threshold: usize,
+
+    // Merge categories whose names differ only by case (Images, images)
+    // into one folder
+    #[arg(long)]
+    case_fold_categories: bool,
+
+    // CSV (path,category) or JSON ({"path": "category"}) file of explicit
+    // categories for specific files; the rest are categorized as usual
+    #[arg(long, value_name = "FILE")]
+    mapping: Option<PathBuf>,
+
+    // Shell command to run after each move, e.g. "notify-send {dst}"; {src}
+    // and {dst} are replaced with quoted paths. Runs with your permissions.
+    #[arg(long, value_name = "CMD")]
+    on_move: Option<String>,
+
+    // Put every category folder under this one, e.g. ".organized"
+    #[arg(long, value_name = "NAME")]
+    root: Option<PathBuf>,
+
+    // Build the planned layout in DIR out of links to the originals, to
+    // browse before committing (implies --dry-run; delete DIR afterwards)
+    #[arg(long, value_name = "DIR")]
+    preview_tree_dir: Option<PathBuf>,
+
+    // With --backup-dir, keep at least this much free on its volume (e.g. 2G)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "0")]
+    min_free: u64,
+
+    // With --dry-run, print only the first N planned moves (0 = all)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    preview_limit: usize,
+
+    // Copy every file here (same relative path) before moving it
+    #[arg(long, value_name = "DIR")]
+    backup_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+// Extra modes besides the default organize run
+#[derive(Subcommand, Debug)]
+enum Command {
+    // List every extension found, its category (or UNMATCHED) and count
+    Analyze {
+        // Only show extensions with no category
+        #[arg(long)]
+        unmatched_only: bool,
+    },
+    // Count files, folders and depth without moving anything
+    Scan,
+    // Show, step by step, how one file is categorized and where a run with
+    // the same options would put it; moves nothing
+    Explain {
+        // The file, as given or relative to --path
+        file: PathBuf,
+    },
+    // Report sets of duplicate files without organizing anything
+    Dedupe {
+        // Delete every copy but one in each set
+        #[arg(long)]
+        apply: bool,
+    },
+    // Move the files of one run back to where they were
+    Undo {
+        // Run id from the manifest (defaults to the latest run)
+        #[arg(long)]
+        run: Option<String>,
+    },
+    // Move every file listed in a --snapshot index back to where it was,
+    // following the manifest through all runs since
+    Restore {
+        // The snapshot index
+        #[arg(long, value_name = "INDEX")]
+        from: PathBuf,
+    },
+    // Re-hash the files a --hash-log run recorded and report any that are
+    // missing or changed; moves nothing
+    Verify {
+        // Run id from the manifest (defaults to every run)
+        #[arg(long)]
+        run: Option<String>,
+        // Only print the files that fail
+        #[arg(long)]
+        quiet: bool,
+    },
 }
 
 fn main() {
-    // Print header
-    println!("{}", "═══════════════════════════════════════".cyan());
-    println!("{}", "      Smart File Organizer  v1.1".cyan().bold());
-    println!("{}", "═══════════════════════════════════════\n".cyan());
+    let mut args = Args::parse();
+    match args.color {
+        ColorMode::Auto => colored::control::set_override(io::stdout().is_terminal()),
+        ColorMode::Always => colored::control::set_override(true),
+        ColorMode::Never => colored::control::set_override(false),
+    }
 
-    let args = Args::parse();
-    let config = Config::load();
+    // Print header (not over a config meant to be piped somewhere)
+    if args.print_config.is_none() {
+        println!("{}", "═══════════════════════════════════════".cyan());
+        println!("{}", "      Smart File Organizer  v1.1".cyan().bold());
+        println!("{}", "═══════════════════════════════════════\n".cyan());
+    }
+
+    args.dry_run |= args.preview_tree_dir.is_some();
+    if args.dup_ignore_date {
+        args.dup_strategy = DuplicateStrategy::NameSize;
+    }
+    let mut config = match args.config.as_slice() {
+        [] => Config::load(),
+        paths => Config::load_layered(paths).unwrap_or_else(|e| {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }),
+    };
+
+    // Switch categories off (or all but some) for this run
+    for name in args.exclude_category.iter().chain(&args.only_category) {
+        if !config.category_names().contains(&name.as_str()) {
+            eprintln!("{} no category named \"{}\"", "⚠".yellow(), name);
+        }
+    }
+    if args.case_fold_categories {
+        for warning in config.fold_category_case() {
+            eprintln!("{} {}", "⚠".yellow(), warning);
+        }
+    }
+    config.exclude_categories(&args.exclude_category);
+    if !args.only_category.is_empty() {
+        config.only_categories(&args.only_category);
+    }
+    // The command line's layout replaces the config's
+    if args.group_by_date_modified_bucket {
+        config.layout = vec![LayoutStep::AgeBucket, LayoutStep::Category];
+    } else if !args.layout.is_empty() {
+        config.layout = std::mem::take(&mut args.layout);
+    }
+
+    if let Some(format) = args.print_config {
+        match config.render(format) {
+            Ok(text) => print!("{text}"),
+            Err(e) => {
+                eprintln!("{} could not write the config: {}", "✗".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Undo { run }) = &args.command {
+        run_undo(&args.manifest, run.as_deref());
+        return;
+    }
+    if let Some(Command::Restore { from }) = &args.command {
+        run_restore(from, &args.manifest);
+        return;
+    }
+    if let Some(Command::Verify { run, quiet }) = &args.command {
+        run_verify(&args.manifest, run.as_deref(), *quiet);
+        return;
+    }
 
     // Make sure the path is a real folder
     if !args.path.is_dir() {
@@ -39,34 +511,272 @@ fn main() {
         std::process::exit(1);
     }
 
+    if let Some(Command::Scan) = args.command {
+        match scan_tree(&args.path, &config) {
+            Ok(t) => println!(
+                "📂 {}: {} file(s) in {} folder(s), max depth {}",
+                args.path.display(), t.files, t.dirs, t.max_depth
+            ),
+            Err(e) => {
+                eprintln!("{} {}", "✗".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Whatever a command deletes, it deletes under the same policy
+    let policy = DeletePolicy {
+        max_files: args.confirm_large_deletes,
+        assume_yes: args.yes,
+        to_trash: args.trash,
+        ..Default::default()
+    };
+
+    if let Some(Command::Dedupe { apply }) = args.command {
+        run_dedupe(&args.path, args.dup_strategy, apply, &policy);
+        return;
+    }
+
+    if let Some(Command::Analyze { unmatched_only }) = args.command {
+        run_analyze(&args.path, &config, unmatched_only);
+        return;
+    }
+
     if args.dry_run {
         println!("{}", "📋 PREVIEW MODE — no files will be moved\n".yellow().bold());
     }
 
     println!("📁 Target: {}\n", args.path.display());
 
+    // Read an explicit file list instead of scanning, if asked to
+    let file_list = if args.stdin_list {
+        Some(read_file_list(io::stdin().lock(), &args.path))
+    } else {
+        args.files_from.as_ref().map(|f| File::open(f).and_then(|f| read_file_list(BufReader::new(f), &args.path)))
+    };
+    let file_list = match file_list.transpose() {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("{} could not read file list: {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let mapping = match &args.mapping {
+        Some(file) => read_mapping(file, &args.path).unwrap_or_else(|e| {
+            eprintln!("{} could not read mapping: {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }),
+        None => Default::default(),
+    };
+
+    // Ctrl-C stops the run at the next file, so nothing is left half-moved;
+    // a second Ctrl-C quits on the spot
+    let cancel = CancelToken::new();
+    let on_interrupt = cancel.clone();
+    let installed = ctrlc::set_handler(move || {
+        if on_interrupt.is_cancelled() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("\n{}", "Stopping after the current file… (Ctrl-C again to quit now)".yellow());
+        on_interrupt.cancel();
+    });
+    if let Err(e) = installed {
+        eprintln!("{} Ctrl-C will quit without finishing the log: {}", "⚠".yellow(), e);
+    }
+
     // Run the organizer
     let opts = OrganizeOpts {
         path: args.path,
         dry_run: args.dry_run,
         find_duplicates: args.find_duplicates,
+        dedupe_scope: args.dedupe_scope,
+        dup_strategy: args.dup_strategy,
         keep_structure: args.keep_structure,
+        extension_case: args.extension_case,
+        rename_template: args.rename_template.clone(),
+        collision_scheme: args.collision_scheme,
+        verify: args.verify,
+        fsync: args.fsync,
+        throttle_ms: args.throttle_ms,
+        max_per_folder: args.max_per_folder,
+        group_unknown: args.group_unknown_by_extension,
+        collect_extensionless: args.collect_extensionless.clone(),
+        manifest: Some(args.manifest),
+        log_file: Some(match args.log_per_run {
+            true => per_run_log_path(&args.log_file),
+            false => args.log_file,
+        }),
+        quiet: false,
+        index_out: args.index_out,
+        snapshot: args.snapshot,
+        file_list,
+        on_move: args.on_move,
+        backup_dir: args.backup_dir,
+        preview_limit: args.preview_limit,
+        root: args.root,
+        hash_log: args.hash_log,
+        merge_into_existing: args.merge_into_existing,
+        detect_language: args.detect_language,
+        image_split: args.image_split,
+        preview_tree_dir: args.preview_tree_dir,
+        case_insensitive_names: args.case_insensitive_names || smart_organizer::CASE_INSENSITIVE_FS,
+        sanitize_names: args.sanitize_names,
+        peek_archives: args.peek_archives,
+        verbose: args.verbose,
+        no_recurse: args.no_recurse,
+        min_free: args.min_free,
+        preserve_owner: args.preserve_owner,
+        resume: args.resume,
+        mime_fallback: args.mime_fallback,
+        force: args.force,
+        top_files: args.top_files,
+        by_parent: args.by_parent,
+        hardlink: args.hardlink,
+        leave_symlink: args.leave_symlink,
+        date_from_name: args.date_from_name,
+        skip_identical: args.skip_identical,
+        skip_locked: args.skip_locked,
+        mapping,
+        archive_older_than: args.archive_older_than,
+        quiet_period: args.quiet_period,
+        dedupe_disk_threshold: args.dedupe_disk_threshold,
+        cancel,
+        keep_newest: args.keep_newest,
+        keep_newest_per_ext: config.keep_newest.iter()
+            .map(|(ext, n)| (ext.trim().trim_start_matches('.').to_ascii_lowercase(), *n))
+            .collect(),
+        keep_newest_action: args.keep_newest_action,
+        delete_policy: policy,
+        confirm_delete: confirm,
+        layout: config.layout.clone(),
+        age_buckets: config.age_buckets.clone(),
+        collapse_chains: args.collapse_chains,
+        min_ext_count: args.min_ext_count,
+        max_ext_count: args.max_ext_count,
+        min_files_to_create_folder: args.min_files_to_create_folder,
+    };
+
+    let scripted = match args.rules_script.as_deref().map(|p| ScriptCategorizer::load(p, &config)).transpose() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
     };
+    let categorizer: &dyn Categorizer = match &scripted {
+        Some(script) => script,
+        None => &config,
+    };
+
+    let explained = match &args.command {
+        Some(Command::Explain { file }) => Some(file),
+        _ => args.explain_config.as_ref(),
+    };
+    if let Some(file) = explained {
+        let file = if file.exists() { file.clone() } else { opts.path.join(file) };
+        run_explain(&opts, categorizer, &file);
+        return;
+    }
+
+    if let Some(script) = &args.emit_script {
+        match emit_script(&opts, categorizer, script) {
+            Ok(n) => println!("{} {} move(s) written to {}", "✓".green().bold(), n, script.display()),
+            Err(e) => {
+                eprintln!("\n{} {}", "✗".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-    match organize(&opts, &config) {
+    let result = match (&args.pick_categories, args.tui) {
+        (Some(given), _) => pick_and_apply(&opts, categorizer, given),
+        (None, true) => review_and_apply(&opts, categorizer),
+        (None, false) => organize(&opts, categorizer),
+    };
+    match result {
         Ok(stats) => {
             println!();
             let label = if opts.dry_run { "would be moved" } else { "organized" };
-            println!("{} {} file(s) {}", "✓".green().bold(), stats.moved, label);
+            if stats.cancelled {
+                println!("{} Cancelled: {} file(s) {} before stopping", "⚠".yellow().bold(), stats.moved, label);
+            } else {
+                println!("{} {} file(s) {}", "✓".green().bold(), stats.moved, label);
+            }
 
-            if stats.duplicates > 0 { println!("   {} duplicate(s) found", stats.duplicates); }
+            if stats.duplicates > 0 {
+                match stats.duplicate_bytes {
+                    0 => println!("   {} duplicate(s) skipped", stats.duplicates),
+                    saved => println!("   {} duplicate(s) skipped (saved {})", stats.duplicates, format_bytes(saved)),
+                }
+            }
             if stats.skipped > 0    { println!("   {} file(s) skipped", stats.skipped); }
-            if stats.errors > 0     { println!("   {} error(s)", stats.errors.to_string().red()); }
+            if stats.archived > 0   { println!("   {} old file(s) archived", stats.archived); }
+            if stats.pruned > 0 {
+                let gone = match (opts.dry_run, opts.prune_policy().to_trash) {
+                    (true, _) => "would be removed",
+                    (false, true) => "moved to the trash",
+                    (false, false) => "deleted",
+                };
+                println!("   {} old file(s) {}", stats.pruned, gone);
+            }
+            if stats.locked > 0     { println!("   {} file(s) in use, left alone", stats.locked); }
+            if stats.recent > 0     { println!("   {} recent file(s) left for later", stats.recent); }
+            if stats.sparse > 0     { println!("   {} file(s) left, too few for a new folder", stats.sparse); }
+            if stats.renamed > 0    { println!("   {} file(s) renamed to avoid a clash", stats.renamed); }
+            if let Some(dir) = opts.backup_dir.as_ref().filter(|_| !opts.dry_run) {
+                println!("   {} backed up to {}", format_bytes(stats.backup_bytes), dir.display());
+            }
+            if stats.errors > 0 {
+                println!("   {} error(s):", stats.errors.to_string().red());
+                for (path, why) in &stats.failures {
+                    println!("     {} — {}", path.display(), why.dimmed());
+                }
+            }
+
+            if !stats.largest.is_empty() {
+                println!("   Largest files:");
+                for (bytes, path) in &stats.largest {
+                    let shown = path.strip_prefix(&opts.path).unwrap_or(path);
+                    println!("     {:>10}  {}", format_bytes(*bytes), shown.display());
+                }
+            }
+
+            if args.explain && !stats.untouched.is_empty() {
+                print_explanation(&opts.path, &stats.untouched);
+            }
 
             if opts.dry_run {
+                // Space each category would receive, biggest first
+                let mut sizes: Vec<_> = stats.bytes_by_category.iter().collect();
+                sizes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+                for (category, bytes) in sizes {
+                    println!("   {:<12} {:>10}", category, format_bytes(*bytes));
+                }
+                if !stats.bytes_by_category.is_empty() {
+                    let total: u64 = stats.bytes_by_category.values().sum();
+                    let usage = match stats.would_copy_bytes {
+                        0 => "net disk usage unchanged".to_string(),
+                        copied => format!("{} more disk used by copies", format_bytes(copied)),
+                    };
+                    println!("   {}", format!("{} to move, {usage}", format_bytes(total)).dimmed());
+                }
                 println!("{}", "   Run without --dry-run to apply.".yellow());
+                if stats.cancelled {
+                    std::process::exit(EXIT_INTERRUPTED);
+                }
+                if stats.has_changes() {
+                    std::process::exit(EXIT_WOULD_CHANGE);
+                }
             } else {
-                println!("{}", "   See organizer_log.txt for details.".dimmed());
+                if let Some(log) = &opts.log_file {
+                    println!("{}", format!("   See {} for details.", log.display()).dimmed());
+                }
+                if stats.cancelled {
+                    std::process::exit(EXIT_INTERRUPTED);
+                }
             }
         }
         Err(e) => {
@@ -75,3 +785,236 @@ fn main() {
         }
     }
 }
+
+// Print how `file` is categorized, one step per line, then its destination
+fn run_explain(opts: &OrganizeOpts, categorizer: &dyn Categorizer, file: &Path) {
+    let explanation = match explain_file(opts, categorizer, file) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+    println!("🔎 {}", file.display());
+    for step in &explanation.steps {
+        println!("   {}", step.dimmed());
+    }
+    match explanation.outcome {
+        FileOutcome::MovesTo(to) => {
+            let shown = to.strip_prefix(&opts.path).unwrap_or(&to);
+            println!("{} would go to {}", "→".green().bold(), shown.display().to_string().yellow());
+        }
+        FileOutcome::Stays(reason) => println!("{} would stay where it is ({})", "·".yellow().bold(), reason),
+        FileOutcome::Fails(message) => println!("{} would fail: {}", "✗".red().bold(), message),
+    }
+}
+
+// Files left in place, grouped by reason
+fn print_explanation(base: &std::path::Path, untouched: &[(PathBuf, SkipReason)]) {
+    let mut sorted: Vec<_> = untouched.iter().collect();
+    sorted.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    println!("\n   {}", "Left untouched:".bold());
+    for (path, reason) in sorted {
+        let shown = path.strip_prefix(base).unwrap_or(path).display();
+        println!("     {:<20} {}", reason.code().yellow(), shown);
+    }
+}
+
+// --tui: plan the run, let the user prune it, then apply what's left
+fn review_and_apply(opts: &OrganizeOpts, categorizer: &dyn Categorizer) -> Result<Stats, OrganizeError> {
+    let plan = plan_moves(opts, categorizer)?;
+    if plan.is_empty() {
+        println!("No files to organize.");
+        return Ok(Stats::default());
+    }
+    match tui::review(&opts.path, &plan)? {
+        Some(kept) => apply_plan(opts, &kept),
+        None => Err(OrganizeError::Cancelled("plan review closed without applying".into())),
+    }
+}
+
+// --pick-categories: plan the run, show it per category, then apply the
+// moves of the categories given (or asked for on the terminal)
+fn pick_and_apply(opts: &OrganizeOpts, categorizer: &dyn Categorizer, given: &[String]) -> Result<Stats, OrganizeError> {
+    let plan = plan_moves(opts, categorizer)?;
+    if plan.is_empty() {
+        println!("No files to organize.");
+        return Ok(Stats::default());
+    }
+    let groups = plan_by_category(opts, &plan);
+    println!("Planned moves by category:");
+    for (n, (category, count)) in groups.iter().enumerate() {
+        println!("  {:>3}. {:<24} {:>6} file(s)", n + 1, category, count);
+    }
+
+    let picked = if !given.is_empty() {
+        given.to_vec()
+    } else if io::stdin().is_terminal() {
+        ask_categories(&groups)
+    } else {
+        return Err(OrganizeError::Options("--pick-categories needs the categories to apply when stdin isn't a terminal".into()));
+    };
+    for name in &picked {
+        if !groups.iter().any(|(c, _)| c.to_lowercase() == name.to_lowercase()) {
+            eprintln!("{} nothing planned for \"{}\"", "⚠".yellow(), name);
+        }
+    }
+    let moves = moves_in_categories(opts, &plan, &picked);
+    if moves.is_empty() {
+        return Err(OrganizeError::Cancelled("no categories picked".into()));
+    }
+    apply_plan(opts, &moves)
+}
+
+// Ask which categories to apply: numbers from the list or names, separated
+// by commas, or "all". An empty answer picks none.
+fn ask_categories(groups: &[(String, usize)]) -> Vec<String> {
+    print!("{} Apply which categories? (e.g. 1,3 or Images,Documents; all) ", "?".yellow().bold());
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() { return Vec::new(); }
+    if answer.trim().eq_ignore_ascii_case("all") {
+        return groups.iter().map(|(c, _)| c.clone()).collect();
+    }
+    answer.split(',').map(str::trim).filter(|p| !p.is_empty()).map(|pick| match pick.parse::<usize>() {
+        Ok(n) if (1..=groups.len()).contains(&n) => groups[n - 1].0.clone(),
+        _ => pick.to_string(),
+    }).collect()
+}
+
+// --emit-script: plan the run and write it out with absolute paths, so the
+// script works from any folder. Returns the number of moves.
+fn emit_script(opts: &OrganizeOpts, categorizer: &dyn Categorizer, script: &Path) -> Result<usize, OrganizeError> {
+    let plan = plan_moves(opts, categorizer)?;
+    let absolute = |p: &PathBuf| std::path::absolute(p).unwrap_or_else(|_| p.clone());
+    let plan: Vec<_> = plan.iter().map(|(from, to)| (absolute(from), absolute(to))).collect();
+    fs::write(script, move_script(&plan, ScriptShell::native()))
+        .map_err(|source| OrganizeError::Io { path: script.to_path_buf(), source })?;
+    Ok(plan.len())
+}
+
+// Ask on the terminal before a big deletion
+fn confirm(what: &str) -> bool {
+    print!("{} Delete {}? [y/N] ", "?".yellow().bold(), what);
+    io::stdout().flush().ok();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+// Print the extension table for `analyze`
+fn run_analyze(path: &std::path::Path, config: &Config, unmatched_only: bool) {
+    let report = match analyze(path, config) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("{:<12} {:<16} {:>7}", "EXTENSION".bold(), "CATEGORY".bold(), "COUNT".bold());
+    for row in report.iter().filter(|r| !unmatched_only || r.category.is_none()) {
+        let ext = if row.ext.is_empty() { "(none)".to_string() } else { format!(".{}", row.ext) };
+        let category = match &row.category {
+            Some(c) => c.green(),
+            None => "UNMATCHED".yellow(),
+        };
+        println!("{:<12} {:<16} {:>7}", ext, category, row.count);
+    }
+
+    let unmatched: usize = report.iter().filter(|r| r.category.is_none()).map(|r| r.count).sum();
+    let total: usize = report.iter().map(|r| r.count).sum();
+    println!("\n{} file(s), {} unmatched", total, unmatched);
+}
+
+// Reverse one run recorded in the manifest
+fn run_undo(manifest: &std::path::Path, run: Option<&str>) {
+    match undo(manifest, run) {
+        Ok(stats) => {
+            println!("{} {} file(s) restored", "✓".green().bold(), stats.moved);
+            if stats.errors > 0 {
+                println!("   {} could not be restored (kept in the manifest)", stats.errors.to_string().red());
+            }
+        }
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Put a snapshot's files back where they were
+fn run_restore(index: &std::path::Path, manifest: &std::path::Path) {
+    match restore_snapshot(index, manifest) {
+        Ok(stats) => {
+            println!("{} {} file(s) restored", "✓".green().bold(), stats.moved);
+            if stats.errors > 0 {
+                println!("   {} could not be restored", stats.errors.to_string().red());
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Check recorded hashes; exits nonzero if any file is missing or changed
+fn run_verify(manifest: &std::path::Path, run: Option<&str>, quiet: bool) {
+    let results = verify_manifest(manifest, run).unwrap_or_else(|e| {
+        eprintln!("{} {}", "✗".red().bold(), e);
+        std::process::exit(1);
+    });
+    for (path, status) in &results {
+        match status {
+            VerifyStatus::Intact if quiet => {}
+            VerifyStatus::Intact => println!("  {} {}", "ok  ".green(), path.display()),
+            VerifyStatus::Missing => println!("  {} {}", "MISS".red(), path.display()),
+            VerifyStatus::Mismatch { .. } => println!("  {} {} (contents changed)", "FAIL".red(), path.display()),
+            VerifyStatus::Unreadable(why) => println!("  {} {} ({})", "FAIL".red(), path.display(), why),
+        }
+    }
+    let failed = results.iter().filter(|(_, s)| !s.is_ok()).count();
+    if results.is_empty() {
+        println!("No recorded hashes to check; organize with --hash-log to record them.");
+    } else if failed == 0 {
+        println!("{} {} file(s) verified", "✓".green().bold(), results.len());
+    } else {
+        eprintln!("{} {} of {} file(s) failed verification", "✗".red().bold(), failed, results.len());
+        std::process::exit(1);
+    }
+}
+
+// Print duplicate sets, optionally deleting the extra copies
+fn run_dedupe(path: &std::path::Path, strategy: DuplicateStrategy, apply: bool, policy: &DeletePolicy) {
+    let sets = match find_duplicate_sets(path, strategy) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{} {}", "✗".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    for set in &sets {
+        println!("{} {} copies, {} each", "●".yellow(), set.files.len(), format_bytes(set.size));
+        for (i, file) in set.files.iter().enumerate() {
+            let shown = file.strip_prefix(path).unwrap_or(file).display();
+            if i == 0 { println!("    {} {}", "keep".green(), shown); } else { println!("    {} {}", "dup ".dimmed(), shown); }
+        }
+    }
+
+    let wasted: u64 = sets.iter().map(|s| s.wasted()).sum();
+    println!("\n{} duplicate set(s), {} wasted", sets.len(), format_bytes(wasted));
+
+    if apply {
+        match remove_duplicates(&sets, policy, confirm) {
+            Ok(n) => println!("{} {} duplicate(s) deleted", "✓".green().bold(), n),
+            Err(e) => {
+                eprintln!("{} {}", "✗".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+    } else if !sets.is_empty() {
+        println!("{}", "   Run with --apply to delete all but the first copy of each set.".yellow());
+    }
+}
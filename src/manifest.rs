@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::error::IoContext;
+use crate::{file_hash, is_link_to, move_file, remove_dir_if_empty, OrganizeError, Stats};
+
+// ── Move manifest ────────────────────────────
+// One JSON object per line, one line per move. Unlike organizer_log.txt this
+// is meant for programs: undo reads it back to reverse a whole run.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    pub run_id: String,
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub timestamp: String,
+    // Content hash at move time, recorded with --hash-log
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    // Made with --hardlink: `src` was never moved, so undo just removes `dst`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub linked: bool,
+}
+
+// Unique-enough id for one organize run, e.g. "20260212-143000-123"
+pub fn new_run_id() -> String {
+    Local::now().format("%Y%m%d-%H%M%S-%3f").to_string()
+}
+
+// Append one move to the manifest
+pub fn write_manifest(out: &mut impl Write, entry: &ManifestEntry) -> std::io::Result<()> {
+    let line = serde_json::to_string(entry)?;
+    writeln!(out, "{line}")
+}
+
+// Read every entry back, oldest first. A missing manifest is just empty.
+pub fn read_manifest(path: &Path) -> std::io::Result<Vec<ManifestEntry>> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        entries.push(serde_json::from_str(&line)?);
+    }
+    Ok(entries)
+}
+
+// Move every file of one run (default: the latest) back where it came from,
+// newest move first. Restored entries are dropped from the manifest; ones
+// that couldn't be restored stay so the undo can be retried.
+pub fn undo(manifest: &Path, run_id: Option<&str>) -> Result<Stats, OrganizeError> {
+    let entries = read_manifest(manifest).at(manifest)?;
+    let mut stats = Stats::default();
+    let Some(run_id) = run_id.or_else(|| entries.last().map(|e| e.run_id.as_str())) else {
+        return Ok(stats);
+    };
+    let run_id = run_id.to_string();
+
+    // Entries that couldn't be restored stay, where they were in the file
+    let mut failed: HashSet<usize> = HashSet::new();
+    for (n, entry) in entries.iter().enumerate().rev().filter(|(_, e)| e.run_id == run_id) {
+        // A link --leave-symlink left at the original path makes way
+        let left_link = is_link_to(&entry.src, &entry.dst);
+        let restored = if entry.linked {
+            fs::remove_file(&entry.dst).at(&entry.dst)
+        } else if entry.src.exists() && !left_link {
+            Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "original path is taken")).at(&entry.src)
+        } else {
+            let unlinked = if left_link { fs::remove_file(&entry.src).at(&entry.src) } else { Ok(()) };
+            unlinked
+                .and_then(|_| entry.src.parent().map_or(Ok(()), |dir| fs::create_dir_all(dir).at(dir)))
+                .and_then(|_| move_file(&entry.dst, &entry.src))
+        };
+        match restored {
+            Ok(()) => {
+                // Tidy up a folder this run created, if it's now empty
+                if let Some(dir) = entry.dst.parent() { remove_dir_if_empty(dir); }
+                stats.moved += 1;
+            }
+            Err(e) => {
+                eprintln!("  ✗ {} — {}", entry.dst.display(), e);
+                stats.errors += 1;
+                failed.insert(n);
+            }
+        }
+    }
+
+    let keep = entries.iter().enumerate().filter(|(n, e)| e.run_id != run_id || failed.contains(n));
+    replace_manifest(manifest, keep.map(|(_, entry)| entry))?;
+    Ok(stats)
+}
+
+// Swap the manifest for one holding only `entries`. They are written to a
+// temporary file next to it, which then takes its place, so a failure part
+// way leaves the old manifest as it was.
+fn replace_manifest<'a>(manifest: &Path, entries: impl Iterator<Item = &'a ManifestEntry>) -> Result<(), OrganizeError> {
+    let mut tmp = manifest.as_os_str().to_owned();
+    tmp.push(".part");
+    let tmp = PathBuf::from(tmp);
+    let result = fs::File::create(&tmp).at(&tmp).and_then(|mut out| {
+        for entry in entries {
+            write_manifest(&mut out, entry).at(&tmp)?;
+        }
+        out.sync_all().at(&tmp)?;
+        fs::rename(&tmp, manifest).at(manifest)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+// How one recorded file held up under `verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyStatus {
+    // Same SHA-256 as when it was moved
+    Intact,
+    // Nothing at the recorded destination any more
+    Missing,
+    // The contents changed since the move
+    Mismatch { expected: String, actual: String },
+    // There, but couldn't be read
+    Unreadable(String),
+}
+
+impl VerifyStatus {
+    pub fn is_ok(&self) -> bool {
+        *self == VerifyStatus::Intact
+    }
+}
+
+// Re-hash every file the manifest recorded a SHA-256 for (--hash-log runs),
+// at its destination, for one run or all of them. Read-only. A destination
+// moved to more than once is checked against its latest entry.
+pub fn verify_manifest(manifest: &Path, run_id: Option<&str>) -> Result<Vec<(PathBuf, VerifyStatus)>, OrganizeError> {
+    let entries = read_manifest(manifest).at(manifest)?;
+    let mut latest: Vec<(PathBuf, String)> = Vec::new();
+    for entry in entries.into_iter().filter(|e| run_id.is_none_or(|id| e.run_id == id)) {
+        let Some(hash) = entry.sha256 else { continue };
+        latest.retain(|(dst, _)| *dst != entry.dst);
+        latest.push((entry.dst, hash));
+    }
+
+    Ok(latest.into_iter().map(|(dst, expected)| {
+        let status = match file_hash(&dst) {
+            Ok(actual) if actual == expected => VerifyStatus::Intact,
+            Ok(actual) => VerifyStatus::Mismatch { expected, actual },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => VerifyStatus::Missing,
+            Err(e) => VerifyStatus::Unreadable(e.to_string()),
+        };
+        (dst, status)
+    }).collect())
+}
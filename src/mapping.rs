@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use crate::{IoContext, OrganizeError};
+
+// ── Mapping files (--mapping) ────────────────
+// An explicit path -> category list, e.g. exported from a spreadsheet for a
+// one-off migration. Listed files go where the mapping says, whatever
+// their extension; everything else is categorized as usual.
+
+// Read a mapping: CSV rows `path,category` (an optional `path,category`
+// header; quote fields with commas, "" for a quote) or, for a .json file,
+// an object {"path": "category"}. Relative paths are taken from `base`.
+// Keys are rebuilt on top of `base` like read_file_list's, so they line up
+// with scanned paths. Missing files and files outside `base` are skipped
+// with a warning.
+pub fn read_mapping(file: &Path, base: &Path) -> Result<HashMap<PathBuf, String>, OrganizeError> {
+    let text = fs::read_to_string(file).at(file)?;
+    let invalid = |message: String| OrganizeError::Config { path: file.to_path_buf(), message };
+    let is_json = file.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let rows: Vec<(String, String)> = if is_json {
+        let map: HashMap<String, String> = serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+        map.into_iter().collect()
+    } else {
+        csv_rows(&text).map_err(invalid)?
+    };
+
+    let root = base.canonicalize().at(base)?;
+    let mut out = HashMap::new();
+    for (path, category) in rows {
+        let full = base.join(&path);
+        let rel = match full.canonicalize() {
+            Ok(p) if p.is_file() => p.strip_prefix(&root).map(Path::to_path_buf),
+            _ => {
+                eprintln!("{} {} in the mapping is not a file, ignoring", "⚠".yellow(), path);
+                continue;
+            }
+        };
+        match rel {
+            Ok(rel) => { out.insert(base.join(rel), category); }
+            Err(_) => eprintln!("{} {} is outside {}, ignoring", "⚠".yellow(), path, base.display()),
+        }
+    }
+    Ok(out)
+}
+
+// `path,category` rows of a CSV file, without the header
+fn csv_rows(text: &str) -> Result<Vec<(String, String)>, String> {
+    let mut rows = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() { continue; }
+        let fields = csv_fields(line).ok_or_else(|| format!("line {}: unclosed quote", n + 1))?;
+        let [path, category] = fields.as_slice() else {
+            return Err(format!("line {}: expected `path,category`, found {} field(s)", n + 1, fields.len()));
+        };
+        let (path, category) = (path.trim(), category.trim());
+        if n == 0 && path.eq_ignore_ascii_case("path") && category.eq_ignore_ascii_case("category") { continue; }
+        if path.is_empty() || category.is_empty() {
+            return Err(format!("line {}: empty path or category", n + 1));
+        }
+        rows.push((path.to_string(), category.to_string()));
+    }
+    Ok(rows)
+}
+
+// Split one CSV line, honouring "quoted, fields" and "" escapes. None if a
+// quote is left open.
+fn csv_fields(line: &str) -> Option<Vec<String>> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut()?;
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => { field.push('"'); chars.next(); }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    (!quoted).then_some(fields)
+}
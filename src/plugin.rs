@@ -0,0 +1,147 @@
+use std::cell::Cell;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use colored::*;
+use rhai::{Dynamic, Engine, Scope, AST};
+
+use crate::{Categorizer, IoContext, MatchReason, OrganizeError};
+
+// ── Rules scripts (--rules-script) ───────────
+// Custom categorization in a Rhai script, no recompiling needed. The
+// script runs once per file with `name`, `ext` (lowercase, "" if none),
+// `size` (bytes) and `mtime` (Unix seconds) in scope, and its last
+// expression decides:
+//
+//   if size > 100 * 1024 * 1024 { "Large" } else if ext == "log" { "" }
+//
+// A string is the category, "" leaves the file where it is, and anything
+// else (no value, an error, a timeout) hands the file to the config.
+
+// Longest a script may run on one file before it's cut off
+pub const SCRIPT_TIME_LIMIT: Duration = Duration::from_millis(100);
+
+// Rhai operations allowed per file, so a runaway loop stops even on a
+// slow clock
+const SCRIPT_MAX_OPERATIONS: u64 = 1_000_000;
+
+pub struct ScriptCategorizer<'a> {
+    engine: Engine,
+    ast: AST,
+    path: PathBuf,
+    // When the current file started, for the time limit
+    started: Rc<Cell<Instant>>,
+    // Decides whatever the script doesn't
+    fallback: &'a dyn Categorizer,
+}
+
+impl<'a> ScriptCategorizer<'a> {
+    // Compile the script at `path`; syntax errors are reported up front
+    // rather than once per file
+    pub fn load(path: &Path, fallback: &'a dyn Categorizer) -> Result<Self, OrganizeError> {
+        let text = fs::read_to_string(path).at(path)?;
+        let started = Rc::new(Cell::new(Instant::now()));
+        let engine = sandboxed_engine(started.clone());
+        let ast = engine.compile(&text)
+            .map_err(|e| OrganizeError::Config { path: path.to_path_buf(), message: e.to_string() })?;
+        Ok(ScriptCategorizer { engine, ast, path: path.to_path_buf(), started, fallback })
+    }
+
+    // What the script says about one file: Some(category), Some("") to
+    // leave it, None to ask the config
+    fn run(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<String> {
+        let mut scope = Scope::new();
+        scope.push("name", path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+        scope.push("ext", ext.to_string());
+        scope.push("size", meta.len() as i64);
+        let mtime = meta.modified().ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+        scope.push("mtime", mtime);
+
+        self.started.set(Instant::now());
+        match self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast) {
+            Ok(value) if value.is_string() => value.into_string().ok(),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!(
+                    "{} {} failed on {}: {}; using the config",
+                    "⚠".yellow(), self.path.display(), path.display(), e,
+                );
+                None
+            }
+        }
+    }
+}
+
+// No file or module access, no eval, and hard caps on time, operations
+// and sizes
+fn sandboxed_engine(started: Rc<Cell<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.set_max_modules(0);
+    engine.disable_symbol("eval");
+    engine.on_progress(move |_| {
+        (started.get().elapsed() > SCRIPT_TIME_LIMIT).then(|| Dynamic::from("time limit exceeded"))
+    });
+    engine
+}
+
+impl Categorizer for ScriptCategorizer<'_> {
+    fn category_for(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<String> {
+        self.explain(path, ext, meta).map(|(category, _)| category)
+    }
+
+    fn explain(&self, path: &Path, ext: &str, meta: &Metadata) -> Option<(String, MatchReason)> {
+        match self.run(path, ext, meta) {
+            Some(category) if category.is_empty() => None,
+            Some(category) => Some((category, MatchReason::Script)),
+            None => self.fallback.explain(path, ext, meta),
+        }
+    }
+
+    fn trace(&self, path: &Path, ext: &str, meta: &Metadata, steps: &mut Vec<String>) -> Option<(String, MatchReason)> {
+        let script = self.path.display();
+        match self.run(path, ext, meta) {
+            Some(category) if category.is_empty() => {
+                steps.push(format!("{script}: \"\", leave it where it is"));
+                None
+            }
+            Some(category) => {
+                steps.push(format!("{script}: {category:?}"));
+                Some((category, MatchReason::Script))
+            }
+            None => {
+                steps.push(format!("{script}: no answer, asking the config"));
+                self.fallback.trace(path, ext, meta, steps)
+            }
+        }
+    }
+
+    fn folder_names(&self) -> Vec<String> {
+        self.fallback.folder_names()
+    }
+
+    fn is_excluded(&self, ext: &str) -> bool {
+        self.fallback.is_excluded(ext)
+    }
+
+    fn mime_category(&self, ext: &str) -> Option<String> {
+        self.fallback.mime_category(ext)
+    }
+
+    fn archive_category(&self, path: &Path) -> Option<String> {
+        self.fallback.archive_category(path)
+    }
+
+    fn companion_extensions(&self) -> Vec<String> {
+        self.fallback.companion_extensions()
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+// ── Rename templates (--rename-template) ─────
+// The name a file gets when it moves, built from placeholders:
+//
+//   {stem}      the name without its extension
+//   {ext}       the extension, without the dot
+//   {date}      YYYY-MM-DD, from the name or else the modification time
+//   {category}  the category the file was sorted into
+//   {counter}   1, 2, 3… per destination folder, in the order files arrive
+//
+// "{date}_{stem}" turns IMG_001.jpg into 2024-03-07_IMG_001.jpg. The layout
+// decides the folders; the template only the name. The result always ends
+// in the file's extension, so a later run still knows what it is.
+
+// Placeholder names, as written between braces
+pub const RENAME_PLACEHOLDERS: [&str; 5] = ["stem", "ext", "date", "category", "counter"];
+
+// A checked template, ready to render
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenameTemplate {
+    source: String,
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Stem,
+    Ext,
+    Date,
+    Category,
+    Counter,
+}
+
+// What the placeholders draw on for one file
+pub struct RenameFile<'a> {
+    pub stem: &'a str,
+    pub ext: &'a str,
+    pub date: NaiveDate,
+    pub category: &'a str,
+    pub counter: usize,
+}
+
+impl RenameTemplate {
+    // The file name for `file`. Slashes in values (Other/psd) become
+    // underscores, dots at either end are dropped (a leading one would hide
+    // the file), the extension is appended if the template didn't end with
+    // it, and a template that renders empty keeps the old stem.
+    pub fn render(&self, file: &RenameFile) -> String {
+        let mut name = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => name.push_str(text),
+                Piece::Field(Field::Stem) => name.push_str(file.stem),
+                Piece::Field(Field::Ext) => name.push_str(file.ext),
+                Piece::Field(Field::Date) => name.push_str(&file.date.format("%Y-%m-%d").to_string()),
+                Piece::Field(Field::Category) => name.push_str(file.category),
+                Piece::Field(Field::Counter) => name.push_str(&file.counter.to_string()),
+            }
+        }
+        let name = name.replace(['/', '\\'], "_");
+        let name = match name.trim_matches('.') {
+            "" => file.stem,
+            trimmed => trimmed,
+        };
+        let suffix = format!(".{}", file.ext.to_lowercase());
+        if file.ext.is_empty() || name.to_lowercase().ends_with(&suffix) {
+            name.to_string()
+        } else {
+            format!("{name}.{}", file.ext)
+        }
+    }
+}
+
+impl FromStr for RenameTemplate {
+    type Err = String;
+
+    // Unknown or unclosed placeholders, path separators and leading dots
+    // (hidden files are never sorted again) are rejected up front
+    fn from_str(s: &str) -> Result<Self, String> {
+        if s.trim().is_empty() {
+            return Err("rename template is empty".into());
+        }
+        if s.contains(['/', '\\']) {
+            return Err(format!("rename template {s:?} contains a path separator; use --layout for folders"));
+        }
+        if s.starts_with('.') {
+            return Err(format!("rename template {s:?} starts with a dot, which would hide the files"));
+        }
+        let mut pieces = Vec::new();
+        let mut rest = s;
+        while let Some(open) = rest.find('{') {
+            let text = &rest[..open];
+            if text.contains('}') {
+                return Err(format!("unmatched }} in rename template {s:?}"));
+            }
+            if !text.is_empty() { pieces.push(Piece::Text(text.to_string())); }
+            let Some(close) = rest[open..].find('}') else {
+                return Err(format!("unclosed {{ in rename template {s:?}"));
+            };
+            let field = match &rest[open + 1..open + close] {
+                "stem" => Field::Stem,
+                "ext" => Field::Ext,
+                "date" => Field::Date,
+                "category" => Field::Category,
+                "counter" => Field::Counter,
+                other => return Err(format!(
+                    "unknown placeholder {{{other}}} in rename template; expected one of {}",
+                    RENAME_PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", "),
+                )),
+            };
+            pieces.push(Piece::Field(field));
+            rest = &rest[open + close + 1..];
+        }
+        if rest.contains('}') {
+            return Err(format!("unmatched }} in rename template {s:?}"));
+        }
+        if !rest.is_empty() { pieces.push(Piece::Text(rest.to_string())); }
+        Ok(RenameTemplate { source: s.to_string(), pieces })
+    }
+}
+
+impl fmt::Display for RenameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
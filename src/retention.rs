@@ -0,0 +1,53 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{file_extension, is_hidden_or_junk};
+
+// ── Retention (--keep-newest) ────────────────
+// Keep only the newest files of each extension (the last 5 .log files, say)
+// and send the older ones away before the rest of the run sorts what's
+// left. Which files go is decided up front, over the whole run, since "the
+// newest five" can't be known one file at a time.
+
+// What happens to the files past the count
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RetentionAction {
+    // Into the system trash, where they can still be recovered
+    #[default]
+    Trash,
+    // Deleted for good
+    Delete,
+    // Packed into Archive/<ext>_<year>.zip, like --archive-older-than
+    Archive,
+}
+
+// How many files of `ext` to keep: a per-extension count wins over the
+// overall one; None keeps them all
+pub fn keep_count(overall: Option<usize>, per_ext: &HashMap<String, usize>, ext: &str) -> Option<usize> {
+    per_ext.get(ext).copied().or(overall)
+}
+
+// The files of `files` beyond the newest `keep(ext)` of their extension,
+// sorted by path. Newest is by modification time, ties going by path.
+// Hidden and junk files, files without an extension and anything that
+// isn't a regular file are never counted.
+pub fn expired_files(files: &[PathBuf], keep: impl Fn(&str) -> Option<usize>) -> Vec<PathBuf> {
+    let mut groups: HashMap<String, Vec<(SystemTime, &Path)>> = HashMap::new();
+    for file in files.iter().filter(|f| !is_hidden_or_junk(f)) {
+        let Some(ext) = file_extension(file) else { continue };
+        let Some(meta) = fs::metadata(file).ok().filter(|m| m.is_file()) else { continue };
+        groups.entry(ext).or_default().push((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), file));
+    }
+
+    let mut expired = Vec::new();
+    for (ext, mut group) in groups {
+        let Some(keep) = keep(&ext) else { continue };
+        group.sort_by_key(|&(modified, path)| (Reverse(modified), path));
+        expired.extend(group.into_iter().skip(keep).map(|(_, path)| path.to_path_buf()));
+    }
+    expired.sort();
+    expired
+}
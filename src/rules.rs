@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// ── Rules ────────────────────────────────────
+// Ordered [[rules]] in config.toml. Every predicate that is set must hold;
+// the first matching rule decides the destination, e.g.
+//
+//   [[rules]]
+//   extensions   = ["pdf"]
+//   min_size     = 10485760   # bytes
+//   min_age_days = 365
+//   destination  = "Archive/OldDocs"
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Rule {
+    // Folder (relative to the target) that matching files go to
+    pub destination: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    // Glob against the whole file name, case-insensitive
+    pub name: Option<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub min_age_days: Option<u64>,
+    pub max_age_days: Option<u64>,
+}
+
+const DAY: u64 = 24 * 60 * 60;
+
+impl Rule {
+    // Does a file with this name, extension, size and age satisfy every predicate?
+    pub fn matches(&self, file_name: &str, ext: &str, size: u64, age: Duration) -> bool {
+        self.mismatch(file_name, ext, size, age).is_none()
+    }
+
+    // The first predicate the file fails, by its config key (`min_size`…),
+    // or None if it satisfies them all
+    pub fn mismatch(&self, file_name: &str, ext: &str, size: u64, age: Duration) -> Option<&'static str> {
+        let ext_ok = self.extensions.is_empty()
+            || self.extensions.iter().any(|e| e.eq_ignore_ascii_case(ext));
+        let name_ok = self.name.as_ref().is_none_or(|g| {
+            let opts = glob::MatchOptions { case_sensitive: false, ..Default::default() };
+            glob::Pattern::new(g).is_ok_and(|p| p.matches_with(file_name, opts))
+        });
+        let age = age.as_secs();
+
+        [
+            (ext_ok, "extensions"),
+            (name_ok, "name"),
+            (self.min_size.is_none_or(|min| size >= min), "min_size"),
+            (self.max_size.is_none_or(|max| size <= max), "max_size"),
+            (self.min_age_days.is_none_or(|d| age >= d * DAY), "min_age_days"),
+            (self.max_age_days.is_none_or(|d| age <= d * DAY), "max_age_days"),
+        ]
+        .into_iter()
+        .find(|(ok, _)| !ok)
+        .map(|(_, key)| key)
+    }
+}
+
+// Destination of the first rule that matches, if any
+pub fn first_matching_rule<'a>(
+    rules: &'a [Rule],
+    file_name: &str,
+    ext: &str,
+    size: u64,
+    age: Duration,
+) -> Option<&'a str> {
+    rules.iter()
+        .find(|r| r.matches(file_name, ext, size, age))
+        .map(|r| r.destination.as_str())
+}
@@ -0,0 +1,61 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// ── Move scripts (--emit-script) ─────────────
+// The plan as a shell script the user reads and runs themselves instead of
+// letting the organizer move anything. Moves never overwrite: `mv -n` on
+// Unix, `move /-Y` (which asks first) on Windows.
+
+// Which shell a script is written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptShell {
+    // POSIX sh: mkdir -p + mv -n
+    Sh,
+    // Windows cmd batch file: mkdir + move /-Y
+    Cmd,
+}
+
+impl ScriptShell {
+    // The shell of the platform we're running on
+    pub fn native() -> Self {
+        if cfg!(windows) { ScriptShell::Cmd } else { ScriptShell::Sh }
+    }
+
+    // Quote one path as a single argument
+    pub fn quote(self, path: &Path) -> String {
+        let text = path.to_string_lossy();
+        match self {
+            ScriptShell::Sh => format!("'{}'", text.replace('\'', r"'\''")),
+            // Windows names can't contain `"`; `%` would expand in a batch file
+            ScriptShell::Cmd => format!("\"{}\"", text.replace('%', "%%")),
+        }
+    }
+}
+
+// One command per move, each destination folder created before the first
+// move into it
+pub fn move_script(moves: &[(PathBuf, PathBuf)], shell: ScriptShell) -> String {
+    let mut out = match shell {
+        ScriptShell::Sh => String::from("#!/bin/sh\nset -e\n"),
+        ScriptShell::Cmd => String::from("@echo off\n"),
+    };
+    let comment = if shell == ScriptShell::Sh { "#" } else { "rem" };
+    out.push_str(&format!("{comment} smart-organizer plan: {} move(s). Review before running.\n", moves.len()));
+
+    let mut made: HashSet<&Path> = HashSet::new();
+    for (from, to) in moves {
+        if let Some(dir) = to.parent().filter(|d| made.insert(d)) {
+            let dir = shell.quote(dir);
+            match shell {
+                ScriptShell::Sh => out.push_str(&format!("mkdir -p {dir}\n")),
+                ScriptShell::Cmd => out.push_str(&format!("if not exist {dir} mkdir {dir}\n")),
+            }
+        }
+        let (from, to) = (shell.quote(from), shell.quote(to));
+        match shell {
+            ScriptShell::Sh => out.push_str(&format!("mv -n {from} {to}\n")),
+            ScriptShell::Cmd => out.push_str(&format!("move /-Y {from} {to}\n")),
+        }
+    }
+    out
+}
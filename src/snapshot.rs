@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use crate::error::IoContext;
+use crate::{is_link_to, move_file, read_manifest, remove_dir_if_empty, OrganizeError, Stats};
+
+// ── Snapshots (--snapshot, restore) ──────────
+// Every file in the target as it was before the first run: path, size and
+// modification time. `restore --from` follows the manifest from each
+// recorded path to wherever later runs took the file, and moves it back,
+// however many runs ago that was.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotEntry {
+    // Absolute path
+    pub path: PathBuf,
+    pub size: u64,
+    // Unix seconds
+    pub mtime: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    // When it was taken (RFC 3339)
+    pub taken: String,
+    // The target folder, absolute
+    pub root: PathBuf,
+    // Every file below it, hidden ones included, sorted by path
+    pub files: Vec<SnapshotEntry>,
+}
+
+// List every file below `base` (symlinks as themselves, never followed).
+// `except` is left out, so a snapshot kept inside the target doesn't list
+// itself.
+pub fn take_snapshot(base: &Path, except: Option<&Path>) -> Result<Snapshot, OrganizeError> {
+    let root = std::path::absolute(base).at(base)?;
+    let except = except.and_then(|p| std::path::absolute(p).ok());
+    let mut files = Vec::new();
+    let mut stack = vec![root.clone()];
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir).at(&dir)? {
+            let entry = entry.at(&dir)?;
+            let path = entry.path();
+            if entry.file_type().at(&path)?.is_dir() {
+                stack.push(path);
+                continue;
+            }
+            if except.as_ref() == Some(&path) { continue; }
+            let meta = fs::symlink_metadata(&path).at(&path)?;
+            let mtime = meta.modified().ok()
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs() as i64);
+            files.push(SnapshotEntry { path, size: meta.len(), mtime });
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Snapshot { taken: Local::now().to_rfc3339(), root, files })
+}
+
+pub fn write_snapshot(snapshot: &Snapshot, path: &Path) -> Result<(), OrganizeError> {
+    let out = BufWriter::new(fs::File::create(path).at(path)?);
+    serde_json::to_writer(out, snapshot).map_err(std::io::Error::from).at(path)
+}
+
+pub fn read_snapshot(path: &Path) -> Result<Snapshot, OrganizeError> {
+    let file = BufReader::new(fs::File::open(path).at(path)?);
+    serde_json::from_reader(file).map_err(|e| OrganizeError::Config { path: path.to_path_buf(), message: e.to_string() })
+}
+
+// Put every file the snapshot at `index` lists back at its recorded path,
+// following its moves through `manifest` across all runs. Files already
+// home are left alone, as are files added after the snapshot. A file
+// whose original path is taken, or that's gone, is reported and skipped.
+// Emptied category folders are removed. The manifest isn't changed, so a
+// restore can be run again.
+pub fn restore_snapshot(index: &Path, manifest: &Path) -> Result<Stats, OrganizeError> {
+    let snapshot = read_snapshot(index)?;
+    let entries = read_manifest(manifest).at(manifest)?;
+
+    // Where each snapshot file is now -> where it was, replaying the moves
+    // in order; a hard-linked "move" never took the original anywhere
+    let mut now: HashMap<PathBuf, PathBuf> = snapshot.files.iter().map(|f| (f.path.clone(), f.path.clone())).collect();
+    for entry in entries.iter().filter(|e| !e.linked) {
+        if let Some(original) = now.remove(&entry.src) {
+            now.insert(entry.dst.clone(), original);
+        }
+    }
+
+    let mut stats = Stats::default();
+    let mut moves: Vec<(PathBuf, PathBuf)> = now.into_iter().filter(|(at, original)| at != original).collect();
+    moves.sort();
+    for (at, original) in moves {
+        // Put back by an earlier restore
+        if !at.exists() && original.exists() { continue; }
+        // A link --leave-symlink left at the original path makes way
+        let left_link = is_link_to(&original, &at);
+        let restored = if original.exists() && !left_link {
+            Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "original path is taken")).at(&original)
+        } else {
+            let unlinked = if left_link { fs::remove_file(&original).at(&original) } else { Ok(()) };
+            unlinked
+                .and_then(|_| original.parent().map_or(Ok(()), |dir| fs::create_dir_all(dir).at(dir)))
+                .and_then(|_| move_file(&at, &original))
+        };
+        match restored {
+            Ok(()) => {
+                // Folders the runs created, from the innermost, while empty
+                for dir in at.ancestors().skip(1).take_while(|d| d.starts_with(&snapshot.root) && *d != snapshot.root) {
+                    remove_dir_if_empty(dir);
+                }
+                stats.moved += 1;
+            }
+            Err(e) => {
+                eprintln!("  ✗ {} — {}", at.display(), e);
+                stats.errors += 1;
+                stats.failures.push((at, e.to_string()));
+            }
+        }
+    }
+    Ok(stats)
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+// ── Plan review (--tui) ──────────────────────
+// The planned moves in a scrollable list, every one checked to start with.
+// Space toggles a move, `a` toggles them all, Enter applies the checked
+// ones and q/Esc cancels without moving anything.
+
+type Move = (PathBuf, PathBuf);
+
+struct Review<'a> {
+    base: &'a Path,
+    moves: &'a [Move],
+    approved: Vec<bool>,
+    list: ListState,
+}
+
+// Show the plan and return the moves that were kept, or None if cancelled
+pub fn review(base: &Path, moves: &[Move]) -> io::Result<Option<Vec<Move>>> {
+    let mut terminal = ratatui::init();
+    let picked = Review::new(base, moves).run(&mut terminal);
+    ratatui::restore();
+    picked
+}
+
+impl<'a> Review<'a> {
+    fn new(base: &'a Path, moves: &'a [Move]) -> Self {
+        Review { base, moves, approved: vec![true; moves.len()], list: ListState::default().with_selected(Some(0)) }
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<Option<Vec<Move>>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press { continue; }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                KeyCode::Enter => return Ok(Some(self.kept())),
+                KeyCode::Char(' ') => self.toggle(),
+                KeyCode::Char('a') => self.toggle_all(),
+                KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                KeyCode::PageDown => self.list.scroll_down_by(10),
+                KeyCode::PageUp => self.list.scroll_up_by(10),
+                KeyCode::Home => self.list.select_first(),
+                KeyCode::End => self.list.select_last(),
+                _ => {}
+            }
+        }
+    }
+
+    fn toggle(&mut self) {
+        if let Some(on) = self.list.selected().and_then(|i| self.approved.get_mut(i)) {
+            *on = !*on;
+        }
+    }
+
+    // Check everything, or uncheck everything if it's all checked already
+    fn toggle_all(&mut self) {
+        let all = self.approved.iter().all(|on| *on);
+        self.approved.fill(!all);
+    }
+
+    fn kept(&self) -> Vec<Move> {
+        self.moves.iter().zip(&self.approved).filter(|(_, on)| **on).map(|(m, _)| m.clone()).collect()
+    }
+
+    fn rel<'p>(&self, path: &'p Path) -> std::path::Display<'p> {
+        path.strip_prefix(self.base).unwrap_or(path).display()
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [plan_area, side] = Layout::horizontal([Constraint::Fill(3), Constraint::Fill(1)]).areas(frame.area());
+        let [summary_area, help_area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(6)]).areas(side);
+
+        let items: Vec<ListItem> = self.moves.iter().zip(&self.approved).map(|((from, to), on)| {
+            let line = format!("[{}] {} → {}", if *on { "x" } else { " " }, self.rel(from), self.rel(to));
+            ListItem::new(if *on { Line::from(line) } else { Line::from(line).dim() })
+        }).collect();
+        let kept = self.approved.iter().filter(|on| **on).count();
+        let plan = List::new(items)
+            .block(Block::bordered().title(format!(" Plan: {kept} of {} moves ", self.moves.len())))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(plan, plan_area, &mut self.list);
+
+        // Checked / planned moves per destination folder
+        let mut folders: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for ((_, to), on) in self.moves.iter().zip(&self.approved) {
+            let dir = to.parent().unwrap_or(to);
+            let count = folders.entry(self.rel(dir).to_string()).or_default();
+            count.0 += usize::from(*on);
+            count.1 += 1;
+        }
+        let summary: Vec<Line> = folders.iter()
+            .map(|(dir, (on, all))| Line::from(format!("{dir}  {on}/{all}")))
+            .collect();
+        frame.render_widget(Paragraph::new(summary).block(Block::bordered().title(" Folders ")), summary_area);
+
+        let help = Paragraph::new(vec![
+            Line::from("Space  toggle   a  all"),
+            Line::from("↑ ↓ PgUp PgDn  move"),
+            Line::from("Enter  apply checked"),
+            Line::from("q Esc  cancel"),
+        ]);
+        frame.render_widget(help.block(Block::bordered().title(" Keys ")), help_area);
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::CASE_INSENSITIVE_FS;
+
+// ── Destination volumes ──────────────────────
+// A USB stick or network share may store fewer names than the disk the
+// files come from: FAT, exFAT and NTFS refuse `:` and friends, and FAT and
+// exFAT can't tell Photo.jpg from photo.jpg. Before a run the destination
+// (and --backup-dir) is looked up, read-only, so names can be made to fit
+// instead of failing file by file.
+
+// Longest name, in bytes, that every common filesystem stores (ext4 and
+// APFS count bytes, NTFS and exFAT count 255 UTF-16 units)
+pub const MAX_NAME_BYTES: usize = 255;
+
+// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// What a volume can store, as far as the organizer cares
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VolumeInfo {
+    // Filesystem type as the OS names it (vfat, exfat, ext4…), when known
+    pub fs_type: Option<String>,
+    // Only names Windows accepts: none of < > : " | ? * or control
+    // characters, and no trailing dots or spaces
+    pub windows_names: bool,
+    // Photo.jpg and photo.jpg are the same file
+    pub case_insensitive: bool,
+}
+
+impl VolumeInfo {
+    // The limits of a filesystem type from /proc/self/mountinfo
+    pub fn for_fs_type(fs_type: &str) -> VolumeInfo {
+        let fs = fs_type.to_ascii_lowercase();
+        let fat = matches!(fs.as_str(), "vfat" | "msdos" | "fat" | "exfat");
+        // fuseblk is how ntfs-3g and exfat-fuse mounts show up; cifs and
+        // smb3 are Windows shares
+        let windows = fat || matches!(fs.as_str(), "ntfs" | "ntfs3" | "fuseblk" | "cifs" | "smb3");
+        VolumeInfo { fs_type: Some(fs_type.to_string()), windows_names: windows, case_insensitive: fat }
+    }
+}
+
+// Look up the volume `dir` is on (or would be, for a folder not made yet).
+// On Windows every name follows Windows rules; on macOS names are assumed
+// case-insensitive; on Linux the mount table says. Nothing is written.
+pub fn probe_volume(dir: &Path) -> VolumeInfo {
+    let platform = VolumeInfo { fs_type: None, windows_names: cfg!(windows), case_insensitive: CASE_INSENSITIVE_FS };
+    if !cfg!(target_os = "linux") {
+        return platform;
+    }
+    let Ok(abs) = std::path::absolute(dir) else { return platform };
+    let Some(existing) = abs.ancestors().find(|p| p.exists()).and_then(|p| p.canonicalize().ok()) else {
+        return platform;
+    };
+    std::fs::read_to_string("/proc/self/mountinfo").ok()
+        .and_then(|table| filesystem_from_mountinfo(&table, &existing))
+        .map_or(platform, |fs| VolumeInfo::for_fs_type(&fs))
+}
+
+// The filesystem type of the mount holding `path` (absolute, symlinks
+// resolved): the entry with the longest mount point above it
+pub fn filesystem_from_mountinfo(mountinfo: &str, path: &Path) -> Option<String> {
+    mountinfo.lines()
+        .filter_map(|line| {
+            // <id> <parent> <dev> <root> <mount point> <options…> - <fs type> <source> <options>
+            let (mount, rest) = line.split_once(" - ")?;
+            let mount_point = unescape_mount(mount.split(' ').nth(4)?);
+            let fs_type = rest.split(' ').next()?;
+            path.starts_with(&mount_point).then(|| (mount_point, fs_type.to_string()))
+        })
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+}
+
+// Mount points escape spaces, tabs, newlines and backslashes as \ooo
+fn unescape_mount(field: &str) -> PathBuf {
+    let mut out = String::new();
+    let mut rest = field;
+    while let Some(at) = rest.find('\\') {
+        out.push_str(&rest[..at]);
+        let code = rest.get(at + 1..at + 4).and_then(|o| u8::from_str_radix(o, 8).ok());
+        match code {
+            Some(byte) => { out.push(byte as char); rest = &rest[at + 4..]; }
+            None => { out.push('\\'); rest = &rest[at + 1..]; }
+        }
+    }
+    out.push_str(rest);
+    PathBuf::from(out)
+}
+
+// A file name every common filesystem can store, looking as much like the
+// original as it can: `:` and `|` become `-`, `"` becomes `'`, `<` and `>`
+// become parentheses, and `?`, `*`, slashes and control characters become
+// `_`. Trailing dots and spaces are dropped, device names like CON get a `_`,
+// and names over MAX_NAME_BYTES are cut short before their extension.
+pub fn sanitize_filename(name: &str) -> String {
+    let mapped: String = name.chars()
+        .map(|c| match c {
+            ':' | '|' => '-',
+            '"' => '\'',
+            '<' => '(',
+            '>' => ')',
+            '?' | '*' | '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let mut name = match mapped.trim_end_matches(['.', ' ']) {
+        "" => "_".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    let device = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(device)) {
+        name.insert(device.len(), '_');
+    }
+    if name.len() > MAX_NAME_BYTES {
+        let ext = name.rfind('.').filter(|&dot| dot > 0 && name.len() - dot <= 32).map_or("", |dot| &name[dot..]);
+        let mut cut = MAX_NAME_BYTES - ext.len();
+        while !name.is_char_boundary(cut) { cut -= 1; }
+        name = format!("{}{ext}", &name[..cut]);
+    }
+    name
+}
+
+// `path` with every folder and file name in it passed through
+// sanitize_filename
+pub fn sanitize_path(path: &Path) -> PathBuf {
+    path.components()
+        .map(|c| match c {
+            Component::Normal(name) => PathBuf::from(sanitize_filename(&name.to_string_lossy())),
+            other => PathBuf::from(other.as_os_str()),
+        })
+        .collect()
+}
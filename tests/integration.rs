@@ -37,12 +37,7 @@ fn write_file(path: &Path, content: &[u8]) {
 
 /// Build OrganizeOpts for testing (no clap involved).
 fn opts(path: &Path) -> OrganizeOpts {
-    OrganizeOpts {
-        path: path.to_path_buf(),
-        dry_run: false,
-        find_duplicates: false,
-        keep_structure: false,
-    }
+    OrganizeOpts::builder().path(path).build()
 }
 
 // ══════════════════════════════════════════════
@@ -75,6 +70,40 @@ fn categorize_unknown_returns_none() {
     assert_eq!(cfg.categorize(""), None);
 }
 
+#[test]
+fn indexed_categorize_matches_a_linear_scan() {
+    let cfg = Config::default();
+    let linear = |ext: &str| {
+        cfg.categories.iter()
+            .find(|(_, exts)| exts.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+            .map(|(cat, _)| cat.as_str())
+    };
+    let mut probes: Vec<String> = cfg.categories.values().flatten().cloned().collect();
+    probes.extend(probes.clone().iter().map(|e| e.to_uppercase()));
+    probes.extend(["xyz", "", "Jpeg", "tar.gz"].map(String::from));
+    for ext in &probes {
+        assert_eq!(cfg.categorize(ext), linear(ext), "extension {ext:?}");
+    }
+}
+
+#[test]
+fn extension_in_two_categories_goes_to_the_first_by_name() {
+    let categories = std::collections::HashMap::from([
+        ("Zips".to_string(), vec!["zip".to_string()]),
+        ("Archives".to_string(), vec!["ZIP".to_string()]),
+    ]);
+    let cfg = Config { categories, ..Default::default() };
+    assert_eq!(cfg.categorize("zip"), Some("Archives"));
+}
+
+#[test]
+fn excluding_a_category_refreshes_the_index() {
+    let mut cfg = Config::default();
+    assert_eq!(cfg.categorize("jpg"), Some("Images"));
+    cfg.exclude_categories(&["Images".to_string()]);
+    assert_eq!(cfg.categorize("jpg"), None);
+}
+
 // ══════════════════════════════════════════════
 //  Config defaults & TOML parsing
 // ══════════════════════════════════════════════
@@ -108,6 +137,297 @@ fn empty_toml_falls_back_to_defaults() {
     assert!(cfg.categorize("jpg").is_some());
 }
 
+#[test]
+fn dotted_extensions_in_config_still_match() {
+    let dir = tmp_dir("cfg_dotted");
+    let path = dir.join("config.toml");
+    fs::write(&path, r#"
+        [categories]
+        Photos = [".jpg", ".PNG", " webp "]
+
+        [[rules]]
+        extensions  = [".pdf"]
+        destination = "Papers"
+    "#).unwrap();
+
+    let cfg = Config::load_from(&path).unwrap();
+    assert_eq!(cfg.categorize("jpg"), Some("Photos"));
+    assert_eq!(cfg.categorize("png"), Some("Photos"));
+    assert_eq!(cfg.categorize("webp"), Some("Photos"));
+    assert_eq!(cfg.categorize_with_rules("a.pdf", "pdf", 1, std::time::Duration::ZERO), Some("Papers"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn wildcard_extensions_are_reported() {
+    let mut cfg: Config = toml::from_str(r#"
+        [categories]
+        Photos = ["*.gif", "jpg"]
+
+        [[rules]]
+        extensions  = ["*.pdf"]
+        destination = "Papers"
+    "#).unwrap();
+
+    // Globs are fine in categories, but rule extensions can't be globs
+    let warnings = cfg.normalize_extensions();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("*.pdf") && warnings[0].contains("\"pdf\""), "{}", warnings[0]);
+    assert_eq!(cfg.categorize("jpg"), Some("Photos"));
+    assert_eq!(cfg.categorize_file("anim.GIF", "gif"), Some("Photos"));
+}
+
+// ══════════════════════════════════════════════
+//  Config::load_from (TOML / JSON / YAML)
+// ══════════════════════════════════════════════
+
+fn load_config_text(label: &str, file: &str, text: &str) -> Result<Config, OrganizeError> {
+    let dir = tmp_dir(label);
+    write_file(&dir.join(file), text.as_bytes());
+    let cfg = Config::load_from(&dir.join(file));
+    let _ = fs::remove_dir_all(&dir);
+    cfg
+}
+
+#[test]
+fn toml_json_and_yaml_give_the_same_config() {
+    let toml = load_config_text("cfg_toml", "c.toml", r#"
+        [categories]
+        Photos = ["jpg", "png"]
+        [name_patterns]
+        Finance = ["invoice_*"]
+    "#).unwrap();
+    let json = load_config_text("cfg_json", "c.json", r#"{
+        "categories": { "Photos": ["jpg", "png"] },
+        "name_patterns": { "Finance": ["invoice_*"] }
+    }"#).unwrap();
+    let yaml = load_config_text("cfg_yaml", "c.yaml", "
+categories:
+  Photos: [jpg, png]
+name_patterns:
+  Finance: ['invoice_*']
+").unwrap();
+    let yml = load_config_text("cfg_yml", "c.YML", "categories:\n  Photos: [jpg, png]\n").unwrap();
+
+    for cfg in [&json, &yaml] {
+        assert_eq!(cfg.categories, toml.categories);
+        assert_eq!(cfg.name_patterns, toml.name_patterns);
+    }
+    assert_eq!(yml.categories, toml.categories);
+    assert_eq!(json.categorize("png"), Some("Photos"));
+}
+
+#[test]
+fn json_without_categories_uses_defaults() {
+    let cfg = load_config_text("cfg_json_empty", "c.json", "{}").unwrap();
+    assert_eq!(cfg.categorize("jpg"), Some("Images"));
+}
+
+#[test]
+fn unknown_config_extension_is_a_clear_error() {
+    let err = load_config_text("cfg_ini", "c.ini", "[categories]").unwrap_err();
+    assert!(matches!(err, OrganizeError::Config { .. }));
+    assert!(err.to_string().contains("unsupported config format"));
+}
+
+#[test]
+fn malformed_config_is_a_config_error() {
+    let err = load_config_text("cfg_bad_json", "c.json", "{ not json").unwrap_err();
+    let OrganizeError::Config { path, .. } = &err else { panic!("expected a config error, got {err:?}") };
+    assert!(path.ends_with("c.json"));
+}
+
+#[test]
+fn missing_config_is_an_io_error_with_its_path() {
+    let err = Config::load_from(Path::new("/no/such/dir/config.toml")).unwrap_err();
+    assert_eq!(err.io_kind(), Some(std::io::ErrorKind::NotFound));
+    assert!(err.to_string().contains("/no/such/dir/config.toml"));
+}
+
+// ══════════════════════════════════════════════
+//  Excluding categories
+// ══════════════════════════════════════════════
+
+#[test]
+fn excluded_category_files_stay_put() {
+    let dir = tmp_dir("org_exclude");
+    write_file(&dir.join("movie.mp4"), b"vid");
+    write_file(&dir.join("Videos/old.mp4"), b"vid");
+    write_file(&dir.join("photo.jpg"), b"img");
+
+    let mut cfg = Config::default();
+    cfg.exclude_categories(&["Videos".to_string()]);
+    let stats = organize(&OrganizeOpts::builder().path(&dir).quiet(true).build(), &cfg).unwrap();
+
+    assert_eq!(cfg.categorize("mp4"), None);
+    assert_eq!(stats.moved, 1);
+    assert_eq!(stats.skipped, 1); // movie.mp4; Videos/old.mp4 isn't even collected
+    assert!(dir.join("movie.mp4").exists());
+    assert!(dir.join("Images/photo.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn only_category_moves_just_the_allowed_ones() {
+    let dir = tmp_dir("org_only");
+    write_file(&dir.join("photo.jpg"), b"img");
+    write_file(&dir.join("song.mp3"), b"snd");
+    write_file(&dir.join("report.pdf"), b"doc");
+    write_file(&dir.join("Screenshot 1.png"), b"img");
+
+    let mut cfg = screenshot_config();
+    cfg.only_categories(&["Images".to_string(), "Music".to_string()]);
+    let stats = organize(&OrganizeOpts::builder().path(&dir).quiet(true).build(), &cfg).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert!(dir.join("Images/photo.jpg").exists());
+    assert!(dir.join("Music/song.mp3").exists());
+    // Screenshots is switched off, so the png falls back to its extension
+    assert!(dir.join("Images/Screenshot 1.png").exists());
+    assert!(dir.join("report.pdf").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Name patterns
+// ══════════════════════════════════════════════
+
+fn screenshot_config() -> Config {
+    let mut cfg = Config::default();
+    cfg.name_patterns.insert("Screenshots".into(), vec!["Screenshot *".into()]);
+    cfg.name_patterns.insert("Finance".into(), vec!["invoice_*".into()]);
+    cfg
+}
+
+#[test]
+fn name_pattern_beats_extension() {
+    let cfg = screenshot_config();
+    assert_eq!(cfg.categorize_file("Screenshot 2026-02-12 at 10.00.png", "png"), Some("Screenshots"));
+    assert_eq!(cfg.categorize_file("invoice_0042.pdf", "pdf"), Some("Finance"));
+    assert_eq!(cfg.categorize_file("holiday.png", "png"), Some("Images"));
+}
+
+#[test]
+fn name_patterns_are_case_insensitive() {
+    let cfg = screenshot_config();
+    assert_eq!(cfg.match_name("SCREENSHOT 1.png"), Some("Screenshots"));
+    assert_eq!(cfg.match_name("report.pdf"), None);
+}
+
+#[test]
+fn parse_name_patterns_from_toml() {
+    let cfg: Config = toml::from_str(r#"
+        [name_patterns]
+        Screenshots = ["Screenshot *"]
+    "#).unwrap();
+    assert_eq!(cfg.categorize_file("Screenshot 1.png", "png"), Some("Screenshots"));
+    assert_eq!(cfg.categorize_file("cat.png", "png"), Some("Images"));
+}
+
+#[test]
+fn organize_routes_screenshots_by_name() {
+    let dir = tmp_dir("org_name_pattern");
+    write_file(&dir.join("Screenshot 2026-01-01.png"), b"img");
+    write_file(&dir.join("cat.png"), b"img");
+
+    let stats = organize(&opts(&dir), &screenshot_config()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert!(dir.join("Screenshots/Screenshot 2026-01-01.png").exists());
+    assert!(dir.join("Images/cat.png").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Rules
+// ══════════════════════════════════════════════
+
+const MB: u64 = 1024 * 1024;
+const DAY: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+
+fn old_big_pdfs() -> Rule {
+    Rule {
+        destination: "Archive/OldDocs".into(),
+        extensions: vec!["pdf".into()],
+        min_size: Some(10 * MB),
+        min_age_days: Some(365),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn rule_needs_every_predicate() {
+    let rule = old_big_pdfs();
+    assert!(rule.matches("a.pdf", "pdf", 20 * MB, DAY * 400));
+    assert!(!rule.matches("a.pdf", "pdf", MB, DAY * 400)); // too small
+    assert!(!rule.matches("a.pdf", "pdf", 20 * MB, DAY * 30)); // too new
+    assert!(!rule.matches("a.doc", "doc", 20 * MB, DAY * 400)); // wrong type
+}
+
+#[test]
+fn rule_with_name_and_max_bounds() {
+    let rule = Rule {
+        destination: "Inbox".into(),
+        name: Some("scan_*".into()),
+        max_size: Some(MB),
+        max_age_days: Some(7),
+        ..Default::default()
+    };
+    assert!(rule.matches("SCAN_001.pdf", "pdf", 1000, DAY * 2));
+    assert!(!rule.matches("scan_001.pdf", "pdf", 2 * MB, DAY * 2));
+    assert!(!rule.matches("scan_001.pdf", "pdf", 1000, DAY * 8));
+    assert!(!rule.matches("photo.pdf", "pdf", 1000, DAY * 2));
+}
+
+#[test]
+fn first_matching_rule_wins_then_falls_back() {
+    let cfg = Config {
+        rules: vec![
+            old_big_pdfs(),
+            Rule { destination: "AllPdfs".into(), extensions: vec!["pdf".into()], ..Default::default() },
+        ],
+        ..Default::default()
+    };
+    assert_eq!(cfg.categorize_with_rules("a.pdf", "pdf", 20 * MB, DAY * 400), Some("Archive/OldDocs"));
+    assert_eq!(cfg.categorize_with_rules("a.pdf", "pdf", MB, DAY), Some("AllPdfs"));
+    assert_eq!(cfg.categorize_with_rules("a.jpg", "jpg", MB, DAY), Some("Images"));
+}
+
+#[test]
+fn parse_rules_from_toml() {
+    let cfg: Config = toml::from_str(r#"
+        [[rules]]
+        extensions  = ["log"]
+        max_size    = 100
+        destination = "Logs/Small"
+    "#).unwrap();
+    assert_eq!(cfg.rules.len(), 1);
+    assert_eq!(cfg.categorize_with_rules("x.log", "log", 50, DAY), Some("Logs/Small"));
+    assert_eq!(cfg.categorize_with_rules("x.log", "log", 500, DAY), None);
+}
+
+#[test]
+fn organize_moves_into_nested_rule_destination() {
+    let dir = tmp_dir("org_rules");
+    write_file(&dir.join("small.log"), b"tiny");
+    let mut cfg = Config::default();
+    cfg.rules.push(Rule {
+        destination: "Logs/Small".into(),
+        extensions: vec!["log".into()],
+        max_size: Some(100),
+        ..Default::default()
+    });
+
+    let stats = organize(&opts(&dir), &cfg).unwrap();
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("Logs/Small/small.log").exists());
+
+    // Re-running doesn't re-collect the rule's destination
+    let again = organize(&opts(&dir), &cfg).unwrap();
+    assert_eq!(again.moved, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 // ══════════════════════════════════════════════
 //  is_hidden_or_junk
 // ══════════════════════════════════════════════
@@ -124,6 +444,48 @@ fn detects_os_junk() {
     assert!(is_hidden_or_junk(Path::new("/tmp/Thumbs.db")));
     assert!(is_hidden_or_junk(Path::new("/tmp/desktop.ini")));
     assert!(is_hidden_or_junk(Path::new("/tmp/organizer_log.txt")));
+    assert!(is_hidden_or_junk(Path::new("/tmp/organizer_manifest.jsonl")));
+    assert!(is_hidden_or_junk(Path::new("/tmp/organizer_2026-02-12_143000.log")));
+}
+
+#[test]
+fn detects_junk_from_other_systems() {
+    for name in [".AppleDouble", ".localized", "$RECYCLE.BIN", "System Volume Information", ".directory", "ehthumbs.db"] {
+        assert!(is_junk(&Path::new("/tmp").join(name)), "{name}");
+    }
+}
+
+#[test]
+fn appledouble_forks_are_junk_by_prefix() {
+    assert!(is_junk(Path::new("/tmp/._photo.jpg")));
+    assert!(is_junk(Path::new("/tmp/._")));
+    // Plain dotfiles are hidden, not junk
+    assert!(!is_junk(Path::new("/tmp/.gitignore")));
+    assert!(!is_junk(Path::new("/tmp/_photo.jpg")));
+}
+
+#[test]
+fn junk_folders_are_not_scanned() {
+    let dir = tmp_dir("junk_dirs");
+    touch(&dir.join("$RECYCLE.BIN/S-1-5-21/$R1.jpg"));
+    touch(&dir.join("System Volume Information/tracking.log"));
+    touch(&dir.join("photo.jpg"));
+
+    assert_eq!(collect_files(&dir, &[]).unwrap(), [dir.join("photo.jpg")]);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn resource_forks_in_a_file_list_are_explained_as_junk() {
+    let dir = tmp_dir("junk_forks");
+    touch(&dir.join("._photo.jpg"));
+    touch(&dir.join(".env"));
+
+    let o = OrganizeOpts::builder().path(&dir).file_list(vec![dir.join("._photo.jpg"), dir.join(".env")]).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.untouched, [(dir.join("._photo.jpg"), SkipReason::Junk), (dir.join(".env"), SkipReason::Hidden)]);
+    fs::remove_dir_all(&dir).ok();
 }
 
 #[test]
@@ -133,6 +495,91 @@ fn normal_files_are_not_junk() {
     assert!(!is_hidden_or_junk(Path::new("/tmp/song.mp3")));
 }
 
+#[test]
+fn dot_leading_names_are_always_hidden() {
+    assert!(is_hidden_or_junk(Path::new("/tmp/.gitignore")));
+    assert!(is_hidden_or_junk(Path::new("/tmp/.env.local")));
+    assert!(is_hidden_or_junk(Path::new("/tmp/.backup.tar")));
+}
+
+// ══════════════════════════════════════════════
+//  file_extension
+// ══════════════════════════════════════════════
+
+#[test]
+fn extension_ignores_leading_dot() {
+    assert_eq!(file_extension(Path::new("/tmp/.gitignore")), None);
+    assert_eq!(file_extension(Path::new("/tmp/.env.local")).as_deref(), Some("local"));
+    assert_eq!(file_extension(Path::new("/tmp/.backup.tar")).as_deref(), Some("tar"));
+}
+
+#[test]
+fn extension_is_lowercased_and_never_empty() {
+    assert_eq!(file_extension(Path::new("/tmp/PHOTO.JPG")).as_deref(), Some("jpg"));
+    assert_eq!(file_extension(Path::new("/tmp/notes.")), None);
+    assert_eq!(file_extension(Path::new("/tmp/Makefile")), None);
+}
+
+#[test]
+fn dotfiles_are_left_in_place_and_not_counted() {
+    let dir = tmp_dir("org_dotfiles");
+    write_file(&dir.join(".gitignore"), b"target/");
+    write_file(&dir.join(".env.local"), b"KEY=1");
+    write_file(&dir.join(".backup.tar"), b"tar");
+
+    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 0);
+    assert_eq!(stats.skipped, 0);
+    assert!(dir.join(".backup.tar").exists());
+    assert!(!dir.join("Archives").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  apply_extension_case
+// ══════════════════════════════════════════════
+
+#[test]
+fn extension_case_modes() {
+    assert_eq!(apply_extension_case("Photo.JpG", ExtensionCase::Preserve), "Photo.JpG");
+    assert_eq!(apply_extension_case("Photo.JpG", ExtensionCase::Lower), "Photo.jpg");
+    assert_eq!(apply_extension_case("Photo.JpG", ExtensionCase::Upper), "Photo.JPG");
+    assert_eq!(apply_extension_case("archive.tar.GZ", ExtensionCase::Lower), "archive.tar.gz");
+    assert_eq!(apply_extension_case("Makefile", ExtensionCase::Upper), "Makefile");
+}
+
+#[test]
+fn organize_applies_extension_case() {
+    for (case, expected) in [
+        (ExtensionCase::Preserve, "Photo.JpG"),
+        (ExtensionCase::Lower, "Photo.jpg"),
+        (ExtensionCase::Upper, "Photo.JPG"),
+    ] {
+        let dir = tmp_dir(&format!("org_ext_case_{case:?}"));
+        write_file(&dir.join("Photo.JpG"), b"img");
+
+        let o = OrganizeOpts::builder().path(&dir).extension_case(case).quiet(true).build();
+        organize(&o, &Config::default()).unwrap();
+
+        assert_eq!(sorted_files(&dir.join("Images")), vec![expected.to_string()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[test]
+fn collision_rename_keeps_chosen_extension_case() {
+    let dir = tmp_dir("org_ext_case_collision");
+    write_file(&dir.join("PHOTO.JPG"), b"new");
+    write_file(&dir.join("Images/PHOTO.JPG"), b"old");
+
+    organize(&OrganizeOpts::builder().path(&dir).quiet(true).build(), &Config::default()).unwrap();
+
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    assert!(dir.join(format!("Images/PHOTO_{today}.JPG")).exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
 // ══════════════════════════════════════════════
 //  resolve_collision
 // ══════════════════════════════════════════════
@@ -181,6 +628,69 @@ fn third_collision_appends_v3() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn reserved_names_count_as_taken() {
+    let dir = tmp_dir("col_reserved");
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut reserved = std::collections::HashSet::new();
+
+    // Nothing is on disk yet, but the first pick is held for this run
+    let first = resolve_collision_reserved(&dir, "photo.jpg", "jpg", &mut reserved);
+    let second = resolve_collision_reserved(&dir, "photo.jpg", "jpg", &mut reserved);
+    let third = resolve_collision_reserved(&dir, "photo.jpg", "jpg", &mut reserved);
+
+    assert_eq!(first, dir.join("photo.jpg"));
+    assert_eq!(second, dir.join(format!("photo_{today}.jpg")));
+    assert_eq!(third, dir.join(format!("photo_{today}_v2.jpg")));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn numeric_scheme_counts_up_in_parentheses() {
+    let dir = tmp_dir("col_numeric");
+    let mut reserved = std::collections::HashSet::new();
+    let names: Vec<PathBuf> = (0..3)
+        .map(|_| resolve_collision_using(&dir, "photo.jpg", "jpg", &mut reserved, false, CollisionScheme::Numeric))
+        .collect();
+
+    assert_eq!(names, vec![dir.join("photo.jpg"), dir.join("photo (1).jpg"), dir.join("photo (2).jpg")]);
+    touch(&dir.join("Makefile"));
+    let bare = resolve_collision_using(&dir, "Makefile", "", &mut reserved, false, CollisionScheme::Numeric);
+    assert_eq!(bare, dir.join("Makefile (1)"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn timestamp_scheme_appends_date_and_time() {
+    let dir = tmp_dir("col_timestamp");
+    let mut reserved = std::collections::HashSet::new();
+    let mut pick = || resolve_collision_using(&dir, "photo.jpg", "jpg", &mut reserved, false, CollisionScheme::Timestamp);
+    let (first, second, third) = (pick(), pick(), pick());
+
+    assert_eq!(first, dir.join("photo.jpg"));
+    let stem = second.file_stem().unwrap().to_string_lossy().into_owned();
+    let stamp = stem.strip_prefix("photo_").unwrap();
+    assert!(chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").is_ok(), "{stamp}");
+    // Same second: counted; the clock ticked over: a fresh stamp
+    let third = third.file_name().unwrap().to_string_lossy().into_owned();
+    assert!(third == format!("{stem}_2.jpg") || (third.starts_with("photo_") && third != format!("{stem}.jpg")), "{third}");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn organize_uses_the_chosen_collision_scheme() {
+    let dir = tmp_dir("col_scheme_organize");
+    write_file(&dir.join("a/photo.jpg"), b"1");
+    write_file(&dir.join("b/photo.jpg"), b"2");
+    write_file(&dir.join("c/photo.jpg"), b"3");
+
+    let o = OrganizeOpts::builder().path(&dir).collision_scheme(CollisionScheme::Numeric).build();
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 3);
+
+    assert_eq!(sorted_files(&dir.join("Images")), vec!["photo (1).jpg", "photo (2).jpg", "photo.jpg"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 // ══════════════════════════════════════════════
 //  collect_files
 // ══════════════════════════════════════════════
@@ -209,6 +719,33 @@ fn skips_category_folders() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn skips_whole_category_tree() {
+    let dir = tmp_dir("cf_skip_tree");
+    touch(&dir.join("new.jpg"));
+    touch(&dir.join("Images/2025/trip/a.jpg"));
+    touch(&dir.join("Images/2025/trip/raw/b.jpg"));
+    touch(&dir.join("Images/c.jpg"));
+
+    let files = collect_files(&dir, &["Images"]).unwrap();
+    assert_eq!(files, vec![dir.join("new.jpg")]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn skip_roots_are_relative_to_base() {
+    let dir = tmp_dir("cf_skip_rel");
+    touch(&dir.join("Archive/OldDocs/x.pdf"));
+    touch(&dir.join("Archive/keep.pdf"));
+    touch(&dir.join("projects/Images/mine.jpg"));
+
+    let mut files = collect_files(&dir, &["Archive/OldDocs", "Images"]).unwrap();
+    files.sort();
+    // A user folder that merely shares a category's name deeper down is not sorted output
+    assert_eq!(files, vec![dir.join("Archive/keep.pdf"), dir.join("projects/Images/mine.jpg")]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn skips_hidden_directories() {
     let dir = tmp_dir("cf_skip_hidden");
@@ -260,6 +797,35 @@ fn move_preserves_binary_content() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn verified_copy_removes_source_when_hashes_match() {
+    let dir = tmp_dir("mv_verify");
+    let src = dir.join("data.bin");
+    let dst = dir.join("sub/data.bin");
+    write_file(&src, b"important \x00 bytes");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+
+    // Force the copy+delete path a cross-device move would take
+    copy_then_remove(&src, &dst, true).unwrap();
+    assert!(!src.exists());
+    assert_eq!(fs::read(&dst).unwrap(), b"important \x00 bytes");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn file_hash_tracks_content() {
+    let dir = tmp_dir("hash");
+    write_file(&dir.join("a.txt"), b"same");
+    write_file(&dir.join("b.txt"), b"same");
+    write_file(&dir.join("c.txt"), b"different");
+
+    let a = file_hash(&dir.join("a.txt")).unwrap();
+    assert_eq!(a, file_hash(&dir.join("b.txt")).unwrap());
+    assert_ne!(a, file_hash(&dir.join("c.txt")).unwrap());
+    assert_eq!(a.len(), 64);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 // ══════════════════════════════════════════════
 //  organize — integration tests
 // ══════════════════════════════════════════════
@@ -295,6 +861,29 @@ fn skips_unknown_extensions() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn group_unknown_sorts_leftovers_by_extension() {
+    let dir = tmp_dir("org_group_unknown");
+    write_file(&dir.join("design.psd"), b"psd");
+    write_file(&dir.join("IMG_1.HEIC"), b"heic");
+    write_file(&dir.join("photo.jpg"), b"img");
+    write_file(&dir.join("Makefile"), b"all:");
+
+    let o = OrganizeOpts::builder().path(&dir).group_unknown(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert_eq!(stats.skipped, 1); // no extension at all
+    assert!(dir.join("Other/psd/design.psd").exists());
+    assert!(dir.join("Other/heic/IMG_1.HEIC").exists());
+    assert!(dir.join("Images/photo.jpg").exists());
+
+    // The umbrella folder is sorted output and isn't re-collected
+    let again = organize(&o, &Config::default()).unwrap();
+    assert_eq!(again.moved, 0);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn skips_junk_files() {
     let dir = tmp_dir("org_junk");
@@ -328,24 +917,86 @@ fn dry_run_moves_nothing() {
 }
 
 #[test]
-fn handles_name_collision() {
-    let dir = tmp_dir("org_collision");
-    write_file(&dir.join("photo.jpg"), b"first");
-    fs::create_dir_all(dir.join("Images")).unwrap();
-    write_file(&dir.join("Images/photo.jpg"), b"already here");
+fn dry_run_totals_bytes_per_category() {
+    let dir = tmp_dir("org_dry_bytes");
+    write_file(&dir.join("a.jpg"), &[0; 1000]);
+    write_file(&dir.join("b.png"), &[0; 500]);
+    write_file(&dir.join("report.pdf"), &[0; 42]);
 
-    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+    let o = OrganizeOpts::builder().path(&dir).dry_run(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
 
-    assert_eq!(stats.moved, 1);
-    assert_eq!(
-        fs::read_to_string(dir.join("Images/photo.jpg")).unwrap(),
-        "already here"
+    assert_eq!(stats.bytes_by_category["Images"], 1500);
+    assert_eq!(stats.bytes_by_category["Documents"], 42);
+    assert_eq!(stats.would_copy_bytes, 0);
+    assert!(!dir.join("Images").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn dry_run_counts_bytes_a_backup_would_copy() {
+    let dir = tmp_dir("org_dry_copy");
+    let side = tmp_dir("org_dry_copy_side");
+    write_file(&dir.join("a.jpg"), &[0; 1000]);
+    write_file(&dir.join("report.pdf"), &[0; 42]);
+
+    let o = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).log_file(None)
+        .backup_dir(side.join("backup")).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.would_copy_bytes, 1042);
+    assert!(!side.join("backup").exists());
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&side);
+}
+
+#[test]
+fn format_bytes_picks_readable_units() {
+    assert_eq!(format_bytes(0), "0 B");
+    assert_eq!(format_bytes(1023), "1023 B");
+    assert_eq!(format_bytes(1536), "1.5 KB");
+    assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GB");
+}
+
+#[test]
+fn handles_name_collision() {
+    let dir = tmp_dir("org_collision");
+    write_file(&dir.join("photo.jpg"), b"first");
+    fs::create_dir_all(dir.join("Images")).unwrap();
+    write_file(&dir.join("Images/photo.jpg"), b"already here");
+
+    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(
+        fs::read_to_string(dir.join("Images/photo.jpg")).unwrap(),
+        "already here"
     );
     let today = Local::now().format("%Y-%m-%d").to_string();
     assert!(dir.join(format!("Images/photo_{today}.jpg")).exists());
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn same_named_files_both_survive() {
+    let dir = tmp_dir("org_same_name");
+    write_file(&dir.join("a/photo.jpg"), b"from a");
+    write_file(&dir.join("b/photo.jpg"), b"from b");
+
+    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut contents = vec![
+        fs::read_to_string(dir.join("Images/photo.jpg")).unwrap(),
+        fs::read_to_string(dir.join(format!("Images/photo_{today}.jpg"))).unwrap(),
+    ];
+    contents.sort();
+    assert_eq!(contents, vec!["from a", "from b"]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn keep_structure_preserves_subfolders() {
     let dir = tmp_dir("org_keep");
@@ -381,6 +1032,94 @@ fn creates_log_file() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn log_footer_closes_each_run_with_totals() {
+    let dir = tmp_dir("log_footer");
+    let log = dir.with_extension("log");
+    let _ = fs::remove_file(&log);
+    write_file(&dir.join("a.jpg"), b"one");
+    write_file(&dir.join("b.pdf"), b"two");
+    touch(&dir.join("c.xyz"));
+
+    let o = OrganizeOpts::builder().path(&dir).log_file(Some(log.clone())).quiet(true).build();
+    organize(&o, &Config::default()).unwrap();
+    touch(&dir.join("d.png"));
+    organize(&o, &Config::default()).unwrap();
+
+    let text = fs::read_to_string(&log).unwrap();
+    let (first, second) = text.split_once("Run finished:").unwrap();
+    assert!(first.contains("Run started:") && first.contains("a.jpg"));
+    let first_footer = second.split("Run started:").next().unwrap();
+    assert!(first_footer.contains("Moved:        2\n"));
+    assert!(first_footer.contains("Skipped:      1\n"));
+    assert!(first_footer.contains("Errors:       0\n"));
+    assert!(first_footer.contains("Elapsed:"));
+    assert!(first_footer.trim_end().ends_with(&"=".repeat(40)));
+    // The second run gets its own header and footer
+    assert_eq!(text.matches("Run started:").count(), 2);
+    assert_eq!(text.matches("Run finished:").count(), 2);
+    assert!(text.trim_end().rsplit("Run finished:").next().unwrap().contains("Moved:        1\n"));
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&log);
+}
+
+#[test]
+fn per_run_log_is_created_and_never_organized() {
+    let dir = tmp_dir("org_log_per_run");
+    write_file(&dir.join("photo.jpg"), b"img");
+    let log = per_run_log_path(&dir.join("organizer_log.txt"));
+    assert_eq!(log.parent(), Some(dir.as_path()));
+
+    let o = OrganizeOpts::builder().path(&dir).log_file(Some(log.clone())).build();
+    organize(&o, &Config::default()).unwrap();
+    assert!(fs::read_to_string(&log).unwrap().contains("photo.jpg"));
+
+    // Even with a config that sorts .log files, the next run leaves it be
+    let mut config = Config::default();
+    config.categories.insert("Logs".into(), vec!["log".into()]);
+    config.reindex();
+    write_file(&dir.join("song.mp3"), b"mp3");
+    let stats = organize(&OrganizeOpts::builder().path(&dir).log_file(None).build(), &config).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert!(log.exists());
+    assert_eq!(stats.untouched, vec![(log, SkipReason::Junk)]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn organize_with_touches_nothing_outside_target() {
+    let dir = tmp_dir("org_embedded");
+    write_file(&dir.join("photo.jpg"), b"img");
+
+    let mut categories = std::collections::HashMap::new();
+    categories.insert("Pictures".to_string(), vec!["jpg".to_string()]);
+    let config = Config { categories, ..Default::default() };
+    let o = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    let stats = organize_with(config, o).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("Pictures/photo.jpg").exists());
+    assert!(!dir.join("organizer_log.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn log_file_can_live_anywhere() {
+    let dir = tmp_dir("org_log_path");
+    let log = dir.join(".logs/run.log");
+    fs::create_dir_all(dir.join(".logs")).unwrap();
+    write_file(&dir.join("photo.jpg"), b"img");
+
+    let o = OrganizeOpts::builder().path(&dir).log_file(Some(log.clone())).quiet(true).build();
+    organize(&o, &Config::default()).unwrap();
+
+    let text = fs::read_to_string(&log).unwrap();
+    assert!(text.contains("Run started:"));
+    assert!(text.contains("photo.jpg"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn empty_directory_returns_zeros() {
     let dir = tmp_dir("org_empty");
@@ -408,6 +1147,75 @@ fn extensionless_files_counted_as_skipped() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn throttle_spaces_out_moves() {
+    let dir = tmp_dir("org_throttle");
+    write_file(&dir.join("a.jpg"), b"img");
+    write_file(&dir.join("b.jpg"), b"img");
+    write_file(&dir.join("c.jpg"), b"img");
+
+    let o = OrganizeOpts::builder().path(&dir).throttle_ms(30).build();
+    let started = std::time::Instant::now();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    // Two pauses between three moves
+    assert_eq!(stats.moved, 3);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(60));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn max_per_folder_spills_into_numbered_folders() {
+    let dir = tmp_dir("org_cap");
+    for i in 0..1001 {
+        touch(&dir.join(format!("photo{i}.jpg")));
+    }
+
+    let o = OrganizeOpts::builder().path(&dir).max_per_folder(1000).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1001);
+    assert_eq!(sorted_files(&dir.join("Images")).len(), 1000);
+    assert_eq!(sorted_files(&dir.join("Images_2")).len(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn max_per_folder_counts_existing_files_and_skips_spill_folders() {
+    let dir = tmp_dir("org_cap_rerun");
+    touch(&dir.join("Images/old1.jpg"));
+    touch(&dir.join("Images/old2.jpg"));
+    touch(&dir.join("Images_2/old3.jpg"));
+    touch(&dir.join("new.jpg"));
+
+    let o = OrganizeOpts::builder().path(&dir).max_per_folder(2).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    // old3.jpg sits in a spill folder and is not re-collected
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("Images_2/new.jpg").exists());
+    assert!(dir.join("Images_2/old3.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn vanished_file_is_an_error_not_an_abort() {
+    let dir = tmp_dir("org_vanished");
+    write_file(&dir.join("photo.jpg"), b"img");
+    write_file(&dir.join("report.pdf"), b"doc");
+
+    // Simulate a file deleted between scan and move
+    let mut o = opts(&dir);
+    o.file_list = Some(vec![dir.join("gone.jpg"), dir.join("photo.jpg"), dir.join("report.pdf")]);
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.errors, 1);
+    assert_eq!(stats.failures[0].0, dir.join("gone.jpg"));
+    assert!(dir.join("Images/photo.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
 // ══════════════════════════════════════════════
 //  Duplicate detection
 // ══════════════════════════════════════════════
@@ -427,6 +1235,67 @@ fn detects_duplicates_same_name_and_size() {
     let _ = fs::remove_dir_all(&dir);
 }
 
+#[test]
+fn per_category_scope_namespaces_duplicate_key() {
+    let dir = tmp_dir("dup_scope");
+    write_file(&dir.join("notes.bak"), b"same");
+    let meta = fs::metadata(dir.join("notes.bak")).unwrap();
+
+    // Global: same name/date/size collide no matter where they'd go
+    assert_eq!(
+        duplicate_key("notes.bak", &meta, None).unwrap(),
+        duplicate_key("notes.bak", &meta, None).unwrap()
+    );
+    // Per category: the same file headed for two categories doesn't
+    assert_ne!(
+        duplicate_key("notes.bak", &meta, Some("Images")).unwrap(),
+        duplicate_key("notes.bak", &meta, Some("Documents")).unwrap()
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn per_category_scope_still_catches_same_category_duplicates() {
+    let dir = tmp_dir("org_dup_scope");
+    write_file(&dir.join("a/photo.jpg"), b"identical");
+    write_file(&dir.join("b/photo.jpg"), b"identical");
+
+    let o = OrganizeOpts::builder()
+        .path(&dir)
+        .find_duplicates(true)
+        .dedupe_scope(DedupeScope::PerCategory)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.duplicates, 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn per_category_scope_moves_the_same_file_into_two_categories() {
+    let dir = tmp_dir("org_dup_scope_two");
+    write_file(&dir.join("a/photo.jpg"), b"identical");
+    write_file(&dir.join("b/photo.jpg"), b"identical");
+    // The second copy is sent to Documents, so the two never share a category
+    let mapping = std::collections::HashMap::from([(dir.join("b/photo.jpg"), "Documents".to_string())]);
+
+    let run = |scope| {
+        let o = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).log_file(None)
+            .find_duplicates(true).dedupe_scope(scope).mapping(mapping.clone()).build();
+        let mut moved = Vec::new();
+        organize_with_events(&o, &Config::default(), &mut |event| {
+            if let OrganizeEvent::Moved { to, .. } = event { moved.push(to); }
+        }).unwrap();
+        moved.sort();
+        moved
+    };
+
+    assert_eq!(run(DedupeScope::PerCategory), vec![dir.join("Documents/photo.jpg"), dir.join("Images/photo.jpg")]);
+    // Compared across the run, one of them is left as a duplicate
+    assert_eq!(run(DedupeScope::Global).len(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
 #[test]
 fn different_sizes_are_not_duplicates() {
     let dir = tmp_dir("org_nodup");
@@ -441,3 +1310,3678 @@ fn different_sizes_are_not_duplicates() {
     assert_eq!(stats.moved, 2);
     let _ = fs::remove_dir_all(&dir);
 }
+
+// ══════════════════════════════════════════════
+//  OrganizeOpts defaults & builder
+// ══════════════════════════════════════════════
+
+#[test]
+fn default_opts_match_cli_defaults() {
+    let o = OrganizeOpts::default();
+    assert_eq!(o.path, PathBuf::from("."));
+    assert!(!o.dry_run && !o.find_duplicates && !o.keep_structure && !o.verify);
+    assert!(o.file_list.is_none());
+}
+
+#[test]
+fn builder_sets_only_given_fields() {
+    let o = OrganizeOpts::builder().path("/data").dry_run(true).keep_structure(true).build();
+    assert_eq!(o.path, PathBuf::from("/data"));
+    assert!(o.dry_run);
+    assert!(o.keep_structure);
+    assert!(!o.find_duplicates);
+}
+
+// ══════════════════════════════════════════════
+//  Explicit file list (--stdin-list / --files-from)
+// ══════════════════════════════════════════════
+
+#[test]
+fn file_list_organizes_only_listed_files() {
+    let dir = tmp_dir("org_list");
+    write_file(&dir.join("photo.jpg"), b"img");
+    write_file(&dir.join("sub/report.pdf"), b"doc");
+    write_file(&dir.join("song.mp3"), b"snd");
+
+    let input = format!("{}\n\n{}\n", dir.join("photo.jpg").display(), dir.join("sub/report.pdf").display());
+    let mut o = opts(&dir);
+    o.file_list = Some(read_file_list(input.as_bytes(), &dir).unwrap());
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert!(dir.join("Images/photo.jpg").exists());
+    assert!(dir.join("Documents/report.pdf").exists());
+    assert!(dir.join("song.mp3").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn file_list_ignores_missing_and_outside_paths() {
+    let dir = tmp_dir("list_filter");
+    let outside = tmp_dir("list_filter_outside");
+    write_file(&dir.join("photo.jpg"), b"img");
+    write_file(&outside.join("other.jpg"), b"img");
+
+    let input = format!(
+        "{}\n{}\n{}\n",
+        dir.join("photo.jpg").display(),
+        dir.join("missing.jpg").display(),
+        outside.join("other.jpg").display()
+    );
+    let files = read_file_list(input.as_bytes(), &dir).unwrap();
+
+    assert_eq!(files, vec![dir.join("photo.jpg")]);
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&outside);
+}
+
+#[test]
+fn file_list_takes_relative_paths_from_the_base() {
+    let dir = tmp_dir("list_relative");
+    write_file(&dir.join("sub/report.pdf"), b"doc");
+    write_file(&dir.join("photo.jpg"), b"img");
+    // The tests run from the crate root, never the base
+    assert_ne!(std::env::current_dir().unwrap().canonicalize().unwrap(), dir.canonicalize().unwrap());
+
+    let files = read_file_list("sub/report.pdf
+./photo.jpg
+src/lib.rs
+".as_bytes(), &dir).unwrap();
+
+    assert_eq!(files, vec![dir.join("sub/report.pdf"), dir.join("photo.jpg")]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  analyze
+// ══════════════════════════════════════════════
+
+#[test]
+fn analyze_tallies_extensions_without_moving() {
+    let dir = tmp_dir("analyze");
+    write_file(&dir.join("a.jpg"), b"img");
+    write_file(&dir.join("sub/b.JPG"), b"img");
+    write_file(&dir.join("c.psd"), b"psd");
+    write_file(&dir.join("Makefile"), b"all:");
+
+    let report = analyze(&dir, &Config::default()).unwrap();
+
+    assert_eq!(report[0].ext, "jpg");
+    assert_eq!(report[0].count, 2);
+    assert_eq!(report[0].category.as_deref(), Some("Images"));
+    let psd = report.iter().find(|r| r.ext == "psd").unwrap();
+    assert_eq!(psd.category, None);
+    assert!(report.iter().any(|r| r.ext.is_empty() && r.count == 1));
+
+    assert!(dir.join("a.jpg").exists());
+    assert!(!dir.join("Images").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn analyze_counts_name_patterns_like_organize() {
+    let dir = tmp_dir("analyze_patterns");
+    write_file(&dir.join("invoice_march.xyz"), b"1");
+    write_file(&dir.join("invoice_april.xyz"), b"2");
+    write_file(&dir.join("notes.xyz"), b"3");
+    let config = Config {
+        name_patterns: std::collections::HashMap::from([("Finance".to_string(), vec!["invoice_*".to_string()])]),
+        ..Config::default()
+    };
+
+    let report = analyze(&dir, &config).unwrap();
+
+    // .xyz is unknown as an extension, but the pattern covers two of them
+    let rows: Vec<_> = report.iter().map(|r| (r.ext.as_str(), r.category.as_deref(), r.count)).collect();
+    assert_eq!(rows, vec![("xyz", Some("Finance"), 2), ("xyz", None, 1)]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  scan_tree
+// ══════════════════════════════════════════════
+
+#[test]
+fn scan_counts_files_dirs_and_depth() {
+    let dir = tmp_dir("scan");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("one/b.jpg"));
+    touch(&dir.join("one/two/three/c.jpg"));
+    touch(&dir.join("Images/sorted.jpg"));
+    touch(&dir.join(".git/config"));
+
+    let t = scan_tree(&dir, &Config::default()).unwrap();
+    assert_eq!(t, TreeSummary { dirs: 3, files: 3, max_depth: 3 });
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn scan_of_flat_dir_has_zero_depth() {
+    let dir = tmp_dir("scan_flat");
+    touch(&dir.join("a.jpg"));
+
+    let t = scan_tree(&dir, &Config::default()).unwrap();
+    assert_eq!(t, TreeSummary { dirs: 0, files: 1, max_depth: 0 });
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Manifest & undo
+// ══════════════════════════════════════════════
+
+#[test]
+fn manifest_round_trips_special_characters() {
+    let dir = tmp_dir("manifest_rt");
+    let entry = ManifestEntry {
+        run_id: "r1".into(),
+        src: dir.join("weird \"name\" -> ü\n.jpg"),
+        dst: dir.join("Images/weird.jpg"),
+        timestamp: "2026-02-12T10:00:00+00:00".into(),
+        sha256: None,
+        linked: false,
+    };
+    let path = dir.join("m.jsonl");
+    let mut f = fs::File::create(&path).unwrap();
+    write_manifest(&mut f, &entry).unwrap();
+    drop(f);
+
+    assert_eq!(read_manifest(&path).unwrap(), vec![entry]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn undo_reverses_latest_run_only() {
+    let dir = tmp_dir("undo_latest");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("a.jpg"), b"first run");
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).build();
+    organize(&o, &Config::default()).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+
+    write_file(&dir.join("b.pdf"), b"second run");
+    organize(&o, &Config::default()).unwrap();
+    assert!(dir.join("Documents/b.pdf").exists());
+
+    let stats = undo(&manifest, None).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("b.pdf").exists());
+    assert!(!dir.join("Documents/b.pdf").exists());
+    assert!(dir.join("Images/a.jpg").exists());
+    assert_eq!(read_manifest(&manifest).unwrap().len(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn undo_by_run_id_restores_nested_paths() {
+    let dir = tmp_dir("undo_by_id");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("trip/photo.jpg"), b"img");
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).build();
+    organize(&o, &Config::default()).unwrap();
+    let run_id = read_manifest(&manifest).unwrap()[0].run_id.clone();
+
+    let stats = undo(&manifest, Some(&run_id)).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(fs::read(dir.join("trip/photo.jpg")).unwrap(), b"img");
+    assert!(read_manifest(&manifest).unwrap().is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn undo_keeps_the_remaining_entries_in_order() {
+    let dir = tmp_dir("undo_order");
+    let manifest = dir.join(".manifest.jsonl");
+    let entry = |run: &str, name: &str| ManifestEntry {
+        run_id: run.into(),
+        src: dir.join(name),
+        dst: dir.join("Moved").join(name),
+        timestamp: "2026-02-12T10:00:00+00:00".into(),
+        sha256: None,
+        linked: false,
+    };
+    let entries = [entry("r1", "a"), entry("r2", "b"), entry("r2", "c"), entry("r1", "e"), entry("r2", "d")];
+    let mut f = fs::File::create(&manifest).unwrap();
+    for e in &entries {
+        write_file(&e.dst, b"x");
+        write_manifest(&mut f, e).unwrap();
+    }
+    drop(f);
+    // b and d can't go back: something else took their old paths
+    write_file(&dir.join("b"), b"new");
+    write_file(&dir.join("d"), b"new");
+
+    let stats = undo(&manifest, Some("r2")).unwrap();
+
+    assert_eq!((stats.moved, stats.errors), (1, 2));
+    let left: Vec<_> = read_manifest(&manifest).unwrap().into_iter().map(|e| e.src).collect();
+    assert_eq!(left, vec![dir.join("a"), dir.join("b"), dir.join("e"), dir.join("d")]);
+    // Rewritten through a temporary file, which took the manifest's place
+    assert!(!dir.join(".manifest.jsonl.part").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Provenance index (--index-out)
+// ══════════════════════════════════════════════
+
+#[test]
+fn index_maps_new_paths_to_originals() {
+    let dir = tmp_dir("index_out");
+    let index = dir.join(".index.json");
+    write_file(&dir.join("trip/photo.jpg"), b"img");
+    write_file(&dir.join("work/report.pdf"), b"doc");
+
+    let o = OrganizeOpts::builder().path(&dir).index_out(&index).build();
+    organize(&o, &Config::default()).unwrap();
+
+    let map = read_index(&index).unwrap();
+    let key = |p: &str| Path::new(p).display().to_string();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map[&key("Images/photo.jpg")], key("trip/photo.jpg"));
+    assert_eq!(map[&key("Documents/report.pdf")], key("work/report.pdf"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn index_accumulates_across_runs_and_skips_dry_run() {
+    let dir = tmp_dir("index_merge");
+    let index = dir.join(".index.json");
+    write_file(&dir.join("a.jpg"), b"img");
+    let o = OrganizeOpts::builder().path(&dir).index_out(&index).build();
+    organize(&o, &Config::default()).unwrap();
+
+    write_file(&dir.join("b.jpg"), b"img");
+    let dry = OrganizeOpts::builder().path(&dir).index_out(&index).dry_run(true).build();
+    organize(&dry, &Config::default()).unwrap();
+    assert_eq!(read_index(&index).unwrap().len(), 1);
+
+    organize(&o, &Config::default()).unwrap();
+    assert_eq!(read_index(&index).unwrap().len(), 2);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  dedupe report
+// ══════════════════════════════════════════════
+
+#[test]
+fn content_strategy_groups_renamed_copies() {
+    let dir = tmp_dir("dedupe_content");
+    write_file(&dir.join("a.jpg"), b"same bytes");
+    write_file(&dir.join("Images/copy of a.jpg"), b"same bytes");
+    write_file(&dir.join("b.jpg"), b"other byte"); // same size, different content
+
+    let sets = find_duplicate_sets(&dir, DuplicateStrategy::Content).unwrap();
+
+    assert_eq!(sets.len(), 1);
+    assert_eq!(sets[0].files, vec![dir.join("Images/copy of a.jpg"), dir.join("a.jpg")]);
+    assert_eq!(sets[0].wasted(), 10);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn name_strategy_report_moves_nothing() {
+    let dir = tmp_dir("dedupe_name");
+    write_file(&dir.join("x/photo.jpg"), b"identical");
+    write_file(&dir.join("y/photo.jpg"), b"identical");
+    write_file(&dir.join("z/photo.jpg"), b"identical");
+
+    let sets = find_duplicate_sets(&dir, DuplicateStrategy::NameDateSize).unwrap();
+
+    assert_eq!(sets.len(), 1);
+    assert_eq!(sets[0].files.len(), 3);
+    assert_eq!(sets[0].wasted(), 18);
+    assert!(dir.join("z/photo.jpg").exists());
+    assert!(!dir.join("Images").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn apply_keeps_first_copy_of_each_set() {
+    let dir = tmp_dir("dedupe_apply");
+    write_file(&dir.join("a.txt"), b"dup");
+    write_file(&dir.join("b.txt"), b"dup");
+
+    let sets = find_duplicate_sets(&dir, DuplicateStrategy::Content).unwrap();
+    assert_eq!(remove_duplicates(&sets, &DeletePolicy::default(), |_| true).unwrap(), 1);
+    assert!(dir.join("a.txt").exists());
+    assert!(!dir.join("b.txt").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn organize_with_content_strategy_skips_renamed_duplicates() {
+    let dir = tmp_dir("org_dup_content");
+    write_file(&dir.join("photo.jpg"), b"identical");
+    write_file(&dir.join("photo (1).jpg"), b"identical");
+
+    let o = OrganizeOpts::builder()
+        .path(&dir)
+        .find_duplicates(true)
+        .dup_strategy(DuplicateStrategy::Content)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(stats.duplicates, 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Unicode normalization
+// ══════════════════════════════════════════════
+
+const CAFE_NFC: &str = "caf\u{e9}.jpg";
+const CAFE_NFD: &str = "cafe\u{301}.jpg";
+
+#[test]
+fn composed_and_decomposed_names_are_duplicates() {
+    let dir = tmp_dir("nfc_dup");
+    write_file(&dir.join("a").join(CAFE_NFC), b"same");
+    write_file(&dir.join("b").join(CAFE_NFD), b"same");
+
+    let o = OrganizeOpts::builder().path(&dir).find_duplicates(true).quiet(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(stats.duplicates, 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn reservation_treats_both_forms_as_one_name() {
+    let dir = tmp_dir("nfc_reserve");
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut reserved = std::collections::HashSet::new();
+
+    let first = resolve_collision_reserved(&dir, CAFE_NFC, "jpg", &mut reserved);
+    let second = resolve_collision_reserved(&dir, CAFE_NFD, "jpg", &mut reserved);
+
+    assert_eq!(first, dir.join(CAFE_NFC));
+    assert_eq!(second, dir.join(format!("cafe\u{301}_{today}.jpg")));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Structured errors
+// ══════════════════════════════════════════════
+
+#[test]
+fn organize_missing_folder_reports_the_path() {
+    let missing = std::env::temp_dir().join("organizer_test_no_such_folder");
+    let err = organize(&opts(&missing), &Config::default()).unwrap_err();
+    let OrganizeError::Io { path, source } = &err else { panic!("expected an io error, got {err:?}") };
+    assert_eq!(path, &missing);
+    assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn failed_move_names_both_ends() {
+    let dir = tmp_dir("err_move");
+    let err = move_file(&dir.join("gone.txt"), &dir.join("Documents/gone.txt")).unwrap_err();
+    assert!(matches!(err, OrganizeError::Move { .. }));
+    let msg = err.to_string();
+    assert!(msg.contains("gone.txt") && msg.contains("Documents"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn into_result_turns_failures_into_partial_run() {
+    assert!(Stats::default().into_result().is_ok());
+
+    let stats = Stats { errors: 1, failures: vec![(PathBuf::from("a.jpg"), "denied".into())], ..Default::default() };
+    match stats.into_result() {
+        Err(OrganizeError::PartialRun { failures }) => assert_eq!(failures.len(), 1),
+        other => panic!("expected a partial run, got {other:?}"),
+    }
+}
+
+// ══════════════════════════════════════════════
+//  --on-move hook
+// ══════════════════════════════════════════════
+
+#[test]
+fn shell_quote_survives_quotes_and_spaces() {
+    if cfg!(windows) { return; }
+    assert_eq!(shell_quote("a b.jpg"), "'a b.jpg'");
+    assert_eq!(shell_quote("it's.jpg"), r"'it'\''s.jpg'");
+}
+
+#[cfg(unix)]
+#[test]
+fn on_move_runs_once_per_moved_file() {
+    let dir = tmp_dir("hook_count");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b b.pdf"));
+    touch(&dir.join("it's.mp3"));
+    let calls = dir.with_extension("calls");
+    fs::remove_file(&calls).ok();
+
+    let opts = OrganizeOpts::builder()
+        .path(&dir)
+        .log_file(None)
+        .quiet(true)
+        .on_move(format!("true {{src}} && test -f {{dst}} && echo x >> '{}'", calls.display()))
+        .build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert_eq!(stats.errors, 0);
+    assert_eq!(fs::read_to_string(&calls).unwrap().lines().count(), 3);
+    fs::remove_file(&calls).ok();
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_on_move_counts_an_error_but_keeps_the_move() {
+    let dir = tmp_dir("hook_fail");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.pdf"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).on_move("false").build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.errors, 2);
+    assert!(dir.join("Images/a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Dry-run touches nothing
+// ══════════════════════════════════════════════
+
+/// Names directly in `dir`, sorted, without the category marker
+fn sorted_files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n != CATEGORY_MARKER)
+        .collect();
+    names.sort();
+    names
+}
+
+/// Every path under `dir`, relative and sorted (category markers left out).
+fn snapshot(dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(d) = stack.pop() {
+        for entry in fs::read_dir(&d).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name() == Some(CATEGORY_MARKER.as_ref()) { continue; }
+            out.push(path.strip_prefix(dir).unwrap().to_path_buf());
+            if path.is_dir() { stack.push(path); }
+        }
+    }
+    out.sort();
+    out
+}
+
+#[test]
+fn dry_run_with_every_destination_feature_changes_nothing() {
+    let dir = tmp_dir("dry_all");
+    touch(&dir.join("a.JPG"));
+    touch(&dir.join("sub/a.JPG"));
+    touch(&dir.join("b.pdf"));
+    touch(&dir.join("c.xyz"));
+    touch(&dir.join("Images/taken.jpg"));
+    touch(&dir.join("deep/er/d.pdf"));
+    age_file(&dir.join("b.pdf"), 400);
+    let before = snapshot(&dir);
+
+    let side = dir.with_extension("side");
+    let every = || OrganizeOpts::builder()
+        .path(&dir)
+        .dry_run(true)
+        .quiet(true)
+        .find_duplicates(true)
+        .keep_structure(true)
+        .extension_case(ExtensionCase::Lower)
+        .max_per_folder(1)
+        .group_unknown(true)
+        .log_file(Some(side.join("log.txt")))
+        .manifest(side.join("manifest.jsonl"))
+        .index_out(side.join("index.json"))
+        .on_move(format!("mkdir {}", side.display()))
+        .backup_dir(side.join("backup"));
+    // Features that can't share a run are tried one set at a time
+    let runs = [
+        every().collapse_chains(1).leave_symlink(true).build(),
+        every().keep_newest(1).keep_newest_action(RetentionAction::Archive).build(),
+        every().keep_newest(1).keep_newest_action(RetentionAction::Delete).build(),
+        every().archive_older_than(365 * DAY).build(),
+    ];
+    for opts in &runs {
+        let stats = organize(opts, &Config::default()).unwrap();
+
+        assert!(stats.moved >= 2);
+        // Each of the last three would let a pdf go
+        if opts.collapse_chains == 0 { assert!(stats.pruned + stats.archived >= 1); }
+        assert_eq!(snapshot(&dir), before);
+        assert!(!side.exists());
+    }
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Extensionless files by name
+// ══════════════════════════════════════════════
+
+fn devops_config() -> Config {
+    Config {
+        filename_categories: std::collections::HashMap::from([("Dockerfile".to_string(), "DevOps".to_string())]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn dockerfile_routes_to_its_configured_category() {
+    let dir = tmp_dir("fname_docker");
+    touch(&dir.join("Dockerfile"));
+    touch(&dir.join("LICENSE"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    let stats = organize(&opts, &devops_config()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(stats.skipped, 1);
+    assert!(dir.join("DevOps/Dockerfile").exists());
+    assert!(dir.join("LICENSE").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn extensionless_collision_gets_no_trailing_dot() {
+    let dir = tmp_dir("fname_collide");
+    touch(&dir.join("DevOps/Dockerfile"));
+    touch(&dir.join("svc/Dockerfile"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    organize(&opts, &devops_config()).unwrap();
+
+    let today = Local::now().format("%Y-%m-%d");
+    assert!(dir.join(format!("DevOps/Dockerfile_{today}")).exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn filename_category_is_exact() {
+    let config = devops_config();
+    assert_eq!(config.categorize_file("Dockerfile", ""), Some("DevOps"));
+    assert_eq!(config.categorize_file("dockerfile", ""), None);
+    assert!(config.category_names().contains(&"DevOps"));
+}
+
+// ══════════════════════════════════════════════
+//  --backup-dir
+// ══════════════════════════════════════════════
+
+#[test]
+fn backup_dir_keeps_a_copy_of_every_moved_file() {
+    let dir = tmp_dir("backup_src");
+    let backup = tmp_dir("backup_dst");
+    write_file(&dir.join("a.jpg"), b"12345");
+    write_file(&dir.join("sub/b.pdf"), b"abc");
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).backup_dir(&backup).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.backup_bytes, 8);
+    assert_eq!(fs::read(backup.join("a.jpg")).unwrap(), b"12345");
+    assert_eq!(fs::read(backup.join("sub/b.pdf")).unwrap(), b"abc");
+    assert!(dir.join("Images/a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(&backup).ok();
+}
+
+#[test]
+fn backup_dir_inside_target_is_refused() {
+    let dir = tmp_dir("backup_inside");
+    touch(&dir.join("a.jpg"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).backup_dir(dir.join("bak")).build();
+    let err = organize(&opts, &Config::default()).unwrap_err();
+
+    assert!(matches!(err, OrganizeError::Options(_)));
+    assert!(dir.join("a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn dry_run_backs_nothing_up() {
+    let dir = tmp_dir("backup_dry");
+    let backup = std::env::temp_dir().join(format!("organizer_test_backup_dry_out_{}", std::process::id()));
+    touch(&dir.join("a.jpg"));
+
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).backup_dir(&backup).build();
+    organize(&opts, &Config::default()).unwrap();
+
+    assert!(!backup.exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  --preview-limit
+// ══════════════════════════════════════════════
+
+#[test]
+fn preview_limit_keeps_the_full_tally() {
+    let dir = tmp_dir("preview_limit");
+    for i in 0..5 {
+        write_file(&dir.join(format!("{i}.jpg")), b"xy");
+    }
+
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).preview_limit(2).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 5);
+    assert_eq!(stats.bytes_by_category["Images"], 10);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn format_count_groups_thousands() {
+    assert_eq!(format_count(0), "0");
+    assert_eq!(format_count(999), "999");
+    assert_eq!(format_count(49_950), "49,950");
+    assert_eq!(format_count(1_234_567), "1,234,567");
+}
+
+// ══════════════════════════════════════════════
+//  --root
+// ══════════════════════════════════════════════
+
+#[test]
+fn root_nests_categories_under_hidden_folder() {
+    let dir = tmp_dir("root_hidden");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.pdf"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).root(".organized").build();
+    assert_eq!(organize(&opts, &Config::default()).unwrap().moved, 2);
+    assert!(dir.join(".organized/Images/a.jpg").exists());
+    assert!(dir.join(".organized/Documents/b.pdf").exists());
+    assert!(!dir.join("Images").exists());
+
+    // A second run finds nothing left to do
+    assert_eq!(organize(&opts, &Config::default()).unwrap().moved, 0);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn visible_root_is_not_rescanned() {
+    let dir = tmp_dir("root_visible");
+    touch(&dir.join("sorted/Images/old.jpg"));
+    touch(&dir.join("new.jpg"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).root("sorted").build();
+    assert_eq!(organize(&opts, &Config::default()).unwrap().moved, 1);
+    assert!(dir.join("sorted/Images/new.jpg").exists());
+    assert!(!dir.join("sorted/Images/Images").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  --hash-log
+// ══════════════════════════════════════════════
+
+const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+#[test]
+fn hash_log_records_content_hash_in_manifest_and_log() {
+    let dir = tmp_dir("hash_log");
+    write_file(&dir.join("a.pdf"), b"abc");
+    let manifest = dir.with_extension("manifest.jsonl");
+    let log = dir.with_extension("log.txt");
+
+    let opts = OrganizeOpts::builder()
+        .path(&dir)
+        .quiet(true)
+        .hash_log(true)
+        .manifest(&manifest)
+        .log_file(Some(log.clone()))
+        .build();
+    organize(&opts, &Config::default()).unwrap();
+
+    let entries = read_manifest(&manifest).unwrap();
+    assert_eq!(entries[0].sha256.as_deref(), Some(ABC_SHA256));
+    assert!(fs::read_to_string(&log).unwrap().contains(&format!("sha256:{ABC_SHA256}")));
+    fs::remove_file(&manifest).ok();
+    fs::remove_file(&log).ok();
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn manifest_without_hash_log_has_no_hash_field() {
+    let entry = ManifestEntry {
+        run_id: "r1".into(),
+        src: "a".into(),
+        dst: "b".into(),
+        timestamp: "t".into(),
+        sha256: None,
+        linked: false,
+    };
+    let mut out = Vec::new();
+    write_manifest(&mut out, &entry).unwrap();
+    assert!(!String::from_utf8(out).unwrap().contains("sha256"));
+}
+
+#[test]
+fn move_file_hashed_returns_the_content_hash() {
+    let dir = tmp_dir("hash_move");
+    write_file(&dir.join("a.txt"), b"abc");
+    let hash = move_file_hashed(&dir.join("a.txt"), &dir.join("b.txt"), true).unwrap();
+    assert_eq!(hash, ABC_SHA256);
+    assert!(!dir.join("a.txt").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Duplicates ignoring dates
+// ══════════════════════════════════════════════
+
+#[test]
+fn copies_with_different_mtimes_are_duplicates_only_when_ignoring_date() {
+    let dir = tmp_dir("dup_no_date");
+    write_file(&dir.join("a/report.pdf"), b"same bytes");
+    write_file(&dir.join("b/report.pdf"), b"same bytes");
+    let old = std::time::SystemTime::now() - std::time::Duration::from_secs(10 * 24 * 3600);
+    fs::File::options().write(true).open(dir.join("b/report.pdf")).unwrap().set_modified(old).unwrap();
+
+    let run = |strategy| {
+        let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true)
+            .find_duplicates(true).dup_strategy(strategy).build();
+        organize(&opts, &Config::default()).unwrap().duplicates
+    };
+    assert_eq!(run(DuplicateStrategy::NameDateSize), 0);
+    assert_eq!(run(DuplicateStrategy::NameSize), 1);
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Skip reasons (--explain)
+// ══════════════════════════════════════════════
+
+#[test]
+fn untouched_files_carry_a_reason() {
+    let dir = tmp_dir("explain");
+    touch(&dir.join("LICENSE"));
+    touch(&dir.join("data.xyz"));
+    touch(&dir.join("song.mp3"));
+    touch(&dir.join("Thumbs.db"));
+    touch(&dir.join("a/photo.jpg"));
+    touch(&dir.join("b/photo.jpg"));
+
+    let mut config = Config::default();
+    config.exclude_categories(&["Music".to_string()]);
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).find_duplicates(true).build();
+    let stats = organize(&opts, &config).unwrap();
+
+    let reason = |name: &str| {
+        stats.untouched.iter().find(|(p, _)| p.ends_with(name)).map(|(_, r)| *r)
+    };
+    assert_eq!(reason("LICENSE"), Some(SkipReason::NoExtension));
+    assert_eq!(reason("data.xyz"), Some(SkipReason::Unmatched));
+    assert_eq!(reason("song.mp3"), Some(SkipReason::Excluded));
+    assert_eq!(reason("Thumbs.db"), Some(SkipReason::Junk));
+    assert_eq!(stats.untouched.iter().filter(|(_, r)| *r == SkipReason::Duplicate).count(), 1);
+    assert_eq!(stats.skipped, 3);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn hidden_files_in_a_file_list_are_explained() {
+    let dir = tmp_dir("explain_hidden");
+    touch(&dir.join(".env"));
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true)
+        .file_list(vec![dir.join(".env")]).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+    assert_eq!(stats.untouched, vec![(dir.join(".env"), SkipReason::Hidden)]);
+    assert_eq!(SkipReason::Hidden.to_string(), "hidden");
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Matcher precedence
+// ══════════════════════════════════════════════
+
+fn precedence_config(order: &str) -> Config {
+    toml::from_str(&format!(r#"
+        [categories]
+        Images = ["png"]
+
+        [name_patterns]
+        Screenshots = ["Screenshot *"]
+
+        [[rules]]
+        extensions  = ["png"]
+        destination = "Ruled"
+
+        [precedence]
+        order = {order}
+    "#)).unwrap()
+}
+
+#[test]
+fn default_precedence_is_rules_names_extensions() {
+    let cfg: Config = toml::from_str("").unwrap();
+    assert_eq!(cfg.precedence.resolved(), vec![Matcher::Rules, Matcher::NamePatterns, Matcher::Extensions]);
+}
+
+#[test]
+fn reordering_precedence_changes_the_winner() {
+    let file = |cfg: &Config| cfg.categorize_with_rules("Screenshot 1.png", "png", 10, std::time::Duration::ZERO).map(String::from);
+
+    let cfg = precedence_config(r#"["rules", "name_patterns", "extensions"]"#);
+    assert_eq!(file(&cfg).as_deref(), Some("Ruled"));
+
+    let cfg = precedence_config(r#"["name_patterns", "rules", "extensions"]"#);
+    assert_eq!(file(&cfg).as_deref(), Some("Screenshots"));
+
+    let cfg = precedence_config(r#"["extensions", "name_patterns", "rules"]"#);
+    assert_eq!(file(&cfg).as_deref(), Some("Images"));
+    assert_eq!(cfg.categorize_file("Screenshot 1.png", "png"), Some("Images"));
+}
+
+#[test]
+fn matchers_left_out_of_precedence_come_last() {
+    let cfg = precedence_config(r#"["extensions"]"#);
+    assert_eq!(cfg.precedence.resolved(), vec![Matcher::Extensions, Matcher::Rules, Matcher::NamePatterns]);
+    assert_eq!(cfg.categorize_file("Screenshot 1.jpg", "jpg"), Some("Screenshots"));
+}
+
+// ══════════════════════════════════════════════
+//  --merge-into-existing
+// ══════════════════════════════════════════════
+
+#[test]
+fn second_run_merges_into_the_existing_structure() {
+    let dir = tmp_dir("merge_existing");
+    write_file(&dir.join("Work/Reports/q1.pdf"), b"q1");
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true)
+        .keep_structure(true).merge_into_existing(true).build();
+    organize(&opts, &Config::default()).unwrap();
+    assert!(dir.join("Documents/Work/Reports/q1.pdf").exists());
+
+    // Same tree spelled differently, plus a name that collides
+    write_file(&dir.join("work/reports/q2.pdf"), b"q2");
+    write_file(&dir.join("WORK/Reports/q1.pdf"), b"q1 again");
+    write_file(&dir.join("work/Invoices/i1.pdf"), b"i1");
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    let reports = dir.join("Documents/Work/Reports");
+    assert!(reports.join("q2.pdf").exists());
+    assert_eq!(fs::read(reports.join("q1.pdf")).unwrap(), b"q1");
+    assert_eq!(sorted_files(&reports).len(), 3);
+    assert!(dir.join("Documents/Work/Invoices/i1.pdf").exists());
+    assert_eq!(sorted_files(&dir.join("Documents")).len(), 1);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn merge_existing_keeps_unmatched_components() {
+    let dir = tmp_dir("merge_fn");
+    fs::create_dir_all(dir.join("Photos/2024")).unwrap();
+    assert_eq!(merge_existing(&dir, Path::new("photos/2024/Trip")), PathBuf::from("Photos/2024/Trip"));
+    assert_eq!(merge_existing(&dir, Path::new("Music")), PathBuf::from("Music"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  --detect-language
+// ══════════════════════════════════════════════
+
+#[test]
+fn text_files_are_sorted_by_language() {
+    let dir = tmp_dir("lang");
+    write_file(&dir.join("en.txt"), b"The quick brown fox jumps over the lazy dog. This is clearly an English \
+        sentence, written to be detected without any doubt whatsoever by the detector.");
+    write_file(&dir.join("fr.txt"), "Bonjour à tous, je m'appelle Marie et j'habite à Paris depuis dix ans. \
+        Nous allons au marché tous les samedis pour acheter du pain et du fromage.".as_bytes());
+    write_file(&dir.join("short.txt"), b"ok");
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).detect_language(true).build();
+    organize(&opts, &Config::default()).unwrap();
+
+    assert!(dir.join("Documents/English/en.txt").exists());
+    assert!(dir.join("Documents/French/fr.txt").exists());
+    assert!(dir.join("Documents/short.txt").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn language_detection_is_opt_in() {
+    let dir = tmp_dir("lang_off");
+    write_file(&dir.join("fr.txt"), "Bonjour à tous, je m'appelle Marie et j'habite à Paris depuis dix ans.".as_bytes());
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    organize(&opts, &Config::default()).unwrap();
+    assert!(dir.join("Documents/fr.txt").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  --preview-tree-dir
+// ══════════════════════════════════════════════
+
+#[test]
+fn preview_tree_mirrors_the_plan_with_links() {
+    let dir = tmp_dir("ptree_src");
+    let tree = tmp_dir("ptree_out");
+    write_file(&dir.join("a.jpg"), b"img");
+    write_file(&dir.join("sub/b.pdf"), b"doc");
+    let before = snapshot(&dir);
+
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true)
+        .keep_structure(true).preview_tree_dir(&tree).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.errors, 0);
+    assert_eq!(snapshot(&dir), before);
+    assert_eq!(fs::read(tree.join("Images/a.jpg")).unwrap(), b"img");
+    assert_eq!(fs::read(tree.join("Documents/sub/b.pdf")).unwrap(), b"doc");
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(&tree).ok();
+}
+
+#[test]
+fn preview_tree_inside_target_is_refused() {
+    let dir = tmp_dir("ptree_inside");
+    touch(&dir.join("a.jpg"));
+    let opts = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).preview_tree_dir(dir.join("preview")).build();
+    assert!(matches!(organize(&opts, &Config::default()), Err(OrganizeError::Options(_))));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Case-insensitive collisions
+// ══════════════════════════════════════════════
+
+#[test]
+fn case_variants_both_survive_when_ignoring_case() {
+    let dir = tmp_dir("case_fold");
+    write_file(&dir.join("a/Photo.jpg"), b"one");
+    write_file(&dir.join("b/photo.jpg"), b"two");
+    write_file(&dir.join("Images/PHOTO.JPG"), b"zero");
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).case_insensitive_names(true).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    let mut names: Vec<String> = sorted_files(&dir.join("Images")).iter().map(|n| n.to_lowercase()).collect();
+    names.dedup();
+    assert_eq!(names.len(), 3, "{names:?}");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn case_folded_reservations_see_each_other() {
+    let dir = tmp_dir("case_reserve");
+    let mut reserved = std::collections::HashSet::new();
+    let first = resolve_collision_with(&dir, "Photo.jpg", "jpg", &mut reserved, true);
+    let second = resolve_collision_with(&dir, "photo.jpg", "jpg", &mut reserved, true);
+    assert_eq!(first, dir.join("Photo.jpg"));
+    assert_ne!(second.to_string_lossy().to_lowercase(), first.to_string_lossy().to_lowercase());
+
+    let mut reserved = std::collections::HashSet::new();
+    resolve_collision_with(&dir, "Photo.jpg", "jpg", &mut reserved, false);
+    assert_eq!(resolve_collision_with(&dir, "photo.jpg", "jpg", &mut reserved, false), dir.join("photo.jpg"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Deletion guard
+// ══════════════════════════════════════════════
+
+fn many_files(dir: &Path, n: usize) -> Vec<PathBuf> {
+    (0..n).map(|i| {
+        let p = dir.join(format!("f{i}.tmp"));
+        write_file(&p, b"x");
+        p
+    }).collect()
+}
+
+#[test]
+fn large_delete_asks_and_no_keeps_everything() {
+    let dir = tmp_dir("del_guard");
+    let files = many_files(&dir, 5);
+    let policy = DeletePolicy { max_files: 3, ..Default::default() };
+
+    let mut asked = None;
+    let err = delete_files(&files, &policy, |what| { asked = Some(what.to_string()); false }).unwrap_err();
+
+    assert!(matches!(err, OrganizeError::Cancelled(_)));
+    assert_eq!(asked.as_deref(), Some("5 files (5 B)"));
+    assert!(files.iter().all(|f| f.exists()));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn yes_bypasses_the_guard() {
+    let dir = tmp_dir("del_yes");
+    let files = many_files(&dir, 5);
+    let policy = DeletePolicy { max_files: 3, assume_yes: true, ..Default::default() };
+
+    let n = delete_files(&files, &policy, |_| panic!("should not ask")).unwrap();
+    assert_eq!(n, 5);
+    assert!(files.iter().all(|f| !f.exists()));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn size_threshold_triggers_the_guard_too() {
+    let policy = DeletePolicy { max_files: 0, max_bytes: 100, ..Default::default() };
+    assert!(!policy.needs_confirmation(1000, 100));
+    assert!(policy.needs_confirmation(1, 101));
+}
+
+// ══════════════════════════════════════════════
+//  --peek-archives
+// ══════════════════════════════════════════════
+
+fn write_zip(path: &Path, names: &[&str]) {
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).unwrap());
+    let stored = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for name in names {
+        zip.start_file(*name, stored).unwrap();
+        std::io::Write::write_all(&mut zip, b"x").unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+fn write_tar(path: &Path, names: &[&str]) {
+    let mut tar = tar::Builder::new(fs::File::create(path).unwrap());
+    for name in names {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_cksum();
+        tar.append_data(&mut header, name, &b"x"[..]).unwrap();
+    }
+    tar.finish().unwrap();
+}
+
+#[test]
+fn archives_follow_their_dominant_content() {
+    let dir = tmp_dir("peek");
+    write_zip(&dir.join("photos.zip"), &["a.jpg", "b.png", "c.jpg", "notes.txt"]);
+    write_tar(&dir.join("music.tar"), &["album/1.mp3", "album/2.flac"]);
+    write_zip(&dir.join("mystery.zip"), &["a.xyz"]);
+    write_file(&dir.join("broken.zip"), b"not a zip");
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).peek_archives(true).build();
+    let stats = organize(&opts, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 4);
+    assert!(dir.join("Images/Archives/photos.zip").exists());
+    assert!(dir.join("Music/Archives/music.tar").exists());
+    assert!(dir.join("Archives/mystery.zip").exists());
+    assert!(dir.join("Archives/broken.zip").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn archives_stay_in_archives_without_peeking() {
+    let dir = tmp_dir("peek_off");
+    write_zip(&dir.join("photos.zip"), &["a.jpg"]);
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    organize(&opts, &Config::default()).unwrap();
+    assert!(dir.join("Archives/photos.zip").exists());
+    assert_eq!(dominant_category(&dir.join("Archives/photos.zip"), &Config::default()).as_deref(), Some("Images"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Verbose dry-run
+// ══════════════════════════════════════════════
+
+#[test]
+fn verbose_output_does_not_change_the_tally() {
+    let dir = tmp_dir("verbose");
+    touch(&dir.join("a/x.jpg"));
+    touch(&dir.join("b/x.jpg"));
+    touch(&dir.join("c.xyz"));
+    touch(&dir.join("README"));
+
+    let run = |verbose| {
+        let opts = OrganizeOpts::builder().path(&dir).dry_run(true).find_duplicates(true).verbose(verbose).build();
+        let s = organize(&opts, &Config::default()).unwrap();
+        (s.moved, s.duplicates, s.skipped, s.untouched.len())
+    };
+    assert_eq!(run(true), (1, 1, 2, 3));
+    assert_eq!(run(true), run(false));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  --no-recurse
+// ══════════════════════════════════════════════
+
+#[test]
+fn no_recurse_collects_only_top_level_files() {
+    let dir = tmp_dir("no_recurse");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join(".hidden.jpg"));
+    touch(&dir.join("project/b.pdf"));
+    touch(&dir.join("Images/c.jpg"));
+
+    let mut files = collect_files_with(&dir, &["Images"], false).unwrap();
+    files.sort();
+    assert_eq!(files, vec![dir.join("a.jpg")]);
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).no_recurse(true).build();
+    assert_eq!(organize(&opts, &Config::default()).unwrap().moved, 1);
+    assert!(dir.join("project/b.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Free-space pre-flight (--min-free)
+// ══════════════════════════════════════════════
+
+#[test]
+fn parse_size_understands_units() {
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("500M"), Ok(500 << 20));
+    assert_eq!(parse_size("2g"), Ok(2 << 30));
+    assert_eq!(parse_size("1.5GB"), Ok(3 << 29));
+    assert_eq!(parse_size("10KiB"), Ok(10 << 10));
+    assert!(parse_size("lots").is_err());
+}
+
+#[test]
+fn backup_without_room_aborts_before_moving() {
+    let dir = tmp_dir("min_free_src");
+    let backup = tmp_dir("min_free_bak");
+    touch(&dir.join("a.jpg"));
+
+    let opts = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true)
+        .backup_dir(&backup).min_free(u64::MAX / 2).build();
+    let err = organize(&opts, &Config::default()).unwrap_err();
+
+    assert!(matches!(err, OrganizeError::InsufficientSpace { needed: 0, .. }), "{err}");
+    assert!(dir.join("a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(&backup).ok();
+}
+
+#[test]
+fn free_space_check_probes_a_missing_dir_through_its_parent() {
+    let missing = std::env::temp_dir().join("organizer_test_no_such_backup/deeper");
+    assert!(check_free_space(&missing, 1, 0).is_ok());
+}
+
+// ══════════════════════════════════════════════
+//  Permissions across the copy path
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn copy_path_keeps_the_file_mode() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tmp_dir("perm_mode");
+    for verify in [false, true] {
+        let src = dir.join(format!("secret_{verify}.txt"));
+        let dst = dir.join(format!("moved_{verify}.txt"));
+        write_file(&src, b"key");
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o640)).unwrap();
+
+        copy_then_remove(&src, &dst, verify).unwrap();
+        assert_eq!(fs::metadata(&dst).unwrap().permissions().mode() & 0o7777, 0o640, "verify = {verify}");
+    }
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn restore_owner_is_a_no_op_for_the_same_owner() {
+    let dir = tmp_dir("perm_owner");
+    write_file(&dir.join("a.txt"), b"x");
+    write_file(&dir.join("b.txt"), b"y");
+    let meta = fs::metadata(dir.join("a.txt")).unwrap();
+    assert!(restore_owner(&meta, &dir.join("b.txt")));
+    assert!(!restore_owner(&meta, &dir.join("missing.txt")));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Resume
+// ══════════════════════════════════════════════
+
+#[test]
+fn resume_skips_files_the_interrupted_run_already_moved() {
+    let dir = tmp_dir("resume");
+    let manifest = dir.join(".manifest.jsonl");
+    for name in ["a.jpg", "b.jpg", "c.pdf"] {
+        write_file(&dir.join(name), name.as_bytes());
+    }
+    // The interrupted run copied a.jpg and recorded it, then died before
+    // removing the original
+    fs::create_dir_all(dir.join("Images")).unwrap();
+    fs::copy(dir.join("a.jpg"), dir.join("Images/a.jpg")).unwrap();
+    let abs = std::path::absolute(&dir).unwrap();
+    let mut f = fs::File::create(&manifest).unwrap();
+    write_manifest(&mut f, &ManifestEntry {
+        run_id: "20260101-000000-000".into(),
+        src: abs.join("a.jpg"),
+        dst: abs.join("Images/a.jpg"),
+        timestamp: "2026-01-01T00:00:00+00:00".into(),
+        sha256: None,
+        linked: false,
+    }).unwrap();
+    drop(f);
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).resume(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.untouched, vec![(dir.join("a.jpg"), SkipReason::AlreadyMoved)]);
+    assert_eq!(snapshot(&dir.join("Images")), vec![PathBuf::from("a.jpg"), PathBuf::from("b.jpg")]);
+    assert!(dir.join("Documents/c.pdf").exists());
+    let entries = read_manifest(&manifest).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries.iter().all(|e| e.run_id == "20260101-000000-000"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn resume_still_moves_a_file_whose_destination_differs_in_size() {
+    let dir = tmp_dir("resume_size");
+    write_file(&dir.join("a.jpg"), b"the real photo");
+    write_file(&dir.join("Images/a.jpg"), b"half");
+
+    let o = OrganizeOpts::builder().path(&dir).resume(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(snapshot(&dir.join("Images")).len(), 2);
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Extension count thresholds
+// ══════════════════════════════════════════════
+
+fn ext_count_fixture(label: &str) -> PathBuf {
+    let dir = tmp_dir(label);
+    for i in 0..3 { write_file(&dir.join(format!("photo{i}.jpg")), b"x"); }
+    for i in 0..2 { write_file(&dir.join(format!("doc{i}.PDF")), b"x"); }
+    write_file(&dir.join("song.mp3"), b"x");
+    dir
+}
+
+#[test]
+fn min_ext_count_leaves_rare_extensions_in_place() {
+    let dir = ext_count_fixture("ext_min");
+    let o = OrganizeOpts::builder().path(&dir).min_ext_count(2).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 5);
+    assert_eq!(stats.untouched, vec![(dir.join("song.mp3"), SkipReason::ExtensionCount)]);
+    assert!(dir.join("Documents/doc1.PDF").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn max_ext_count_leaves_bulk_extensions_in_place() {
+    let dir = ext_count_fixture("ext_max");
+    let o = OrganizeOpts::builder().path(&dir).max_ext_count(2).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert!(dir.join("photo0.jpg").exists());
+    assert!(dir.join("Music/song.mp3").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn ext_count_bounds_are_inclusive_and_validated() {
+    let dir = ext_count_fixture("ext_both");
+    let o = OrganizeOpts::builder().path(&dir).min_ext_count(2).max_ext_count(2).build();
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 2);
+
+    let bad = OrganizeOpts::builder().path(&dir).min_ext_count(3).max_ext_count(2).build();
+    assert!(matches!(organize(&bad, &Config::default()), Err(OrganizeError::Options(_))));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Custom categorizer
+// ══════════════════════════════════════════════
+
+/// Sorts by size alone: small files to Small/, the rest stay put
+struct BySize;
+
+impl Categorizer for BySize {
+    fn category_for(&self, _path: &Path, _ext: &str, meta: &fs::Metadata) -> Option<String> {
+        (meta.len() < 10).then(|| "Small".to_string())
+    }
+
+    fn folder_names(&self) -> Vec<String> {
+        vec!["Small".into()]
+    }
+}
+
+#[test]
+fn organize_accepts_a_custom_categorizer() {
+    let dir = tmp_dir("custom_categorizer");
+    write_file(&dir.join("tiny.weird"), b"x");
+    write_file(&dir.join("Makefile"), b"all:");
+    write_file(&dir.join("big.jpg"), b"far more than ten bytes");
+    write_file(&dir.join("Small/earlier.txt"), b"x");
+
+    let stats = organize(&opts(&dir), &BySize).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert!(dir.join("Small/tiny.weird").exists());
+    assert!(dir.join("Small/Makefile").exists());
+    assert!(dir.join("big.jpg").exists());
+    assert_eq!(stats.untouched, vec![(dir.join("big.jpg"), SkipReason::Unmatched)]);
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Category folder markers
+// ══════════════════════════════════════════════
+
+#[test]
+fn renamed_category_folder_is_not_resorted() {
+    let dir = tmp_dir("marker_rename");
+    write_file(&dir.join("a.jpg"), b"first run");
+    organize(&opts(&dir), &Config::default()).unwrap();
+    assert!(dir.join("Images").join(CATEGORY_MARKER).exists());
+
+    // The user renames Images to Photos in their config
+    let mut config = Config::default();
+    let images = config.categories.remove("Images").unwrap();
+    config.categories.insert("Photos".into(), images);
+    config.reindex();
+    write_file(&dir.join("b.jpg"), b"second run");
+
+    let stats = organize(&opts(&dir), &config).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("Images/a.jpg").exists());
+    assert!(dir.join("Photos/b.jpg").exists());
+    assert!(!dir.join("Photos/a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn dry_run_leaves_no_marker_and_undo_removes_it() {
+    let dir = tmp_dir("marker_undo");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("a.pdf"), b"x");
+
+    let dry = OrganizeOpts::builder().path(&dir).dry_run(true).build();
+    organize(&dry, &Config::default()).unwrap();
+    assert!(!dir.join("Documents").exists());
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).build();
+    organize(&o, &Config::default()).unwrap();
+    undo(&manifest, None).unwrap();
+
+    assert!(dir.join("a.pdf").exists());
+    assert!(!dir.join("Documents").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  MIME fallback
+// ══════════════════════════════════════════════
+
+#[test]
+fn mime_category_resolves_extensions_missing_from_the_config() {
+    let config = Config::default();
+    assert_eq!(config.categorize("tiff"), None);
+    assert_eq!(config.mime_category("tiff"), Some("Images"));
+    assert_eq!(config.mime_category("m4a"), Some("Music"));
+    assert_eq!(config.mime_category("ogv"), Some("Videos"));
+    assert_eq!(config.mime_category("psd"), None);
+    assert_eq!(config.mime_category("notarealext"), None);
+}
+
+#[test]
+fn mime_categories_can_be_overridden_in_config() {
+    let mut config: Config = toml::from_str(r#"
+        [mime_categories]
+        image        = "Pictures"
+        "image/tiff" = "Scans"
+    "#).unwrap();
+    assert_eq!(config.mime_category("tiff"), Some("Scans"));
+    assert_eq!(config.mime_category("bmp"), Some("Pictures"));
+    assert_eq!(config.mime_category("m4a"), None);
+
+    config.exclude_categories(&["Scans".to_string()]);
+    assert_eq!(config.mime_category("tiff"), None);
+}
+
+#[test]
+fn mime_fallback_is_opt_in() {
+    let dir = tmp_dir("mime_fallback");
+    write_file(&dir.join("scan.tiff"), b"x");
+    write_file(&dir.join("voice.m4a"), b"x");
+    write_file(&dir.join("photo.jpg"), b"x");
+
+    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("scan.tiff").exists());
+
+    let o = OrganizeOpts::builder().path(&dir).mime_fallback(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!(stats.moved, 2);
+    assert!(dir.join("Images/scan.tiff").exists());
+    assert!(dir.join("Music/voice.m4a").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Progress events
+// ══════════════════════════════════════════════
+
+#[test]
+fn organize_with_events_reports_every_file() {
+    let dir = tmp_dir("events");
+    write_file(&dir.join("a.jpg"), b"x");
+    write_file(&dir.join("b.unknown"), b"x");
+    write_file(&dir.join(".hidden"), b"x");
+
+    let mut events = Vec::new();
+    let o = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true).build();
+    let stats = organize_with_events(&o, &Config::default(), &mut |e| events.push(e)).unwrap();
+
+    assert!(matches!(events.first(), Some(OrganizeEvent::Started { total: 2 })));
+    let moved: Vec<_> = events.iter().filter_map(|e| match e {
+        OrganizeEvent::Moved { from, to, .. } => Some((from.clone(), to.clone())),
+        _ => None,
+    }).collect();
+    assert_eq!(moved, vec![(dir.join("a.jpg"), dir.join("Images/a.jpg"))]);
+    assert!(events.iter().any(|e| matches!(e,
+        OrganizeEvent::Skipped { path, reason: SkipReason::Unmatched } if path == &dir.join("b.unknown"))));
+    match events.last() {
+        Some(OrganizeEvent::Finished { stats: last }) => assert_eq!(last.moved, stats.moved),
+        other => panic!("expected Finished, got {other:?}"),
+    }
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn failed_file_is_reported_as_an_error_event() {
+    let dir = tmp_dir("events_error");
+    let list = vec![dir.join("gone.jpg")];
+
+    let mut events = Vec::new();
+    let o = OrganizeOpts::builder().path(&dir).file_list(list).log_file(None).quiet(true).build();
+    organize_with_events(&o, &Config::default(), &mut |e| events.push(e)).unwrap();
+
+    assert!(events.iter().any(|e| matches!(e, OrganizeEvent::Error { path, .. } if path == &dir.join("gone.jpg"))));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Dangerous targets
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn roots_and_system_folders_are_dangerous() {
+    for path in ["/", "/usr", "/usr/", "/usr/share/doc", "/etc", "/home", "/System/Library"] {
+        assert!(is_dangerous_target(Path::new(path)), "{path}");
+    }
+    for path in ["/usr2", "/home/alex/Downloads", "/srv/media", "/var/tmp/inbox"] {
+        assert!(!is_dangerous_target(Path::new(path)), "{path}");
+    }
+}
+
+#[test]
+fn home_folder_is_dangerous_but_not_its_subfolders() {
+    let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) else { return };
+    let home = PathBuf::from(home);
+    assert!(is_dangerous_target(&home));
+    assert!(!is_dangerous_target(&home.join("Downloads")));
+    assert!(!is_dangerous_target(&std::env::temp_dir().join("organizer_inbox")));
+}
+
+#[cfg(unix)]
+#[test]
+fn organize_refuses_a_dangerous_target_without_force() {
+    // An empty file list: even with --force nothing could be touched
+    let o = OrganizeOpts::builder().path("/").file_list(Vec::new()).dry_run(true).log_file(None).quiet(true).build();
+    assert!(matches!(organize(&o, &Config::default()), Err(OrganizeError::Options(_))));
+
+    let forced = OrganizeOpts::builder().path("/").file_list(Vec::new()).dry_run(true).force(true)
+        .log_file(None).quiet(true).build();
+    assert_eq!(organize(&forced, &Config::default()).unwrap().moved, 0);
+}
+
+// ══════════════════════════════════════════════
+//  Largest files
+// ══════════════════════════════════════════════
+
+#[test]
+fn top_files_keeps_the_largest_moves_biggest_first() {
+    let dir = tmp_dir("top_files");
+    for (name, size) in [("a.jpg", 30), ("b.pdf", 500), ("c.mp3", 10), ("d.mp4", 2000), ("e.zip", 500)] {
+        write_file(&dir.join(name), &vec![0u8; size]);
+    }
+    write_file(&dir.join("huge.unknown"), &vec![0u8; 9000]);
+
+    let o = OrganizeOpts::builder().path(&dir).top_files(3).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    let sizes: Vec<u64> = stats.largest.iter().map(|(b, _)| *b).collect();
+    assert_eq!(sizes, vec![2000, 500, 500]);
+    assert_eq!(stats.largest[0].1, dir.join("Videos/d.mp4"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn top_files_is_off_by_default() {
+    let dir = tmp_dir("top_files_off");
+    write_file(&dir.join("a.jpg"), b"x");
+    let stats = organize(&opts(&dir), &Config::default()).unwrap();
+    assert!(stats.largest.is_empty());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  By parent folder
+// ══════════════════════════════════════════════
+
+#[test]
+fn parent_category_uses_the_immediate_parent() {
+    let base = Path::new("/data/inbox");
+    assert_eq!(parent_category(base, &base.join("ClientA/brief.pdf")).as_deref(), Some("Projects/ClientA"));
+    assert_eq!(parent_category(base, &base.join("ClientA/src/main.rs")).as_deref(), Some("Projects/src"));
+    assert_eq!(parent_category(base, &base.join("loose.jpg")), None);
+    assert_eq!(parent_category(base, Path::new("/elsewhere/x.jpg")), None);
+}
+
+#[test]
+fn by_parent_groups_files_by_folder() {
+    let dir = tmp_dir("by_parent");
+    write_file(&dir.join("ClientA/brief.pdf"), b"x");
+    write_file(&dir.join("ClientA/logo.unknown"), b"x");
+    write_file(&dir.join("ClientB/notes/todo.txt"), b"x");
+    write_file(&dir.join("loose.jpg"), b"x");
+
+    let o = OrganizeOpts::builder().path(&dir).by_parent(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 4);
+    assert!(dir.join("Projects/ClientA/brief.pdf").exists());
+    assert!(dir.join("Projects/ClientA/logo.unknown").exists());
+    assert!(dir.join("Projects/notes/todo.txt").exists());
+    assert!(dir.join("Images/loose.jpg").exists());
+
+    // The Projects tree is output now, not input
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 0);
+
+    let both = OrganizeOpts::builder().path(&dir).by_parent(true).keep_structure(true).build();
+    assert!(matches!(organize(&both, &Config::default()), Err(OrganizeError::Options(_))));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Plan, then apply
+// ══════════════════════════════════════════════
+
+#[test]
+fn plan_moves_touches_nothing() {
+    let dir = tmp_dir("plan_only");
+    write_file(&dir.join("a.jpg"), b"x");
+    write_file(&dir.join("b.pdf"), b"x");
+    let before = snapshot(&dir);
+
+    let mut plan = plan_moves(&opts(&dir), &Config::default()).unwrap();
+    plan.sort();
+
+    assert_eq!(plan, vec![
+        (dir.join("a.jpg"), dir.join("Images/a.jpg")),
+        (dir.join("b.pdf"), dir.join("Documents/b.pdf")),
+    ]);
+    assert_eq!(snapshot(&dir), before);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn apply_plan_moves_only_the_kept_subset() {
+    let dir = tmp_dir("plan_apply");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("a.jpg"), b"x");
+    write_file(&dir.join("b.pdf"), b"x");
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).log_file(None).quiet(true).build();
+
+    let plan = plan_moves(&o, &Config::default()).unwrap();
+    let kept: Vec<_> = plan.into_iter().filter(|(from, _)| from.ends_with("b.pdf")).collect();
+    let stats = apply_plan(&o, &kept).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert!(dir.join("a.jpg").exists());
+    assert!(dir.join("Documents/b.pdf").exists());
+    assert_eq!(read_manifest(&manifest).unwrap().len(), 1);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn apply_plan_renames_when_the_destination_was_taken_meanwhile() {
+    let dir = tmp_dir("plan_taken");
+    write_file(&dir.join("a.jpg"), b"mine");
+    let o = OrganizeOpts::builder().path(&dir).log_file(None).quiet(true)
+        .collision_scheme(CollisionScheme::Numeric).build();
+    let plan = plan_moves(&o, &Config::default()).unwrap();
+
+    write_file(&dir.join("Images/a.jpg"), b"someone else's");
+    apply_plan(&o, &plan).unwrap();
+
+    assert_eq!(fs::read(dir.join("Images/a.jpg")).unwrap(), b"someone else's");
+    assert_eq!(fs::read(dir.join("Images/a (1).jpg")).unwrap(), b"mine");
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Hard-link mode
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn hardlink_mode_links_and_keeps_the_source() {
+    use std::os::unix::fs::MetadataExt;
+    let dir = tmp_dir("hardlink");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("photo.jpg"), b"pixels");
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).hardlink(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    let (src, dst) = (fs::metadata(dir.join("photo.jpg")).unwrap(), fs::metadata(dir.join("Images/photo.jpg")).unwrap());
+    assert_eq!((src.dev(), src.ino()), (dst.dev(), dst.ino()));
+    assert_eq!(src.nlink(), 2);
+
+    // Already linked: the next run has nothing to do
+    let again = organize(&o, &Config::default()).unwrap();
+    assert_eq!(again.moved, 0);
+    assert_eq!(again.untouched, vec![(dir.join("photo.jpg"), SkipReason::AlreadyMoved)]);
+
+    // Undo only drops the link
+    undo(&manifest, None).unwrap();
+    assert!(dir.join("photo.jpg").exists());
+    assert!(!dir.join("Images/photo.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn link_or_copy_leaves_the_source() {
+    let dir = tmp_dir("link_or_copy");
+    write_file(&dir.join("a.txt"), b"data");
+    link_or_copy(&dir.join("a.txt"), &dir.join("b.txt")).unwrap();
+
+    assert_eq!(fs::read(dir.join("a.txt")).unwrap(), b"data");
+    assert_eq!(fs::read(dir.join("b.txt")).unwrap(), b"data");
+    assert!(!same_file(&dir.join("a.txt"), &dir.join("missing.txt")));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Dates from file names
+// ══════════════════════════════════════════════
+
+fn ymd(y: i32, m: u32, d: u32) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::from_ymd_opt(y, m, d)
+}
+
+#[test]
+fn date_from_name_reads_dashed_dates() {
+    assert_eq!(date_from_name("2024-03-12 report.pdf"), ymd(2024, 3, 12));
+    assert_eq!(date_from_name("Screenshot 2024_03_12 at 10.15.00.png"), ymd(2024, 3, 12));
+    assert_eq!(date_from_name("scan.2023.12.31.tiff"), ymd(2023, 12, 31));
+}
+
+#[test]
+fn date_from_name_reads_compact_camera_dates() {
+    assert_eq!(date_from_name("IMG_20240312_101500.jpg"), ymd(2024, 3, 12));
+    assert_eq!(date_from_name("VID-20231105-WA0001.mp4"), ymd(2023, 11, 5));
+    assert_eq!(date_from_name("20220101.pdf"), ymd(2022, 1, 1));
+}
+
+#[test]
+fn date_from_name_ignores_numbers_that_are_not_dates() {
+    assert_eq!(date_from_name("invoice_12345678.pdf"), None);
+    assert_eq!(date_from_name("2024-13-01.txt"), None);
+    assert_eq!(date_from_name("track_123456789.mp3"), None);
+    assert_eq!(date_from_name("holiday.jpg"), None);
+}
+
+#[test]
+fn date_from_name_sorts_into_year_and_month() {
+    let dir = tmp_dir("date_from_name");
+    write_file(&dir.join("IMG_20240312_101500.jpg"), b"x");
+    write_file(&dir.join("plain.jpg"), b"x");
+
+    let o = OrganizeOpts::builder().path(&dir).date_from_name(true).build();
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 2);
+
+    assert!(dir.join("Images/2024/03/IMG_20240312_101500.jpg").exists());
+    let now = Local::now().format("%Y/%m").to_string();
+    assert!(dir.join("Images").join(now).join("plain.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Collision outcomes
+// ══════════════════════════════════════════════
+
+#[test]
+fn collision_outcome_fresh_identical_and_renamed() {
+    let dir = tmp_dir("col_outcome");
+    let dest = dir.join("Images");
+    write_file(&dir.join("new.jpg"), b"new");
+    write_file(&dir.join("same.jpg"), b"same bytes");
+    write_file(&dest.join("same.jpg"), b"same bytes");
+    write_file(&dir.join("other.jpg"), b"mine");
+    write_file(&dest.join("other.jpg"), b"theirs");
+    let mut reserved = std::collections::HashSet::new();
+    let mut check = |name: &str| resolve_collision_checked(
+        &dir.join(name), &dest, name, "jpg", &mut reserved, false, CollisionScheme::Numeric, true,
+    );
+
+    assert_eq!(check("new.jpg"), CollisionOutcome::Fresh(dest.join("new.jpg")));
+    assert_eq!(check("same.jpg"), CollisionOutcome::IdenticalSkip);
+    assert_eq!(check("other.jpg"), CollisionOutcome::Renamed(dest.join("other (1).jpg")));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn identical_contents_are_renamed_unless_asked_to_skip() {
+    let dir = tmp_dir("col_outcome_off");
+    write_file(&dir.join("same.jpg"), b"same bytes");
+    write_file(&dir.join("Images/same.jpg"), b"same bytes");
+    let outcome = resolve_collision_checked(
+        &dir.join("same.jpg"), &dir.join("Images"), "same.jpg", "jpg",
+        &mut std::collections::HashSet::new(), false, CollisionScheme::Numeric, false,
+    );
+    assert_eq!(outcome, CollisionOutcome::Renamed(dir.join("Images/same (1).jpg")));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn skip_identical_tallies_each_outcome() {
+    let dir = tmp_dir("col_outcome_organize");
+    write_file(&dir.join("same.jpg"), b"same bytes");
+    write_file(&dir.join("Images/same.jpg"), b"same bytes");
+    write_file(&dir.join("other.jpg"), b"mine");
+    write_file(&dir.join("Images/other.jpg"), b"theirs");
+    write_file(&dir.join("new.jpg"), b"new");
+
+    let o = OrganizeOpts::builder().path(&dir).skip_identical(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!((stats.moved, stats.renamed, stats.duplicates), (2, 1, 1));
+    assert_eq!(stats.untouched, vec![(dir.join("same.jpg"), SkipReason::Identical)]);
+    assert!(dir.join("same.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Collapsing single-child chains
+// ══════════════════════════════════════════════
+
+#[test]
+fn collapse_lifts_a_deep_chain() {
+    let dir = tmp_dir("collapse_chain");
+    write_file(&dir.join("a/b/c/d/report.pdf"), b"x");
+    write_file(&dir.join("a/b/c/d/notes.txt"), b"x");
+    write_file(&dir.join("solo.pdf"), b"x");
+
+    let renames = collapse_single_child_dirs(&dir, 1).unwrap();
+
+    assert_eq!(renames, vec![(dir.join("a/b/c/d"), dir.join("a"))]);
+    assert_eq!(snapshot(&dir), ["a", "a/notes.txt", "a/report.pdf", "solo.pdf"].map(PathBuf::from));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn collapse_keeps_the_requested_depth_and_survives_name_clashes() {
+    let dir = tmp_dir("collapse_keep");
+    // The deepest folder holds a "b" of its own, like the folder it replaces
+    write_file(&dir.join("a/b/c/b/x.pdf"), b"x");
+    write_file(&dir.join("a/b/c/y.pdf"), b"y");
+
+    collapse_single_child_dirs(&dir, 2).unwrap();
+
+    assert_eq!(snapshot(&dir), ["a", "a/b", "a/b/b", "a/b/b/x.pdf", "a/b/y.pdf"].map(PathBuf::from));
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn collapse_chains_after_organize_keeps_undo_working() {
+    let dir = tmp_dir("collapse_organize");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("Work/2024/Q1/Final/report.pdf"), b"x");
+
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest)
+        .keep_structure(true).collapse_chains(1).top_files(1).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Documents/Work/report.pdf").exists());
+    assert!(!dir.join("Documents/Work/2024").exists());
+    assert_eq!(stats.largest[0].1, dir.join("Documents/Work/report.pdf"));
+
+    undo(&manifest, None).unwrap();
+    assert!(dir.join("Work/2024/Q1/Final/report.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Files in use
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn locked_files_are_left_and_tallied() {
+    use fs2::FileExt;
+    let dir = tmp_dir("skip_locked");
+    write_file(&dir.join("open.docx"), b"busy");
+    write_file(&dir.join("free.docx"), b"idle");
+    let holder = fs::File::open(dir.join("open.docx")).unwrap();
+    holder.lock_exclusive().unwrap();
+
+    assert!(is_file_locked(&dir.join("open.docx")));
+    assert!(!is_file_locked(&dir.join("free.docx")));
+
+    let o = OrganizeOpts::builder().path(&dir).skip_locked(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!((stats.moved, stats.locked, stats.skipped), (1, 1, 0));
+    assert_eq!(stats.untouched, vec![(dir.join("open.docx"), SkipReason::Locked)]);
+    assert!(dir.join("open.docx").exists());
+    assert!(dir.join("Documents/free.docx").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Companion files
+// ══════════════════════════════════════════════
+
+fn raw_config() -> Config {
+    let mut config = Config::default();
+    config.categories.insert("Raw".into(), vec!["cr2".into()]);
+    config.companions = vec!["xmp".into()];
+    config.reindex();
+    config
+}
+
+#[test]
+fn group_companions_pairs_sidecars_by_stem_or_full_name() {
+    let files: Vec<PathBuf> = ["a/photo.CR2", "a/photo.xmp", "a/other.CR2.XMP", "a/other.CR2", "b/photo.xmp", "a/lone.xmp"]
+        .iter().map(PathBuf::from).collect();
+
+    let (rest, grouped) = group_companions(&files, &["xmp".to_string()]);
+
+    assert_eq!(rest, ["a/photo.CR2", "a/other.CR2", "b/photo.xmp", "a/lone.xmp"].map(PathBuf::from));
+    assert_eq!(grouped[Path::new("a/photo.CR2")], [PathBuf::from("a/photo.xmp")]);
+    assert_eq!(grouped[Path::new("a/other.CR2")], [PathBuf::from("a/other.CR2.XMP")]);
+}
+
+#[test]
+fn sidecar_lands_next_to_its_main_file_with_the_same_renaming() {
+    let dir = tmp_dir("companions");
+    write_file(&dir.join("photo.CR2"), b"raw");
+    write_file(&dir.join("photo.xmp"), b"edits");
+    // The main file's name is taken, so both get the same new stem
+    write_file(&dir.join("Raw/photo.CR2"), b"older raw");
+
+    let o = OrganizeOpts::builder().path(&dir).collision_scheme(CollisionScheme::Numeric).build();
+    let stats = organize(&o, &raw_config()).unwrap();
+
+    assert_eq!((stats.moved, stats.skipped), (2, 0));
+    assert_eq!(sorted_files(&dir.join("Raw")), ["photo (1).CR2", "photo (1).xmp", "photo.CR2"]);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn sidecar_stays_when_its_main_file_does() {
+    let dir = tmp_dir("companions_stay");
+    write_file(&dir.join("photo.CR2"), b"raw");
+    write_file(&dir.join("photo.xmp"), b"edits");
+    // No category takes .CR2 files
+    let config = Config { companions: vec!["xmp".into()], ..Default::default() };
+
+    let stats = organize(&OrganizeOpts::builder().path(&dir).build(), &config).unwrap();
+
+    assert_eq!(stats.moved, 0);
+    assert!(stats.untouched.contains(&(dir.join("photo.xmp"), SkipReason::Companion)));
+    assert!(dir.join("photo.xmp").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Move scripts
+// ══════════════════════════════════════════════
+
+#[test]
+fn sh_script_quotes_paths_and_makes_each_folder_once() {
+    let plan = vec![
+        (PathBuf::from("/in/it's here.jpg"), PathBuf::from("/in/Images/it's here.jpg")),
+        (PathBuf::from("/in/$HOME `x`.png"), PathBuf::from("/in/Images/$HOME `x`.png")),
+    ];
+
+    let script = move_script(&plan, ScriptShell::Sh);
+
+    assert!(script.starts_with("#!/bin/sh\n"));
+    let commands: Vec<&str> = script.lines().filter(|l| !l.starts_with('#') && *l != "set -e").collect();
+    assert_eq!(commands, [
+        "mkdir -p '/in/Images'",
+        r"mv -n '/in/it'\''s here.jpg' '/in/Images/it'\''s here.jpg'",
+        "mv -n '/in/$HOME `x`.png' '/in/Images/$HOME `x`.png'",
+    ]);
+}
+
+#[test]
+fn cmd_script_uses_double_quotes_and_escapes_percent() {
+    // Forward slashes, so the folder splits off on every platform
+    let plan = vec![(PathBuf::from("C:/in/100% done.pdf"), PathBuf::from("C:/in/Documents/100% done.pdf"))];
+
+    let script = move_script(&plan, ScriptShell::Cmd);
+
+    assert!(script.contains("if not exist \"C:/in/Documents\" mkdir \"C:/in/Documents\"\n"));
+    assert!(script.contains("move /-Y \"C:/in/100%% done.pdf\" \"C:/in/Documents/100%% done.pdf\"\n"));
+}
+
+// ══════════════════════════════════════════════
+//  Layout pipeline
+// ══════════════════════════════════════════════
+
+fn layout(spec: &[&str]) -> Vec<LayoutStep> {
+    spec.iter().map(|s| s.parse().unwrap()).collect()
+}
+
+#[test]
+fn layout_steps_parse_and_reject_unknown_ones() {
+    assert_eq!(layout(&["category", "by_date:YearMonth"]), [LayoutStep::Category, LayoutStep::ByDate(DateDepth::YearMonth)]);
+    assert_eq!("BY_DATE:year".parse::<LayoutStep>(), Ok(LayoutStep::ByDate(DateDepth::Year)));
+    assert!("by_date:Week".parse::<LayoutStep>().is_err());
+    assert!("size".parse::<LayoutStep>().is_err());
+}
+
+#[test]
+fn layout_category_then_date_nests_dates_inside_categories() {
+    let dir = tmp_dir("layout_cat_date");
+    touch(&dir.join("IMG_20240312_101500.jpg"));
+    touch(&dir.join("2023-11-02 report.pdf"));
+
+    let o = OrganizeOpts::builder().path(&dir).layout(layout(&["category", "by_date:YearMonth"])).build();
+    organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Images/2024/03/IMG_20240312_101500.jpg").exists());
+    assert!(dir.join("Documents/2023/11/2023-11-02 report.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn layout_date_then_category_nests_categories_inside_years() {
+    let dir = tmp_dir("layout_date_cat");
+    touch(&dir.join("IMG_20240312_101500.jpg"));
+    touch(&dir.join("2023-11-02 report.pdf"));
+
+    let o = OrganizeOpts::builder().path(&dir).layout(layout(&["by_date:Year", "category"])).build();
+    organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("2024/Images/IMG_20240312_101500.jpg").exists());
+    assert!(dir.join("2023/Documents/2023-11-02 report.pdf").exists());
+    // The year folders are marked as output, so a second run leaves them be
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 0);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn layout_with_parent_and_extension_levels() {
+    let dir = tmp_dir("layout_parent_ext");
+    touch(&dir.join("Trip/beach.JPG"));
+    touch(&dir.join("top.png"));
+
+    let o = OrganizeOpts::builder().path(&dir).layout(layout(&["category", "parent", "extension"])).build();
+    organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Images/Trip/jpg/beach.JPG").exists());
+    // No parent folder for a top-level file: that level is left out
+    assert!(dir.join("Images/png/top.png").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn layout_is_read_from_config() {
+    let config: Config = toml::from_str(r#"layout = ["by_date:YearMonthDay", "category"]"#).unwrap();
+    assert_eq!(config.layout, layout(&["by_date:YearMonthDay", "category"]));
+    assert!(toml::from_str::<Config>(r#"layout = ["by_size"]"#).is_err());
+}
+
+// ══════════════════════════════════════════════
+//  Mapping files
+// ══════════════════════════════════════════════
+
+#[test]
+fn csv_mapping_overrides_the_category_of_listed_files() {
+    let dir = tmp_dir("mapping_csv");
+    let map = tmp_dir("mapping_csv_file").join("map.csv");
+    touch(&dir.join("q1/summary.pdf"));
+    touch(&dir.join("other.pdf"));
+    touch(&dir.join("LICENSE"));
+    write_file(&map, format!(
+        "path,category\n\"q1/summary.pdf\",Finance/Reports\n{},Legal\n",
+        dir.join("LICENSE").display(),
+    ).as_bytes());
+
+    let mapping = read_mapping(&map, &dir).unwrap();
+    assert_eq!(mapping[&dir.join("q1/summary.pdf")], "Finance/Reports");
+
+    let stats = organize(&OrganizeOpts::builder().path(&dir).mapping(mapping).build(), &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert!(dir.join("Finance/Reports/summary.pdf").exists());
+    // Extensionless files are sorted too when the mapping names them
+    assert!(dir.join("Legal/LICENSE").exists());
+    assert!(dir.join("Documents/other.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(map.parent().unwrap()).ok();
+}
+
+#[test]
+fn json_mapping_skips_missing_files() {
+    let dir = tmp_dir("mapping_json");
+    let map = tmp_dir("mapping_json_file").join("map.json");
+    touch(&dir.join("a, b.jpg"));
+    write_file(&map, br#"{"a, b.jpg": "Keep", "gone.jpg": "Keep"}"#);
+
+    let mapping = read_mapping(&map, &dir).unwrap();
+
+    assert_eq!(mapping.len(), 1);
+    assert_eq!(mapping[&dir.join("a, b.jpg")], "Keep");
+    fs::remove_dir_all(&dir).ok();
+    fs::remove_dir_all(map.parent().unwrap()).ok();
+}
+
+#[test]
+fn malformed_mapping_rows_are_config_errors() {
+    let dir = tmp_dir("mapping_bad");
+    write_file(&dir.join("map.csv"), b"photo.jpg\n");
+
+    let err = read_mapping(&dir.join("map.csv"), &dir).unwrap_err();
+
+    assert!(matches!(err, OrganizeError::Config { .. }), "{err}");
+    assert!(err.to_string().contains("line 1"));
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Destinations inside the source tree
+// ══════════════════════════════════════════════
+
+#[test]
+fn destination_conflict_spots_own_folder_and_escapes() {
+    let base = Path::new("/data/inbox");
+    let file = base.join("Trip/a.jpg");
+
+    assert!(destination_conflict(base, &file, &base.join("Images")).is_none());
+    assert!(destination_conflict(base, &file, &base.join("Images/Trip")).is_none());
+    assert!(destination_conflict(base, &base.join("a.jpg"), &base.join("Images")).is_none());
+    assert!(destination_conflict(base, &file, &base.join("Trip/Images")).unwrap().contains("own folder"));
+    assert!(destination_conflict(base, &file, &base.join("../Images")).unwrap().contains(".."));
+    assert!(destination_conflict(base, &file, base).is_some());
+}
+
+#[test]
+fn layouts_that_sort_into_the_files_own_folder_fail_those_files() {
+    let dir = tmp_dir("subtree_layout");
+    touch(&dir.join("Trip/a.jpg"));
+    touch(&dir.join("top.jpg"));
+
+    let steps = vec![LayoutStep::Parent, LayoutStep::Category];
+    let stats = organize(&OrganizeOpts::builder().path(&dir).layout(steps).build(), &Config::default()).unwrap();
+
+    assert_eq!((stats.moved, stats.errors), (1, 1));
+    assert!(dir.join("Trip/a.jpg").exists());
+    assert!(!dir.join("Trip").join(CATEGORY_MARKER).exists());
+    assert!(dir.join("Images/top.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn roots_that_climb_out_or_equal_the_target_are_refused() {
+    let dir = tmp_dir("subtree_root");
+    touch(&dir.join("a.jpg"));
+
+    for root in ["../out", ".", "a/../b"] {
+        let err = organize(&OrganizeOpts::builder().path(&dir).root(root).build(), &Config::default()).unwrap_err();
+        assert!(matches!(err, OrganizeError::Options(_)), "{root}: {err}");
+    }
+    assert!(dir.join("a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn absolute_root_inside_the_target_is_not_rescanned() {
+    let dir = tmp_dir("subtree_abs_root");
+    touch(&dir.join("a.jpg"));
+    let root = std::path::absolute(dir.join("sorted")).unwrap();
+    let o = OrganizeOpts::builder().path(&dir).root(&root).build();
+
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 1);
+    assert_eq!(organize(&o, &Config::default()).unwrap().moved, 0);
+    assert!(dir.join("sorted/Images/a.jpg").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Verify
+// ══════════════════════════════════════════════
+
+#[test]
+fn verify_flags_exactly_the_changed_and_missing_files() {
+    let dir = tmp_dir("verify");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("a.jpg"), b"a");
+    write_file(&dir.join("b.pdf"), b"b");
+    write_file(&dir.join("c.mp3"), b"c");
+    let o = OrganizeOpts::builder().path(&dir).manifest(&manifest).hash_log(true).build();
+    organize(&o, &Config::default()).unwrap();
+
+    write_file(&dir.join("Documents/b.pdf"), b"rotted");
+    fs::remove_file(dir.join("Music/c.mp3")).unwrap();
+    let results = verify_manifest(&manifest, None).unwrap();
+
+    let status = |p: &str| results.iter().find(|(path, _)| path.ends_with(p)).map(|(_, s)| s.clone()).unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(status("Images/a.jpg"), VerifyStatus::Intact);
+    assert!(matches!(status("Documents/b.pdf"), VerifyStatus::Mismatch { .. }));
+    assert_eq!(status("Music/c.mp3"), VerifyStatus::Missing);
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn verify_skips_entries_without_a_hash() {
+    let dir = tmp_dir("verify_nohash");
+    let manifest = dir.join(".manifest.jsonl");
+    touch(&dir.join("a.jpg"));
+    organize(&OrganizeOpts::builder().path(&dir).manifest(&manifest).build(), &Config::default()).unwrap();
+
+    assert!(verify_manifest(&manifest, None).unwrap().is_empty());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Match reasons
+// ══════════════════════════════════════════════
+
+#[test]
+fn config_explains_which_matcher_decided() {
+    let config: Config = toml::from_str(r#"
+        [categories]
+        Images = ["jpg"]
+        [name_patterns]
+        Screenshots = ["Screenshot *"]
+        [filename_categories]
+        Dockerfile = "DevOps"
+        [[rules]]
+        extensions  = ["log"]
+        destination = "Logs"
+    "#).unwrap();
+    let why = |name: &str, ext: &str| {
+        config.categorize_explained(name, ext, 1, std::time::Duration::ZERO).map(|(c, r)| format!("{c} [{r}]"))
+    };
+
+    assert_eq!(why("a.jpg", "jpg").as_deref(), Some("Images [ext:jpg]"));
+    assert_eq!(why("Screenshot 1.jpg", "jpg").as_deref(), Some("Screenshots [name:Screenshot *]"));
+    assert_eq!(why("Dockerfile", "").as_deref(), Some("DevOps [file:Dockerfile]"));
+    assert_eq!(why("app.log", "log").as_deref(), Some("Logs [rule:1]"));
+    assert_eq!(why("a.xyz", "xyz"), None);
+}
+
+#[test]
+fn moved_events_carry_the_reason_for_each_match_kind() {
+    let dir = tmp_dir("match_reasons");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("scan.tiff"));
+    touch(&dir.join("mapped.pdf"));
+    touch(&dir.join("Trip/notes.txt"));
+    touch(&dir.join("blob.psd"));
+    let mapping = std::collections::HashMap::from([(dir.join("mapped.pdf"), "Keep".to_string())]);
+    let o = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true)
+        .mime_fallback(true).mapping(mapping).by_parent(true).group_unknown(true).build();
+
+    let mut reasons = std::collections::HashMap::new();
+    organize_with_events(&o, &Config::default(), &mut |event| {
+        if let OrganizeEvent::Moved { from, reason, .. } = event {
+            reasons.insert(from.file_name().unwrap().to_string_lossy().into_owned(), reason.unwrap().to_string());
+        }
+    }).unwrap();
+
+    assert_eq!(reasons["a.jpg"], "ext:jpg");
+    assert_eq!(reasons["scan.tiff"], "mime:image/tiff");
+    assert_eq!(reasons["mapped.pdf"], "mapping");
+    assert_eq!(reasons["notes.txt"], "parent");
+    assert_eq!(reasons["blob.psd"], "unknown:psd");
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Archiving old files
+// ══════════════════════════════════════════════
+
+/// Backdate a file's modification time by `days`
+fn age_file(path: &Path, days: u64) {
+    let then = std::time::SystemTime::now() - std::time::Duration::from_secs(days * 24 * 3600);
+    fs::File::options().write(true).open(path).unwrap().set_modified(then).unwrap();
+}
+
+#[test]
+fn parse_duration_units() {
+    assert_eq!(parse_duration("90d"), Ok(std::time::Duration::from_secs(90 * 86400)));
+    assert_eq!(parse_duration("2w"), parse_duration("14"));
+    assert_eq!(parse_duration("1Y"), Ok(std::time::Duration::from_secs(365 * 86400)));
+    assert!(parse_duration("3q").is_err());
+    assert!(parse_duration("d").is_err());
+}
+
+#[test]
+fn old_files_are_zipped_per_category_and_year_then_removed() {
+    let dir = tmp_dir("archive_old");
+    write_file(&dir.join("old/report.pdf"), b"quarterly numbers");
+    write_file(&dir.join("fresh.pdf"), b"new");
+    age_file(&dir.join("old/report.pdf"), 400);
+    let year = chrono::Datelike::year(&chrono::DateTime::<Local>::from(
+        fs::metadata(dir.join("old/report.pdf")).unwrap().modified().unwrap(),
+    ));
+
+    let o = OrganizeOpts::builder().path(&dir).archive_older_than(parse_duration("1y").unwrap()).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!((stats.archived, stats.moved), (1, 1));
+    assert!(!dir.join("old/report.pdf").exists());
+    assert!(dir.join("Documents/fresh.pdf").exists());
+    let zip_path = dir.join(format!("Archive/Documents_{year}.zip"));
+    let mut zip = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+    let mut contents = String::new();
+    std::io::Read::read_to_string(&mut zip.by_name("old/report.pdf").unwrap(), &mut contents).unwrap();
+    assert_eq!(contents, "quarterly numbers");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn appending_to_an_archive_keeps_existing_entries_and_renames_clashes() {
+    let dir = tmp_dir("archive_append");
+    let zip_path = dir.join("Archive/Images_2020.zip");
+    write_file(&dir.join("a.jpg"), b"first");
+    append_to_zip(&zip_path, &[(dir.join("a.jpg"), "a.jpg".into())]).unwrap();
+    write_file(&dir.join("a.jpg"), b"second");
+
+    let names = append_to_zip(&zip_path, &[(dir.join("a.jpg"), "a.jpg".into())]).unwrap();
+
+    assert_eq!(names, ["a (2).jpg"]);
+    let zip = zip::ZipArchive::new(fs::File::open(&zip_path).unwrap()).unwrap();
+    let mut all: Vec<&str> = zip.file_names().collect();
+    all.sort();
+    assert_eq!(all, ["a (2).jpg", "a.jpg"]);
+    assert!(!dir.join("Archive/Images_2020.zip.part").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn failed_archive_write_keeps_the_originals() {
+    let dir = tmp_dir("archive_fail");
+    write_file(&dir.join("old.pdf"), b"x");
+    age_file(&dir.join("old.pdf"), 400);
+    // A corrupt archive where the new one would go can't be appended to
+    let year = chrono::Datelike::year(&chrono::DateTime::<Local>::from(
+        fs::metadata(dir.join("old.pdf")).unwrap().modified().unwrap(),
+    ));
+    write_file(&dir.join(format!("Archive/Documents_{year}.zip")), b"not a zip");
+
+    let o = OrganizeOpts::builder().path(&dir).archive_older_than(parse_duration("30d").unwrap()).quiet(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!((stats.archived, stats.errors), (0, 1));
+    assert!(dir.join("old.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn archiving_a_big_batch_needs_a_go_ahead() {
+    let dir = tmp_dir("archive_confirm");
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        write_file(&dir.join(name), b"old");
+        age_file(&dir.join(name), 400);
+    }
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).archive_older_than(parse_duration("30d").unwrap())
+        .delete_policy(DeletePolicy { max_files: 2, ..DeletePolicy::default() })
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    // Refused: nothing is packed, so nothing is lost or packed twice later
+    assert_eq!((stats.archived, stats.errors), (0, 3));
+    assert!(dir.join("a.pdf").exists());
+    assert!(!dir.join("Archive").exists());
+
+    let yes = DeletePolicy { assume_yes: true, ..o.delete_policy.clone() };
+    let stats = organize(&OrganizeOpts { delete_policy: yes, ..o }, &Config::default()).unwrap();
+    assert_eq!((stats.archived, stats.errors), (3, 0));
+    assert!(!dir.join("a.pdf").exists());
+    fs::remove_dir_all(&dir).ok();
+}
+
+// ══════════════════════════════════════════════
+//  Case-folded categories
+// ══════════════════════════════════════════════
+
+#[test]
+fn categories_differing_by_case_merge_on_load() {
+    let dir = tmp_dir("case_fold");
+    let path = dir.join("config.toml");
+    write_file(&path, br#"
+        case_fold_categories = true
+        [categories]
+        Images = ["jpg", "png"]
+        images = ["png", "heic"]
+        [filename_categories]
+        cover = "IMAGES"
+    "#);
+
+    let config = Config::load_from(&path).unwrap();
+
+    let mut exts = config.categories["Images"].clone();
+    exts.sort();
+    assert_eq!(exts, ["heic", "jpg", "png"]);
+    assert!(!config.categories.contains_key("images"));
+    assert_eq!(config.categorize("heic"), Some("Images"));
+    assert_eq!(config.filename_categories["cover"], "Images");
+    fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn folding_reports_each_merge() {
+    let mut config = Config::default();
+    config.categories.insert("images".into(), vec!["heic".into()]);
+    config.categories.insert("MUSIC".into(), vec!["opus".into()]);
+
+    let warnings = config.fold_category_case();
+
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings.iter().any(|w| w.contains("\"images\"") && w.contains("\"Images\"")));
+    // Upper case sorts first, so MUSIC is the spelling kept
+    assert!(config.categories.contains_key("MUSIC") && !config.categories.contains_key("Music"));
+    assert_eq!(config.categorize("mp3"), Some("MUSIC"));
+}
+
+// ══════════════════════════════════════════════
+//  Disk-backed duplicate detection
+// ══════════════════════════════════════════════
+
+#[test]
+fn seen_store_moves_to_disk_past_the_threshold() {
+    assert!(!SeenStore::for_run(10, 10).unwrap().is_on_disk());
+    assert!(SeenStore::for_run(11, 10).unwrap().is_on_disk());
+    // 0 keeps everything in memory however big the run
+    assert!(!SeenStore::for_run(1_000_000, 0).unwrap().is_on_disk());
+}
+
+#[test]
+fn disk_seen_remembers_the_first_file_and_cleans_up() {
+    let mut seen = DiskSeen::new().unwrap();
+    let dir = seen.dir().to_path_buf();
+
+    assert_eq!(seen.first_or_insert("a|1", Path::new("x/a 'one'.txt")).unwrap(), None);
+    assert_eq!(seen.first_or_insert("b|1", Path::new("x/b.txt")).unwrap(), None);
+    assert_eq!(
+        seen.first_or_insert("a|1", Path::new("y/a.txt")).unwrap(),
+        Some(PathBuf::from("x/a 'one'.txt"))
+    );
+    assert!(dir.is_dir());
+
+    drop(seen);
+    assert!(!dir.exists());
+}
+
+#[test]
+fn duplicates_found_with_fingerprints_on_disk() {
+    let dir = tmp_dir("dup_disk");
+    write_file(&dir.join("a/photo.jpg"), b"identical");
+    write_file(&dir.join("b/photo.jpg"), b"identical");
+    write_file(&dir.join("c/other.jpg"), b"different");
+
+    let mut o = opts(&dir);
+    o.find_duplicates = true;
+    o.dedupe_disk_threshold = 1;
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.duplicates, 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Relative date buckets
+// ══════════════════════════════════════════════
+
+#[test]
+fn age_buckets_at_their_boundaries() {
+    let now = std::time::SystemTime::UNIX_EPOCH + 1000 * DAY;
+    let buckets = default_age_buckets();
+    let bucket = |age: std::time::Duration| age_bucket(now, now - age, &buckets);
+
+    assert_eq!(bucket(std::time::Duration::ZERO), "Recent");
+    assert_eq!(bucket(7 * DAY), "Recent");
+    assert_eq!(bucket(7 * DAY + std::time::Duration::from_secs(1)), "ThisMonth");
+    assert_eq!(bucket(30 * DAY), "ThisMonth");
+    assert_eq!(bucket(30 * DAY + std::time::Duration::from_secs(1)), "ThisYear");
+    assert_eq!(bucket(365 * DAY), "ThisYear");
+    assert_eq!(bucket(365 * DAY + std::time::Duration::from_secs(1)), "Older");
+    // Modified after `now` (clock skew) counts as brand new
+    assert_eq!(age_bucket(now, now + DAY, &buckets), "Recent");
+}
+
+#[test]
+fn age_buckets_from_config() {
+    let cfg: Config = toml::from_str(r#"
+        age_buckets = [
+            { name = "Today", within = "24h" },
+            { name = "Quarter", within = "3m" },
+        ]
+    "#).unwrap();
+    let now = std::time::SystemTime::UNIX_EPOCH + 1000 * DAY;
+
+    assert_eq!(age_bucket(now, now - DAY, &cfg.age_buckets), "Today");
+    assert_eq!(age_bucket(now, now - 90 * DAY, &cfg.age_buckets), "Quarter");
+    // Past the last limit, with no catch-all bucket configured
+    assert_eq!(age_bucket(now, now - 91 * DAY, &cfg.age_buckets), "Older");
+
+    assert_eq!(toml::from_str::<Config>("").unwrap().age_buckets, default_age_buckets());
+    assert!(toml::from_str::<Config>(r#"age_buckets = [{ name = "X", within = "soon" }]"#).is_err());
+}
+
+#[test]
+fn age_bucket_layout_step() {
+    let dir = tmp_dir("age_bucket");
+    touch(&dir.join("new.jpg"));
+    touch(&dir.join("old.pdf"));
+    age_file(&dir.join("old.pdf"), 400);
+
+    let mut o = opts(&dir);
+    o.layout = "age_bucket,category".split(',').map(|s| s.parse().unwrap()).collect();
+    organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Recent/Images/new.jpg").exists());
+    assert!(dir.join("Older/Documents/old.pdf").exists());
+    assert_eq!(LayoutStep::AgeBucket.to_string(), "age_bucket");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Rules scripts
+// ══════════════════════════════════════════════
+
+#[test]
+fn rules_script_routes_by_size() {
+    let dir = tmp_dir("rules_script");
+    let script = dir.with_extension("rhai");
+    fs::write(&script, r#"if size > 1000 { "Large" } else if ext == "log" { "" }"#).unwrap();
+    write_file(&dir.join("big.jpg"), &[0; 2000]);
+    write_file(&dir.join("small.jpg"), b"tiny");
+    write_file(&dir.join("app.log"), b"kept");
+
+    let config = Config::default();
+    let categorizer = ScriptCategorizer::load(&script, &config).unwrap();
+    organize(&opts(&dir), &categorizer).unwrap();
+
+    assert!(dir.join("Large/big.jpg").exists());
+    // No answer from the script: the config decides
+    assert!(dir.join("Images/small.jpg").exists());
+    // "" leaves the file alone
+    assert!(dir.join("app.log").exists());
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&script);
+}
+
+#[test]
+fn rules_script_errors_fall_back_to_config() {
+    let dir = tmp_dir("rules_script_err");
+    let script = dir.with_extension("rhai");
+    // A runaway loop is cut off; an unknown variable is an error
+    fs::write(&script, r#"if ext == "jpg" { loop {} } else { undefined_var }"#).unwrap();
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.pdf"));
+
+    let config = Config::default();
+    let categorizer = ScriptCategorizer::load(&script, &config).unwrap();
+    organize(&opts(&dir), &categorizer).unwrap();
+
+    assert!(dir.join("Images/a.jpg").exists());
+    assert!(dir.join("Documents/b.pdf").exists());
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&script);
+}
+
+#[test]
+fn rules_script_syntax_errors_are_reported_up_front() {
+    let dir = tmp_dir("rules_script_bad");
+    let script = dir.join("rules.rhai");
+    fs::write(&script, "if size > {").unwrap();
+
+    let config = Config::default();
+    assert!(matches!(ScriptCategorizer::load(&script, &config), Err(OrganizeError::Config { .. })));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Printing the effective config
+// ══════════════════════════════════════════════
+
+#[test]
+fn printed_config_reflects_category_exclusion() {
+    let mut config = Config::default();
+    config.exclude_categories(&["Images".to_string()]);
+
+    let text = config.render(ConfigFormat::Toml).unwrap();
+    assert!(text.contains(r#"excluded = ["Images"]"#));
+
+    let printed: Config = toml::from_str(&text).unwrap();
+    assert!(!printed.categories.contains_key("Images"));
+    assert!(printed.categories.contains_key("Documents"));
+    // A record of the run, not something a file can switch on
+    assert!(printed.excluded.is_empty());
+}
+
+#[test]
+fn printed_config_loads_back_in_every_format() {
+    let dir = tmp_dir("print_config");
+    let config = Config {
+        layout: vec![LayoutStep::ByDate(DateDepth::Year), LayoutStep::Category],
+        rules: vec![Rule { destination: "Big".into(), min_size: Some(1 << 20), ..Default::default() }],
+        ..Default::default()
+    };
+
+    for (format, ext) in [(ConfigFormat::Toml, "toml"), (ConfigFormat::Json, "json"), (ConfigFormat::Yaml, "yaml")] {
+        let path = dir.join(format!("config.{ext}"));
+        fs::write(&path, config.render(format).unwrap()).unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+
+        assert_eq!(loaded.layout, config.layout, "{ext}");
+        assert_eq!(loaded.age_buckets, default_age_buckets(), "{ext}");
+        assert_eq!(loaded.rules[0].min_size, Some(1 << 20), "{ext}");
+        assert_eq!(loaded.categorize("jpg"), Some("Images"), "{ext}");
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn format_duration_round_trips() {
+    for text in ["1y", "6m", "2w", "3d", "12h"] {
+        assert_eq!(format_duration(parse_duration(text).unwrap()), text);
+    }
+    assert_eq!(format_duration(parse_duration("7d").unwrap()), "1w");
+}
+
+// ══════════════════════════════════════════════
+//  Durable moves (--fsync)
+// ══════════════════════════════════════════════
+
+#[test]
+fn fsync_moves_files_into_new_folders() {
+    let dir = tmp_dir("fsync");
+    write_file(&dir.join("photo.jpg"), b"jpeg");
+    write_file(&dir.join("Work/report.pdf"), b"pdf");
+
+    let o = OrganizeOpts::builder().path(&dir).fsync(true).keep_structure(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 2);
+    assert_eq!(fs::read(dir.join("Images/photo.jpg")).unwrap(), b"jpeg");
+    assert_eq!(fs::read(dir.join("Documents/Work/report.pdf")).unwrap(), b"pdf");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn move_file_durable_hashes_on_request() {
+    let dir = tmp_dir("fsync_hash");
+    write_file(&dir.join("a.txt"), b"hello");
+
+    let hash = move_file_durable(&dir.join("a.txt"), &dir.join("b.txt"), true, true).unwrap();
+    assert_eq!(hash.as_deref(), Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"));
+    assert_eq!(move_file_durable(&dir.join("b.txt"), &dir.join("c.txt"), false, false).unwrap(), None);
+
+    assert!(!dir.join("a.txt").exists() && !dir.join("b.txt").exists());
+    assert_eq!(fs::read(dir.join("c.txt")).unwrap(), b"hello");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Quiet period
+// ══════════════════════════════════════════════
+
+#[test]
+fn quiet_period_boundary() {
+    let now = std::time::SystemTime::UNIX_EPOCH + 1000 * DAY;
+    let second = std::time::Duration::from_secs(1);
+
+    assert!(in_quiet_period(now, now, DAY));
+    assert!(in_quiet_period(now, now - DAY + second, DAY));
+    // Exactly one period old: sorted
+    assert!(!in_quiet_period(now, now - DAY, DAY));
+    assert!(!in_quiet_period(now, now - 30 * DAY, DAY));
+    // A future timestamp is as recent as it gets
+    assert!(in_quiet_period(now, now + second, DAY));
+}
+
+#[test]
+fn quiet_period_leaves_recent_files() {
+    let dir = tmp_dir("quiet_period");
+    touch(&dir.join("fresh.jpg"));
+    touch(&dir.join("old.jpg"));
+    age_file(&dir.join("old.jpg"), 2);
+
+    let o = OrganizeOpts::builder().path(&dir).quiet_period(DAY).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("fresh.jpg").exists());
+    assert!(dir.join("Images/old.jpg").exists());
+    assert_eq!(stats.recent, 1);
+    assert_eq!(stats.untouched, [(dir.join("fresh.jpg"), SkipReason::TooRecent)]);
+    assert_eq!(SkipReason::TooRecent.code(), "too-recent");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Image dimensions
+// ══════════════════════════════════════════════
+
+/// Write a blank picture of the given size; the format follows the extension
+fn write_image(path: &Path, width: u32, height: u32) {
+    if let Some(p) = path.parent() {
+        fs::create_dir_all(p).unwrap();
+    }
+    image::RgbImage::new(width, height).save(path).unwrap();
+}
+
+#[test]
+fn image_split_by_orientation() {
+    let dir = tmp_dir("image_orientation");
+    write_image(&dir.join("wide.png"), 40, 20);
+    write_image(&dir.join("tall.jpg"), 20, 40);
+    write_image(&dir.join("square.png"), 16, 16);
+    write_file(&dir.join("broken.png"), b"not a png at all");
+
+    let o = OrganizeOpts::builder().path(&dir).image_split(ImageSplit::Orientation).build();
+    organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Images/Landscape/wide.png").exists());
+    assert!(dir.join("Images/Portrait/tall.jpg").exists());
+    assert!(dir.join("Images/Square/square.png").exists());
+    // Unreadable header: plain category
+    assert!(dir.join("Images/broken.png").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn image_dimensions_from_header() {
+    let dir = tmp_dir("image_dimensions");
+    write_image(&dir.join("a.png"), 30, 10);
+
+    assert_eq!(image_dimensions(&dir.join("a.png"), "png"), Some((30, 10)));
+    // Not a format the image crate reads
+    fs::copy(dir.join("a.png"), dir.join("a.psd")).unwrap();
+    assert_eq!(image_dimensions(&dir.join("a.psd"), "psd"), None);
+    assert_eq!(image_dimensions(&dir.join("missing.png"), "png"), None);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn image_resolution_tiers() {
+    assert_eq!(image_subfolder(ImageSplit::Resolution, 3840, 2160), "4K");
+    assert_eq!(image_subfolder(ImageSplit::Resolution, 2160, 3840), "4K");
+    assert_eq!(image_subfolder(ImageSplit::Resolution, 3839, 2160), "1080p");
+    assert_eq!(image_subfolder(ImageSplit::Resolution, 1920, 1080), "1080p");
+    assert_eq!(image_subfolder(ImageSplit::Resolution, 1919, 1080), "Small");
+    assert_eq!(image_subfolder(ImageSplit::Orientation, 1, 1), "Square");
+}
+
+// ══════════════════════════════════════════════
+//  Applying picked categories
+// ══════════════════════════════════════════════
+
+#[test]
+fn plan_grouped_by_category_then_partly_applied() {
+    let dir = tmp_dir("pick_categories");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.png"));
+    touch(&dir.join("c.pdf"));
+    touch(&dir.join("d.mp3"));
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).layout(vec![LayoutStep::Category, LayoutStep::Extension]).build();
+    let plan = plan_moves(&o, &Config::default()).unwrap();
+    assert_eq!(
+        plan_by_category(&o, &plan),
+        [("Documents".to_string(), 1), ("Images".to_string(), 2), ("Music".to_string(), 1)]
+    );
+
+    let picked = moves_in_categories(&o, &plan, &["images".to_string(), "Music".to_string()]);
+    let stats = apply_plan(&o, &picked).unwrap();
+
+    assert_eq!(stats.moved, 3);
+    assert!(dir.join("Images/jpg/a.jpg").exists());
+    assert!(dir.join("Images/png/b.png").exists());
+    assert!(dir.join("Music/mp3/d.mp3").exists());
+    assert!(dir.join("c.pdf").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn plan_categories_sit_below_the_root() {
+    let dir = tmp_dir("pick_categories_root");
+    touch(&dir.join("a.jpg"));
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).root("Sorted").build();
+    let plan = plan_moves(&o, &Config::default()).unwrap();
+
+    assert_eq!(plan_by_category(&o, &plan), [("Images".to_string(), 1)]);
+    assert_eq!(moves_in_categories(&o, &plan, &["Images".to_string()]), plan);
+    assert!(moves_in_categories(&o, &plan, &["Sorted".to_string()]).is_empty());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Deep trees
+// ══════════════════════════════════════════════
+
+#[test]
+fn collect_files_survives_very_deep_trees() {
+    let dir = tmp_dir("deep_tree");
+    // As deep as the path length limit allows with one-letter folders
+    let depth = 1500;
+    let deepest = (0..depth).fold(dir.clone(), |p, _| p.join("d"));
+    write_file(&deepest.join("bottom.txt"), b"");
+    touch(&dir.join("top.txt"));
+    touch(&dir.join("d/d/.hidden"));
+
+    // A small stack, which a walk recursing once per level would overflow
+    let walk = dir.clone();
+    let files = std::thread::Builder::new()
+        .stack_size(128 * 1024)
+        .spawn(move || collect_files(&walk, &[]).unwrap())
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let mut files: Vec<_> = files.iter().map(|p| p.strip_prefix(&dir).unwrap().components().count()).collect();
+    files.sort();
+    assert_eq!(files, [1, depth + 1]);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Space saved by skipping duplicates
+// ══════════════════════════════════════════════
+
+#[test]
+fn duplicate_bytes_sum_the_skipped_copies() {
+    let dir = tmp_dir("dup_bytes");
+    write_file(&dir.join("a/photo.jpg"), &[1; 300]);
+    write_file(&dir.join("b/photo.jpg"), &[1; 300]);
+    write_file(&dir.join("c/photo.jpg"), &[1; 300]);
+    write_file(&dir.join("a/notes.pdf"), &[2; 50]);
+    write_file(&dir.join("b/notes.pdf"), &[2; 50]);
+    write_file(&dir.join("unique.txt"), &[3; 999]);
+
+    let o = OrganizeOpts::builder().path(&dir).find_duplicates(true).dup_strategy(DuplicateStrategy::NameSize).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.duplicates, 3);
+    assert_eq!(stats.duplicate_bytes, 2 * 300 + 50);
+    assert_eq!(format_bytes(stats.duplicate_bytes), "650 B");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Dry-run as a check
+// ══════════════════════════════════════════════
+
+#[test]
+fn dry_run_reports_whether_anything_would_change() {
+    let dir = tmp_dir("dry_run_check");
+    touch(&dir.join("a.jpg"));
+    let o = OrganizeOpts::builder().path(&dir).dry_run(true).quiet(true).build();
+
+    // Something to move: the CLI exits nonzero
+    assert!(organize(&o, &Config::default()).unwrap().has_changes());
+
+    organize(&OrganizeOpts { dry_run: false, ..o.clone() }, &Config::default()).unwrap();
+    touch(&dir.join("notes.unknownext"));
+    // Already sorted, and nothing else it would touch: exit 0
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert!(!stats.has_changes());
+    assert_eq!(stats.skipped, 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Snapshots and restore
+// ══════════════════════════════════════════════
+
+#[test]
+fn restore_puts_the_snapshot_layout_back_after_several_runs() {
+    let dir = tmp_dir("snapshot_restore");
+    let manifest = dir.with_extension("jsonl");
+    let _ = fs::remove_file(&manifest);
+    let index = dir.join("before.json");
+    write_file(&dir.join("a.jpg"), b"jpeg");
+    write_file(&dir.join("Work/report.pdf"), b"report");
+    write_file(&dir.join("Work/2024/notes.txt"), b"notes");
+    write_file(&dir.join(".hidden"), b"");
+    age_file(&dir.join("a.jpg"), 3);
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).manifest(&manifest).snapshot(&index).keep_structure(true)
+        .build();
+    organize(&o, &Config::default()).unwrap();
+    let snapshot = read_snapshot(&index).unwrap();
+    assert_eq!(snapshot.files.len(), 4);
+    assert!(dir.join("Documents/Work/2024/notes.txt").exists());
+
+    // A second run: the snapshot stays as it was, and isn't sorted itself
+    touch(&dir.join("later.png"));
+    organize(&o, &Config::default()).unwrap();
+    assert_eq!(read_snapshot(&index).unwrap(), snapshot);
+    assert!(dir.join("before.json").exists());
+
+    let stats = restore_snapshot(&index, &manifest).unwrap();
+    assert_eq!(stats.moved, 3);
+    assert_eq!(stats.errors, 0);
+
+    // Same files, sizes and times as before; the file added later stays
+    // sorted, in the one category folder still in use
+    let mut now = take_snapshot(&dir, Some(&index)).unwrap().files;
+    now.retain(|f| !f.path.starts_with(dir.join("Images")));
+    assert_eq!(now, snapshot.files);
+    assert!(!dir.join("Documents").exists());
+    assert!(dir.join("Images/later.png").exists());
+
+    // Running it again changes nothing
+    assert_eq!(restore_snapshot(&index, &manifest).unwrap().moved, 0);
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&manifest);
+}
+
+#[test]
+fn restore_leaves_a_taken_original_path() {
+    let dir = tmp_dir("snapshot_taken");
+    let manifest = dir.with_extension("jsonl");
+    let _ = fs::remove_file(&manifest);
+    let index = dir.with_extension("json");
+    let _ = fs::remove_file(&index);
+    write_file(&dir.join("a.jpg"), b"first");
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).manifest(&manifest).snapshot(&index).build();
+    organize(&o, &Config::default()).unwrap();
+    write_file(&dir.join("a.jpg"), b"someone else");
+
+    let stats = restore_snapshot(&index, &manifest).unwrap();
+    assert_eq!((stats.moved, stats.errors), (0, 1));
+    assert_eq!(fs::read(dir.join("Images/a.jpg")).unwrap(), b"first");
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_file(&manifest);
+    let _ = fs::remove_file(&index);
+}
+
+// ══════════════════════════════════════════════
+//  Minimum files per new folder
+// ══════════════════════════════════════════════
+
+#[test]
+fn min_files_to_create_folder_leaves_a_lone_category_in_place() {
+    let dir = tmp_dir("min_files_folder");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.png"));
+    touch(&dir.join("song.flac"));
+
+    let o = OrganizeOpts { min_files_to_create_folder: 2, ..opts(&dir) };
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.moved, stats.sparse), (2, 1));
+    assert!(dir.join("Images/a.jpg").exists());
+    assert!(dir.join("song.flac").exists());
+    assert!(!dir.join("Music").exists());
+    assert!(stats.untouched.contains(&(dir.join("song.flac"), SkipReason::SparseCategory)));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn min_files_to_create_folder_still_fills_an_existing_folder() {
+    let dir = tmp_dir("min_files_existing");
+    fs::create_dir_all(dir.join("Music")).unwrap();
+    touch(&dir.join("song.flac"));
+
+    let o = OrganizeOpts { min_files_to_create_folder: 5, ..opts(&dir) };
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.moved, stats.sparse), (1, 0));
+    assert!(dir.join("Music/song.flac").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+/// Config, counting how often it is asked
+struct Counted(std::sync::atomic::AtomicUsize);
+
+impl Categorizer for Counted {
+    fn category_for(&self, path: &Path, ext: &str, meta: &fs::Metadata) -> Option<String> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Config::default().category_for(path, ext, meta)
+    }
+}
+
+#[test]
+fn min_files_to_create_folder_categorizes_each_file_once() {
+    let dir = tmp_dir("min_files_once");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("song.flac"));
+    touch(&dir.join("b.png"));
+    touch(&dir.join("c.gif"));
+
+    let counted = Counted(Default::default());
+    let o = OrganizeOpts { min_files_to_create_folder: 3, ..opts(&dir) };
+    let stats = organize(&o, &counted).unwrap();
+
+    assert_eq!(counted.0.into_inner(), 4);
+    // The images waited for the third one, then all went
+    assert_eq!((stats.moved, stats.sparse), (3, 1));
+    assert!(dir.join("Images/a.jpg").exists());
+    assert!(dir.join("song.flac").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Rename templates
+// ══════════════════════════════════════════════
+
+/// photo.JPG from 2024-03-07, the 2nd file into Images
+fn rename_sample() -> RenameFile<'static> {
+    RenameFile {
+        stem: "photo", ext: "JPG", date: chrono::NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+        category: "Images", counter: 2,
+    }
+}
+
+fn render(template: &str, file: &RenameFile) -> String {
+    template.parse::<RenameTemplate>().unwrap().render(file)
+}
+
+#[test]
+fn rename_template_fills_each_placeholder() {
+    let file = rename_sample();
+    assert_eq!(render("{stem}.{ext}", &file), "photo.JPG");
+    assert_eq!(render("{date}_{stem}", &file), "2024-03-07_photo.JPG");
+    assert_eq!(render("{category}-{stem}", &file), "Images-photo.JPG");
+    assert_eq!(render("{stem}_{counter}", &file), "photo_2.JPG");
+    assert_eq!(render("{ext}_{stem}", &file), "JPG_photo.JPG");
+}
+
+#[test]
+fn rename_template_keeps_names_valid_for_later_runs() {
+    let file = rename_sample();
+    // The extension is kept whatever its case, and not doubled
+    assert_eq!(render("{stem}.jpg", &file), "photo.jpg");
+    assert_eq!(render("{stem}.", &file), "photo.JPG");
+    // Nested categories can't add folders
+    let nested = RenameFile { category: "Other/psd", ..rename_sample() };
+    assert_eq!(render("{category}_{stem}", &nested), "Other_psd_photo.JPG");
+    // Nothing left but dots: the old stem
+    let bare = RenameFile { stem: "Dockerfile", ext: "", ..rename_sample() };
+    assert_eq!(render("{stem}.{ext}", &bare), "Dockerfile");
+    assert_eq!(render("{ext}", &bare), "Dockerfile");
+}
+
+#[test]
+fn rename_template_rejects_bad_templates() {
+    for bad in ["", "{name}", "{stem", "stem}", "a}{stem}", "{date}/{stem}", ".{stem}"] {
+        assert!(bad.parse::<RenameTemplate>().is_err(), "{bad:?} was accepted");
+    }
+    let err = "{size}".parse::<RenameTemplate>().unwrap_err();
+    assert!(err.contains("{size}") && err.contains("{counter}"), "{err}");
+}
+
+#[test]
+fn rename_template_names_moved_files_before_collisions() {
+    let dir = tmp_dir("rename_template");
+    write_file(&dir.join("2024-03-07 scan.pdf"), b"one");
+    write_file(&dir.join("a/photo.jpg"), b"two");
+    write_file(&dir.join("b/photo.jpg"), b"three");
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None)
+        .rename_template("{category}_{counter}".parse().unwrap())
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!(stats.moved, 3);
+    assert_eq!(fs::read(dir.join("Documents/Documents_1.pdf")).unwrap(), b"one");
+    assert!(dir.join("Images/Images_1.jpg").exists());
+    assert!(dir.join("Images/Images_2.jpg").exists());
+
+    // {date} comes from the name; two files rendering the same name are
+    // told apart like any other clash
+    let dir2 = tmp_dir("rename_template_date");
+    write_file(&dir2.join("2024-03-07 scan.pdf"), b"one");
+    write_file(&dir2.join("2024-03-07 receipt.pdf"), b"two");
+    let o = OrganizeOpts { path: dir2.clone(), rename_template: Some("{date}".parse().unwrap()), ..o };
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.moved, stats.renamed), (2, 1));
+    assert!(dir2.join("Documents/2024-03-07.pdf").exists());
+    assert_eq!(fs::read_dir(dir2.join("Documents")).unwrap().count(), 3);
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&dir2);
+}
+
+// ══════════════════════════════════════════════
+//  Globs in categories
+// ══════════════════════════════════════════════
+
+#[test]
+fn category_entries_can_be_extensions_or_globs() {
+    let dir = tmp_dir("category_globs");
+    let path = dir.join("config.toml");
+    fs::write(&path, r#"
+        [categories]
+        Images      = ["jpg", "png"]
+        Screenshots = ["Screenshot *", "png_never"]
+        Backups     = ["*.backup", "old"]
+    "#).unwrap();
+    let cfg = Config::load_from(&path).unwrap();
+
+    // A bare word is an extension
+    assert_eq!(cfg.categorize_file("photo.png", "png"), Some("Images"));
+    assert_eq!(cfg.categorize_file("notes.old", "old"), Some("Backups"));
+    // Anything with a wildcard or a dot is a glob on the whole name, and it
+    // beats the extension
+    assert_eq!(cfg.categorize_file("Screenshot 2024-03-07.png", "png"), Some("Screenshots"));
+    assert_eq!(cfg.categorize_file("screenshot 1.JPG", "jpg"), Some("Screenshots"));
+    assert_eq!(cfg.categorize_file("db.sqlite.backup", "backup"), Some("Backups"));
+    // Globs don't leak into the extension lookup
+    assert_eq!(cfg.categorize("backup"), None);
+    assert!(is_category_glob("*.backup") && is_category_glob("a.b") && !is_category_glob("jpg"));
+
+    touch(&dir.join("Screenshot 1.png"));
+    touch(&dir.join("cat.png"));
+    touch(&dir.join("site.backup"));
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).build();
+    let stats = organize(&o, &cfg).unwrap();
+    assert_eq!(stats.moved, 3);
+    assert!(dir.join("Screenshots/Screenshot 1.png").exists());
+    assert!(dir.join("Images/cat.png").exists());
+    assert!(dir.join("Backups/site.backup").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Special files
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn pipes_and_sockets_are_skipped_not_moved() {
+    let dir = tmp_dir("special_files");
+    let fifo = dir.join("feed.txt");
+    assert!(std::process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+    let socket = dir.join("agent.pdf");
+    let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+    touch(&dir.join("a.jpg"));
+
+    // Content hashing would block forever reading the pipe
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None)
+        .find_duplicates(true).dup_strategy(DuplicateStrategy::Content)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.moved, stats.skipped, stats.errors), (1, 2, 0));
+    assert!(stats.untouched.contains(&(fifo.clone(), SkipReason::Special)));
+    assert!(stats.untouched.contains(&(socket.clone(), SkipReason::Special)));
+    assert_eq!(SkipReason::Special.code(), "special-file");
+    assert!(fs::symlink_metadata(&fifo).is_ok());
+    assert!(!dir.join("Documents").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Keep newest N per extension
+// ══════════════════════════════════════════════
+
+/// day1.log … day7.log, day7 the newest
+fn seven_logs(dir: &Path) {
+    for day in 1..=7 {
+        let path = dir.join(format!("day{day}.log"));
+        touch(&path);
+        age_file(&path, 10 - day);
+    }
+}
+
+#[test]
+fn keep_newest_removes_exactly_the_oldest_files() {
+    let dir = tmp_dir("keep_newest");
+    seven_logs(&dir);
+    touch(&dir.join("a.jpg"));
+
+    // A preview removes nothing
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).keep_newest(5).keep_newest_action(RetentionAction::Delete)
+        .build();
+    let preview = organize(&OrganizeOpts { dry_run: true, ..o.clone() }, &Config::default()).unwrap();
+    assert_eq!(preview.pruned, 2);
+    assert!(preview.has_changes());
+    assert!(dir.join("day1.log").exists());
+
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.pruned, stats.moved, stats.errors), (2, 1, 0));
+    assert!(!dir.join("day1.log").exists());
+    assert!(!dir.join("day2.log").exists());
+    for day in 3..=7 {
+        assert!(dir.join(format!("day{day}.log")).exists(), "day{day}.log was removed");
+    }
+    // Only one .jpg, well under the count
+    assert!(dir.join("Images/a.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn keep_newest_asks_before_deleting_a_big_batch() {
+    let dir = tmp_dir("keep_newest_confirm");
+    seven_logs(&dir);
+
+    // Over the policy's limit and nobody said yes: nothing is deleted
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).keep_newest(0).keep_newest_action(RetentionAction::Delete)
+        .delete_policy(DeletePolicy { max_files: 3, ..DeletePolicy::default() })
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.pruned, stats.errors), (0, 7));
+    assert!(stats.failures[0].1.contains("not confirmed"), "{:?}", stats.failures);
+    for day in 1..=7 {
+        assert!(dir.join(format!("day{day}.log")).exists());
+    }
+
+    // Once confirmed, they all go
+    let stats = organize(&OrganizeOpts { confirm_delete: |_| true, ..o }, &Config::default()).unwrap();
+    assert_eq!((stats.pruned, stats.errors), (7, 0));
+    assert!(!dir.join("day7.log").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn keep_newest_never_removes_files_the_run_leaves_alone() {
+    let dir = tmp_dir("keep_newest_eligible");
+    for name in ["a.pdf", "b.pdf", "c.pdf", "old.jpg"] {
+        touch(&dir.join(name));
+        age_file(&dir.join(name), 5);
+    }
+    touch(&dir.join("new.jpg"));
+    // --only-category Images: pdfs are excluded for this run
+    let mut config = Config::default();
+    config.only_categories(&["Images".to_string()]);
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).keep_newest(0).keep_newest_action(RetentionAction::Delete)
+        .quiet_period(DAY).confirm_delete(|_| true)
+        .build();
+    let stats = organize(&o, &config).unwrap();
+
+    assert_eq!(stats.pruned, 1);
+    assert!(!dir.join("old.jpg").exists());
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        assert!(dir.join(name).exists());
+        assert!(stats.untouched.contains(&(dir.join(name), SkipReason::Excluded)));
+    }
+    // Too recent to be sorted, so too recent to be pruned
+    assert!(dir.join("new.jpg").exists());
+    assert!(stats.untouched.contains(&(dir.join("new.jpg"), SkipReason::TooRecent)));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn keep_newest_per_extension_can_archive_the_rest() {
+    let dir = tmp_dir("keep_newest_archive");
+    seven_logs(&dir);
+    for name in ["a.jpg", "b.jpg", "c.jpg"] {
+        touch(&dir.join(name));
+    }
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None)
+        .keep_newest_per_ext(std::collections::HashMap::from([("log".to_string(), 6)]))
+        .keep_newest_action(RetentionAction::Archive)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    // Only .log has a count; every .jpg is sorted as usual
+    assert_eq!((stats.archived, stats.pruned, stats.moved), (1, 0, 3));
+    assert!(!dir.join("day1.log").exists());
+    assert!(dir.join("day2.log").exists());
+    let zips: Vec<_> = fs::read_dir(dir.join("Archive")).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.starts_with("log_") && n.ends_with(".zip"))
+        .collect();
+    assert_eq!(zips.len(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Layered configs
+// ══════════════════════════════════════════════
+
+#[test]
+fn a_later_config_adds_to_an_existing_category() {
+    let dir = tmp_dir("cfg_layered");
+    let base = dir.join("base.toml");
+    let mine = dir.join("mine.yaml");
+    fs::write(&base, r#"
+        [categories]
+        Images = ["jpg", "png"]
+        Papers = ["pdf"]
+
+        [filename_categories]
+        Makefile = "Build"
+        Dockerfile = "Build"
+
+        [[rules]]
+        extensions  = ["pdf"]
+        destination = "Base"
+    "#).unwrap();
+    fs::write(&mine, "
+categories:
+  Images: [heic, .PNG]
+  Code: [rs]
+filename_categories:
+  Makefile: DevOps
+rules:
+  - extensions: [pdf]
+    destination: Mine
+").unwrap();
+
+    let cfg = Config::load_layered(&[base.clone(), mine.clone()]).unwrap();
+    // Augmented, not replaced; the repeated png appears once
+    assert_eq!(cfg.categories["Images"], ["jpg", "png", "heic"]);
+    assert_eq!(cfg.categorize("jpg"), Some("Images"));
+    assert_eq!(cfg.categorize("heic"), Some("Images"));
+    assert_eq!(cfg.categorize("pdf"), Some("Papers"));
+    assert_eq!(cfg.categorize("rs"), Some("Code"));
+    // The base replaced the default categories, so there are no others
+    assert_eq!(cfg.categorize("mp4"), None);
+    // Tables go key by key, and the later rules come first
+    assert_eq!(cfg.filename_category("Makefile"), Some("DevOps"));
+    assert_eq!(cfg.filename_category("Dockerfile"), Some("Build"));
+    assert_eq!(cfg.categorize_with_rules("a.pdf", "pdf", 1, std::time::Duration::ZERO), Some("Mine"));
+
+    // In the other order, base's rule wins
+    let reversed = Config::load_layered(&[mine, base]).unwrap();
+    assert_eq!(reversed.categories["Images"], ["heic", "png", "jpg"]);
+    assert_eq!(reversed.categorize_with_rules("a.pdf", "pdf", 1, std::time::Duration::ZERO), Some("Base"));
+
+    // What --print-config shows loads back the same
+    let printed = dir.join("printed.toml");
+    fs::write(&printed, cfg.render(ConfigFormat::Toml).unwrap()).unwrap();
+    assert_eq!(Config::load_from(&printed).unwrap().categories, cfg.categories);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn layered_configs_keep_defaults_no_file_sets() {
+    let dir = tmp_dir("cfg_layered_defaults");
+    let a = dir.join("a.toml");
+    let b = dir.join("b.json");
+    fs::write(&a, "layout = [\"category\", \"extension\"]\n").unwrap();
+    fs::write(&b, r#"{ "layout": ["extension"], "name_patterns": { "Finance": ["invoice_*"] } }"#).unwrap();
+
+    let cfg = Config::load_layered(&[a, b]).unwrap();
+    assert_eq!(cfg.categorize("mp4"), Some("Videos"));
+    assert_eq!(cfg.layout, [LayoutStep::Extension]);
+    assert_eq!(cfg.match_name("invoice_7.pdf"), Some("Finance"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Destination volumes and name sanitizing
+// ══════════════════════════════════════════════
+
+#[test]
+fn sanitize_filename_maps_illegal_characters() {
+    assert_eq!(sanitize_filename("meeting 10:30.txt"), "meeting 10-30.txt");
+    assert_eq!(sanitize_filename("a|b.txt"), "a-b.txt");
+    assert_eq!(sanitize_filename("\"quoted\".pdf"), "'quoted'.pdf");
+    assert_eq!(sanitize_filename("<draft>.doc"), "(draft).doc");
+    assert_eq!(sanitize_filename("what?*.png"), "what__.png");
+    assert_eq!(sanitize_filename("back\\slash.md"), "back_slash.md");
+    assert_eq!(sanitize_filename("tab\there.txt"), "tab_here.txt");
+    // Names that are already fine are untouched
+    assert_eq!(sanitize_filename("Résumé (final) #2.pdf"), "Résumé (final) #2.pdf");
+}
+
+#[test]
+fn sanitize_filename_handles_windows_name_rules() {
+    assert_eq!(sanitize_filename("notes. "), "notes");
+    assert_eq!(sanitize_filename("..."), "_");
+    assert_eq!(sanitize_filename("CON.txt"), "CON_.txt");
+    assert_eq!(sanitize_filename("lpt1"), "lpt1_");
+    assert_eq!(sanitize_filename("console.txt"), "console.txt");
+
+    let long = format!("{}.jpeg", "é".repeat(200));
+    let short = sanitize_filename(&long);
+    assert!(short.len() <= MAX_NAME_BYTES, "{} bytes", short.len());
+    assert!(short.ends_with("é.jpeg"));
+}
+
+#[test]
+fn mount_table_gives_the_innermost_filesystem() {
+    let table = "\
+22 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw
+40 22 8:17 / /media/usb\\040stick rw,relatime - vfat /dev/sdb1 rw
+41 22 8:33 / /media/usb rw,relatime - ntfs3 /dev/sdc1 rw
+";
+    let fs = |p: &str| filesystem_from_mountinfo(table, Path::new(p));
+    assert_eq!(fs("/home/me/Downloads").as_deref(), Some("ext4"));
+    assert_eq!(fs("/media/usb stick/Images").as_deref(), Some("vfat"));
+    assert_eq!(fs("/media/usb/Images").as_deref(), Some("ntfs3"));
+    assert_eq!(fs("/media/usbx").as_deref(), Some("ext4"));
+
+    let vfat = VolumeInfo::for_fs_type("vfat");
+    assert!(vfat.windows_names && vfat.case_insensitive);
+    let ntfs = VolumeInfo::for_fs_type("ntfs3");
+    assert!(ntfs.windows_names && !ntfs.case_insensitive);
+    assert!(!VolumeInfo::for_fs_type("ext4").windows_names);
+}
+
+#[cfg(unix)]
+#[test]
+fn sanitize_names_fits_destination_names() {
+    let dir = tmp_dir("sanitize_names");
+    touch(&dir.join("meeting 10:30.txt"));
+    write_file(&dir.join("Q3: plans/a?.pdf"), b"x");
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).keep_structure(true).sanitize_names(true)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+    assert_eq!((stats.moved, stats.errors), (2, 0));
+    assert!(dir.join("Documents/meeting 10-30.txt").exists());
+    assert!(dir.join("Documents/Q3- plans/a_.pdf").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Collecting extensionless files
+// ══════════════════════════════════════════════
+
+#[test]
+fn collect_extensionless_gathers_files_without_an_extension() {
+    let dir = tmp_dir("collect_extensionless");
+    touch(&dir.join("Makefile"));
+    touch(&dir.join("src/LICENSE"));
+    touch(&dir.join("Dockerfile"));
+    touch(&dir.join(".envrc"));
+    touch(&dir.join("a.jpg"));
+    let cfg = Config {
+        filename_categories: std::collections::HashMap::from([("Dockerfile".into(), "DevOps".into())]),
+        ..Config::default()
+    };
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).collect_extensionless(EXTENSIONLESS_ROOT)
+        .build();
+    let stats = organize(&o, &cfg).unwrap();
+    assert_eq!(stats.moved, 4);
+    assert!(dir.join("NoExtension/Makefile").exists());
+    assert!(dir.join("NoExtension/LICENSE").exists());
+    // A known name keeps its own category, and hidden files stay put
+    assert!(dir.join("DevOps/Dockerfile").exists());
+    assert!(dir.join(".envrc").exists());
+
+    // The folder is sorted output from now on
+    touch(&dir.join("README"));
+    let o = OrganizeOpts { collect_extensionless: Some("Loose".into()), ..o };
+    organize(&o, &cfg).unwrap();
+    assert!(dir.join("Loose/README").exists());
+    assert!(dir.join("NoExtension/Makefile").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Hashing progress and cancellation
+// ══════════════════════════════════════════════
+
+#[test]
+fn content_hashing_reports_bytes_and_still_finds_copies() {
+    let dir = tmp_dir("hashing_progress");
+    write_file(&dir.join("a.jpg"), b"same contents");
+    write_file(&dir.join("renamed.jpg"), b"same contents");
+    write_file(&dir.join("other.jpg"), b"other contents");
+    write_file(&dir.join("lone.jpg"), b"a size nobody else has");
+
+    let mut progress = Vec::new();
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None)
+        .find_duplicates(true).dup_strategy(DuplicateStrategy::Content)
+        .build();
+    let stats = organize_with_events(&o, &Config::default(), &mut |e| {
+        if let OrganizeEvent::Hashing { done, total } = e { progress.push((done, total)); }
+    }).unwrap();
+
+    // Only the two 13-byte files share a size; the others are never read
+    assert_eq!(progress.first(), Some(&(0, 26)));
+    assert_eq!(progress.last(), Some(&(26, 26)));
+    assert_eq!(stats.hashed_bytes, 26);
+    assert_eq!(stats.duplicates, 1);
+    assert_eq!(stats.moved, 3);
+    assert!(!stats.cancelled);
+    assert!(dir.join("renamed.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cancelling_while_hashing_stops_before_any_move() {
+    let dir = tmp_dir("hashing_cancel");
+    let big = vec![7u8; 3 << 20];
+    for name in ["a.mp4", "b.mp4", "c.mp4", "d.mp4"] {
+        write_file(&dir.join(name), &big);
+    }
+
+    let cancel = CancelToken::new();
+    let mut hashing = 0;
+    let mut finished = None;
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None)
+        .find_duplicates(true).dup_strategy(DuplicateStrategy::Content)
+        .cancel(cancel.clone())
+        .build();
+    let stats = organize_with_events(&o, &Config::default(), &mut |e| match e {
+        OrganizeEvent::Hashing { done, .. } => {
+            hashing += 1;
+            if done > 0 { cancel.cancel(); }
+        }
+        OrganizeEvent::Finished { stats } => finished = Some(stats),
+        _ => {}
+    }).unwrap();
+
+    // Stopped after the first chunk instead of reading all 12 MiB
+    assert!(stats.cancelled);
+    assert_eq!(hashing, 2);
+    assert!(stats.hashed_bytes > 0 && stats.hashed_bytes < 12 << 20, "{}", stats.hashed_bytes);
+    assert_eq!(stats.moved, 0);
+    assert_eq!(stats.duplicates, 0);
+    assert!(finished.is_some_and(|f| f.cancelled));
+    for name in ["a.mp4", "b.mp4", "c.mp4", "d.mp4"] {
+        assert!(dir.join(name).exists());
+    }
+    assert!(!dir.join("Videos").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cancelled_token_stops_between_files() {
+    let dir = tmp_dir("cancel_between_files");
+    touch(&dir.join("a.jpg"));
+    touch(&dir.join("b.jpg"));
+
+    let cancel = CancelToken::new();
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).cancel(cancel.clone()).build();
+    let stats = organize_with_events(&o, &Config::default(), &mut |e| {
+        if matches!(e, OrganizeEvent::Moved { .. }) { cancel.cancel(); }
+    }).unwrap();
+
+    assert!(stats.cancelled);
+    assert_eq!(stats.moved, 1);
+    // Whichever came first moved; the other was never reached
+    let moved = |name: &str| dir.join("Images").join(name).exists();
+    assert!(moved("a.jpg") != moved("b.jpg"));
+    assert!(dir.join("a.jpg").exists() != dir.join("b.jpg").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Explaining one file
+// ══════════════════════════════════════════════
+
+#[test]
+fn explain_traces_the_rule_that_placed_a_file() {
+    let dir = tmp_dir("explain_rule");
+    write_file(&dir.join("report.PDF"), &[0u8; 2048]);
+    let cfg = Config {
+        rules: vec![
+            Rule { destination: "Tiny".into(), max_size: Some(100), ..Rule::default() },
+            Rule { destination: "Archive/BigDocs".into(), extensions: vec!["pdf".into()], min_size: Some(1024), ..Rule::default() },
+        ],
+        ..Config::default()
+    };
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).build();
+    let explained = explain_file(&o, &cfg, &dir.join("report.PDF")).unwrap();
+    let steps = explained.steps.join("\n");
+
+    assert!(steps.contains(r#"extension: "pdf" (written "PDF")"#), "{steps}");
+    assert!(steps.contains("rule 1 (Tiny): no match, fails max_size"), "{steps}");
+    assert!(steps.contains("rule 2 (Archive/BigDocs): matches"), "{steps}");
+    assert!(steps.contains("category: Archive/BigDocs [rule:2]"), "{steps}");
+    assert_eq!(explained.matched, Some(("Archive/BigDocs".into(), MatchReason::Rule(2))));
+    assert_eq!(explained.outcome, FileOutcome::MovesTo(dir.join("Archive/BigDocs/report.PDF")));
+    // Nothing moved
+    assert!(dir.join("report.PDF").exists());
+    assert!(!dir.join("Archive").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn explain_says_why_a_file_stays() {
+    let dir = tmp_dir("explain_unmatched");
+    touch(&dir.join("notes.weird"));
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).find_duplicates(true).build();
+    let explained = explain_file(&o, &Config::default(), &dir.join("notes.weird")).unwrap();
+    let steps = explained.steps.join("\n");
+
+    assert!(steps.contains(r#"categories: no category lists "weird""#), "{steps}");
+    assert!(steps.contains("not checked, as they depend on the other files: duplicates"), "{steps}");
+    assert_eq!(explained.matched, None);
+    assert_eq!(explained.outcome, FileOutcome::Stays(SkipReason::Unmatched));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Leaving symlinks behind
+// ══════════════════════════════════════════════
+
+#[cfg(unix)]
+#[test]
+fn leave_symlink_keeps_the_old_path_working() {
+    let dir = tmp_dir("leave_symlink");
+    let manifest = dir.join(".manifest.jsonl");
+    write_file(&dir.join("Work/report.pdf"), b"quarterly");
+
+    let o = OrganizeOpts::builder()
+        .path(&dir).quiet(true).log_file(None).manifest(&manifest).leave_symlink(true)
+        .build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert_eq!(stats.moved, 1);
+    assert_eq!(stats.errors, 0);
+    let old = dir.join("Work/report.pdf");
+    assert!(fs::symlink_metadata(&old).unwrap().file_type().is_symlink());
+    assert!(fs::symlink_metadata(dir.join("Documents/report.pdf")).unwrap().is_file());
+    // Relative, and it resolves to the moved file
+    assert_eq!(fs::read_link(&old).unwrap(), Path::new("../Documents/report.pdf"));
+    assert_eq!(fs::canonicalize(&old).unwrap(), fs::canonicalize(dir.join("Documents/report.pdf")).unwrap());
+    assert_eq!(fs::read(&old).unwrap(), b"quarterly");
+
+    // The next run leaves the link where it is
+    let again = organize(&o, &Config::default()).unwrap();
+    assert_eq!(again.moved, 0);
+    assert_eq!(again.untouched, vec![(old.clone(), SkipReason::Symlink)]);
+
+    // Undo puts the file itself back in place of the link
+    undo(&manifest, None).unwrap();
+    assert!(fs::symlink_metadata(&old).unwrap().is_file());
+    assert_eq!(fs::read(&old).unwrap(), b"quarterly");
+    assert!(!dir.join("Documents/report.pdf").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// ══════════════════════════════════════════════
+//  Duplicates before collisions
+// ══════════════════════════════════════════════
+
+#[test]
+fn duplicates_are_caught_before_collision_renaming() {
+    let dir = tmp_dir("dupes_before_collisions");
+    write_file(&dir.join("a/photo.jpg"), b"the same shot");
+    write_file(&dir.join("b/photo.jpg"), b"the same shot");
+    write_file(&dir.join("c/photo.jpg"), b"a different, longer shot");
+
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).find_duplicates(true).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    // Whatever order they come in: one plain move, one dated rename for
+    // the different file, and the copy left as a duplicate
+    assert_eq!(stats.moved, 2);
+    assert_eq!(stats.renamed, 1);
+    assert_eq!(stats.duplicates, 1);
+    let mut names: Vec<String> = fs::read_dir(dir.join("Images")).unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|n| n.ends_with(".jpg"))
+        .collect();
+    names.sort();
+    assert_eq!(names.len(), 2, "{names:?}");
+    assert_eq!(names[0], "photo.jpg");
+    assert!(names[1].starts_with("photo_") && names[1].ends_with(".jpg"), "{names:?}");
+    let contents: Vec<Vec<u8>> = names.iter().map(|n| fs::read(dir.join("Images").join(n)).unwrap()).collect();
+    assert!(contents.contains(&b"the same shot".to_vec()));
+    assert!(contents.contains(&b"a different, longer shot".to_vec()));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_into_the_target_are_left_on_every_run() {
+    let dir = tmp_dir("symlink_skip");
+    let outside = tmp_dir("symlink_skip_outside");
+    write_file(&dir.join("real.pdf"), b"x");
+    write_file(&outside.join("elsewhere.jpg"), b"y");
+    std::os::unix::fs::symlink("real.pdf", dir.join("alias.pdf")).unwrap();
+    std::os::unix::fs::symlink(outside.join("elsewhere.jpg"), dir.join("external.jpg")).unwrap();
+
+    // No --leave-symlink: the link into the target still stays put
+    let o = OrganizeOpts::builder().path(&dir).quiet(true).log_file(None).build();
+    let stats = organize(&o, &Config::default()).unwrap();
+
+    assert!(dir.join("Documents/real.pdf").exists());
+    assert!(fs::symlink_metadata(dir.join("alias.pdf")).unwrap().file_type().is_symlink());
+    assert!(stats.untouched.contains(&(dir.join("alias.pdf"), SkipReason::Symlink)));
+    // A link to a file outside the target is sorted like before
+    assert!(fs::symlink_metadata(dir.join("Images/external.jpg")).unwrap().file_type().is_symlink());
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&outside);
+}